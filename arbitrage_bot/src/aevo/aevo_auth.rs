@@ -0,0 +1,138 @@
+use std::fmt;
+
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+
+pub const AEVO_API_KEY_ENV: &str = "AEVO_API_KEY";
+pub const AEVO_API_SECRET_ENV: &str = "AEVO_API_SECRET";
+
+#[derive(Debug, Error)]
+pub enum AEVOAuthError {
+    #[error("AEVO credential {0} is not configured")]
+    MissingCredential(&'static str),
+    #[error("AEVO rejected authentication: {0}")]
+    Rejected(String),
+    #[error("AEVO closed connection before acknowledging authentication")]
+    NoAcknowledgement,
+    #[error("Unexpected AEVO authentication response: {0}")]
+    UnexpectedResponse(String),
+    #[error("Invalid AEVO header value")]
+    InvalidHeader(#[from] reqwest::header::InvalidHeaderValue),
+}
+
+/// AEVO API credentials
+///
+/// Secret never appears in `Debug` output and credentials can not be serialized,
+/// so they can not leak into logs or written files
+#[derive(Clone, Deserialize)]
+pub struct AEVOCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+impl fmt::Debug for AEVOCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AEVOCredentials")
+            .field("api_key", &self.api_key)
+            .field("api_secret", &"<redacted>")
+            .finish()
+    }
+}
+
+impl AEVOCredentials {
+    pub fn new(api_key: String, api_secret: String) -> Self {
+        Self {
+            api_key,
+            api_secret,
+        }
+    }
+
+    ///Reads credentials from `AEVO_API_KEY` and `AEVO_API_SECRET`
+    pub fn from_env() -> Result<Self, AEVOAuthError> {
+        let read = |name: &'static str| {
+            std::env::var(name)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .ok_or(AEVOAuthError::MissingCredential(name))
+        };
+
+        Ok(Self::new(
+            read(AEVO_API_KEY_ENV)?,
+            read(AEVO_API_SECRET_ENV)?,
+        ))
+    }
+
+    /// HMAC-SHA256 signature of a REST request
+    ///
+    /// Signed message is `key,timestamp,METHOD,path,body`, signature is hex encoded
+    pub fn sign(&self, timestamp: u128, method: &str, path: &str, body: &str) -> String {
+        let message = format!(
+            "{},{timestamp},{},{path},{body}",
+            self.api_key,
+            method.to_uppercase()
+        );
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.api_secret.as_bytes())
+            .expect("HMAC accepts keys of any size");
+        mac.update(message.as_bytes());
+
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    ///Headers authenticating REST request to AEVO
+    pub fn rest_headers(
+        &self,
+        timestamp: u128,
+        method: &str,
+        path: &str,
+        body: &str,
+    ) -> Result<HeaderMap, AEVOAuthError> {
+        let mut headers = HeaderMap::new();
+
+        headers.insert("AEVO-KEY", HeaderValue::from_str(&self.api_key)?);
+        headers.insert(
+            "AEVO-TIMESTAMP",
+            HeaderValue::from_str(&timestamp.to_string())?,
+        );
+        headers.insert(
+            "AEVO-SIGNATURE",
+            HeaderValue::from_str(&self.sign(timestamp, method, path, body))?,
+        );
+
+        Ok(headers)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthResultAEVO {
+    pub success: bool,
+}
+
+///Acknowledgement of `auth` operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthResponseAEVO {
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(default)]
+    pub data: Option<AuthResultAEVO>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl AuthResponseAEVO {
+    pub fn into_result(self) -> Result<(), AEVOAuthError> {
+        match (self.data, self.error) {
+            (_, Some(error)) => Err(AEVOAuthError::Rejected(error)),
+            (Some(AuthResultAEVO { success: true }), None) => Ok(()),
+            (Some(AuthResultAEVO { success: false }), None) => {
+                Err(AEVOAuthError::Rejected("success is false".to_string()))
+            }
+            (None, None) => Err(AEVOAuthError::UnexpectedResponse(
+                "neither data nor error present".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::{fmt, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use thiserror::Error;
+
+use super::aevo_structs::ChannelsResponseAEVO;
+
+///Prefix of orderbook channels in `channels` response
+pub const ORDERBOOK_CHANNEL_PREFIX: &str = "orderbook:";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AEVOInstrumentError {
+    #[error("Invalid AEVO instrument symbol {0:?}")]
+    InvalidSymbol(String),
+    #[error("AEVO instrument {symbol} is not available, orderbook channels: {available:?}")]
+    NotFound {
+        symbol: String,
+        available: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OptionTypeAEVO {
+    Call,
+    Put,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstrumentKindAEVO {
+    Perpetual,
+    Future {
+        expiry: NaiveDate,
+    },
+    Option {
+        expiry: NaiveDate,
+        strike: Decimal,
+        option_type: OptionTypeAEVO,
+    },
+}
+
+/// AEVO instrument parsed from its symbol
+///
+/// Symbols look like `ETH-PERP`, `ETH-30JUN23` or `ETH-30JUN23-1600-C`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstrumentAEVO {
+    pub symbol: String,
+    pub underlying: String,
+    pub kind: InstrumentKindAEVO,
+}
+
+impl InstrumentAEVO {
+    pub fn is_perpetual(&self) -> bool {
+        self.kind == InstrumentKindAEVO::Perpetual
+    }
+
+    ///Channel carrying orderbook of the instrument
+    pub fn orderbook_channel(&self) -> String {
+        format!("{ORDERBOOK_CHANNEL_PREFIX}{}", self.symbol)
+    }
+}
+
+impl fmt::Display for InstrumentAEVO {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.symbol)
+    }
+}
+
+fn parse_expiry(symbol: &str, expiry: &str) -> Result<NaiveDate, AEVOInstrumentError> {
+    NaiveDate::parse_from_str(expiry, "%d%b%y")
+        .map_err(|_| AEVOInstrumentError::InvalidSymbol(symbol.to_string()))
+}
+
+impl FromStr for InstrumentAEVO {
+    type Err = AEVOInstrumentError;
+
+    fn from_str(symbol: &str) -> Result<Self, Self::Err> {
+        let invalid = || AEVOInstrumentError::InvalidSymbol(symbol.to_string());
+        let parts: Vec<&str> = symbol.split('-').collect();
+
+        let kind = match parts.as_slice() {
+            [_, "PERP"] => InstrumentKindAEVO::Perpetual,
+            [_, expiry] => InstrumentKindAEVO::Future {
+                expiry: parse_expiry(symbol, expiry)?,
+            },
+            [_, expiry, strike, option_type] => InstrumentKindAEVO::Option {
+                expiry: parse_expiry(symbol, expiry)?,
+                strike: Decimal::from_str(strike).map_err(|_| invalid())?,
+                option_type: match *option_type {
+                    "C" => OptionTypeAEVO::Call,
+                    "P" => OptionTypeAEVO::Put,
+                    _ => return Err(invalid()),
+                },
+            },
+            _ => return Err(invalid()),
+        };
+
+        let underlying = parts[0];
+        if underlying.is_empty() || !underlying.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            symbol: symbol.to_string(),
+            underlying: underlying.to_string(),
+            kind,
+        })
+    }
+}
+
+impl ChannelsResponseAEVO {
+    ///Instruments which have orderbook channel, unparsable channels are skipped
+    pub fn orderbook_instruments(&self) -> Vec<InstrumentAEVO> {
+        self.data
+            .iter()
+            .filter_map(|channel| channel.strip_prefix(ORDERBOOK_CHANNEL_PREFIX))
+            .filter_map(|symbol| symbol.parse().ok())
+            .collect()
+    }
+
+    ///Finds orderbook channel of exactly the requested instrument
+    pub fn find_orderbook_channel(&self, symbol: &str) -> Result<String, AEVOInstrumentError> {
+        let requested: InstrumentAEVO = symbol.parse()?;
+
+        self.orderbook_instruments()
+            .into_iter()
+            .find(|instrument| *instrument == requested)
+            .map(|instrument| instrument.orderbook_channel())
+            .ok_or_else(|| AEVOInstrumentError::NotFound {
+                symbol: symbol.to_string(),
+                available: self
+                    .data
+                    .iter()
+                    .filter(|channel| channel.starts_with(ORDERBOOK_CHANNEL_PREFIX))
+                    .cloned()
+                    .collect(),
+            })
+    }
+}
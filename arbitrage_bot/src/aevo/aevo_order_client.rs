@@ -0,0 +1,314 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use anyhow::{ensure, Context, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use log::debug;
+use reqwest::{Client, Method};
+use rust_decimal::Decimal;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{sync::Mutex, time::timeout};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    config::AEVOConfig,
+    feed_source::FeedSource,
+    instrument::InstrumentSpec,
+    order::{OrderClient, OrderReport, OrderRequest, TimeInForce},
+    venue::{Side, Venue},
+};
+
+use super::{
+    aevo_auth::AEVOCredentials,
+    aevo_order_structs::{
+        to_fixed, CancelOrderDataAEVO, ErrorResponseAEVO, InstrumentResponseAEVO, OrderPayloadAEVO,
+        OrderRequestWSAEVO, OrderResponseAEVO, ResponseWSAEVO,
+    },
+    aevo_orderbook_feed::AEVOWSAuthenticator,
+    aevo_signer::{parse_address, AEVOSigner, OrderSignatureDataAEVO},
+};
+
+///Time to wait for acknowledgement of a websocket order request
+const ORDER_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum AEVOOrderError {
+    #[error("AEVO rejected order request: {0}")]
+    Rejected(String),
+    #[error("AEVO closed connection before acknowledging order request")]
+    NoAcknowledgement,
+    #[error("AEVO account is not configured")]
+    MissingAccount,
+}
+
+///Channel used to send order requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderTransportAEVO {
+    #[default]
+    Rest,
+    ///`create_order` and `cancel_order` ops on authenticated websocket
+    Websocket,
+}
+
+/// Places orders on AEVO
+///
+/// Orders are signed with account signing key, requests are authenticated with
+/// API credentials of `AEVOWSAuthenticator`. Acknowledgements carry filled amount
+/// and average price, so IOC fills are known as soon as order is placed.
+#[derive(Debug)]
+pub struct AEVOOrderClient {
+    http: Client,
+    rest_url: String,
+    credentials: AEVOCredentials,
+    signer: AEVOSigner,
+    account: String,
+    instrument_id: String,
+    spec: InstrumentSpec,
+    websocket: Option<Mutex<FeedSource>>,
+    next_request_id: AtomicU64,
+}
+
+impl AEVOOrderClient {
+    ///Resolves traded instrument and opens websocket when it is the configured transport
+    pub async fn connect(
+        auth: &AEVOWSAuthenticator,
+        config: &AEVOConfig,
+        signer: AEVOSigner,
+    ) -> Result<Self> {
+        let account = config.account()?;
+        parse_address(&account).context("aevo.account")?;
+
+        let mut client = Self {
+            http: Client::new(),
+            rest_url: config.rest_url.trim_end_matches('/').to_string(),
+            credentials: auth.credentials().clone(),
+            signer,
+            account,
+            instrument_id: String::new(),
+            spec: config.spec(),
+            websocket: None,
+            next_request_id: AtomicU64::new(1),
+        };
+
+        let instrument: InstrumentResponseAEVO = client
+            .rest(
+                Method::GET,
+                &format!("/instrument/{}", config.instrument),
+                None,
+            )
+            .await?;
+        client.instrument_id = instrument.instrument_id;
+
+        if config.order_transport == OrderTransportAEVO::Websocket {
+            client.websocket = Some(Mutex::new(auth.authenticate().await?));
+        }
+
+        debug!(
+            "AEVO order client for {} on {} ({}), signer {}",
+            client.account,
+            instrument.instrument_name,
+            client.instrument_id,
+            client.signer.address()
+        );
+
+        Ok(client)
+    }
+
+    /// Builds signed order as it is sent to AEVO
+    ///
+    /// Limit price is rounded to a tick, up for buys and down for sells, amount down to a step
+    pub fn build_order(&self, request: &OrderRequest) -> Result<OrderPayloadAEVO> {
+        let limit_price = match request.side {
+            Side::Buy => self.spec.ceil_price(request.price),
+            Side::Sell => self.spec.floor_price(request.price),
+        };
+        let amount = self.spec.floor_size(request.quantity);
+        ensure!(
+            amount > Decimal::ZERO,
+            "Quantity {} is below AEVO step size {}",
+            request.quantity,
+            self.spec.step_size
+        );
+
+        let now = Utc::now();
+        let signature_data = OrderSignatureDataAEVO {
+            maker: parse_address(&self.account)?,
+            is_buy: request.side == Side::Buy,
+            limit_price: to_fixed(limit_price)?,
+            amount: to_fixed(amount)?,
+            //Salt makes repeated orders with same parameters distinct
+            salt: now.timestamp_subsec_nanos() as u128 ^ request.client_id as u128,
+            instrument: self
+                .instrument_id
+                .parse()
+                .context("Invalid AEVO instrument id")?,
+            timestamp: now.timestamp() as u128,
+        };
+
+        Ok(OrderPayloadAEVO {
+            instrument: self.instrument_id.clone(),
+            maker: self.account.clone(),
+            is_buy: signature_data.is_buy,
+            amount: signature_data.amount.to_string(),
+            limit_price: signature_data.limit_price.to_string(),
+            salt: signature_data.salt.to_string(),
+            signature: self.signer.sign_order(&signature_data)?,
+            timestamp: signature_data.timestamp.to_string(),
+            post_only: false,
+            reduce_only: request.reduce_only,
+            time_in_force: match request.time_in_force {
+                TimeInForce::Ioc => "IOC",
+                TimeInForce::Gtc => "GTC",
+            }
+            .to_string(),
+        })
+    }
+
+    ///Authenticated REST request, error bodies are turned into `Rejected`
+    async fn rest<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<String>,
+    ) -> Result<T> {
+        let body = body.unwrap_or_default();
+        let timestamp = Utc::now()
+            .timestamp_nanos_opt()
+            .context("System time out of range")? as u128;
+        let headers = self
+            .credentials
+            .rest_headers(timestamp, method.as_str(), path, &body)?;
+
+        let response = self
+            .http
+            .request(method, format!("{}{path}", self.rest_url))
+            .headers(headers)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("AEVO request to {path} failed"))?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            let error = serde_json::from_str::<ErrorResponseAEVO>(&text)
+                .map(|error| error.error)
+                .unwrap_or(format!("{status} {text}"));
+            return Err(AEVOOrderError::Rejected(error).into());
+        }
+
+        response
+            .json()
+            .await
+            .with_context(|| format!("Invalid AEVO response from {path}"))
+    }
+
+    ///Sends websocket op and waits for response with the same id
+    async fn websocket_request<T: Serialize, R: DeserializeOwned>(
+        &self,
+        websocket: &Mutex<FeedSource>,
+        op: &str,
+        data: T,
+    ) -> Result<R> {
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        let request = OrderRequestWSAEVO::new(op, id, data);
+
+        let mut websocket = websocket.lock().await;
+        websocket
+            .send(Message::Text(serde_json::to_string(&request)?))
+            .await?;
+
+        loop {
+            let message = timeout(ORDER_ACK_TIMEOUT, websocket.next_frame())
+                .await
+                .with_context(|| format!("No AEVO {op} acknowledgement in {ORDER_ACK_TIMEOUT:?}"))?
+                .ok_or(AEVOOrderError::NoAcknowledgement)??;
+
+            match message {
+                Message::Text(text) => {
+                    //Channel messages may arrive before acknowledgement
+                    let Ok(response) = serde_json::from_str::<ResponseWSAEVO>(&text) else {
+                        continue;
+                    };
+                    if response.id != Some(id) {
+                        continue;
+                    }
+                    if let Some(error) = response.error {
+                        return Err(AEVOOrderError::Rejected(error).into());
+                    }
+                    let data = response.data.ok_or(AEVOOrderError::NoAcknowledgement)?;
+                    return serde_json::from_value(data)
+                        .with_context(|| format!("Invalid AEVO {op} acknowledgement"));
+                }
+                Message::Ping(_) | Message::Pong(_) => {}
+                _ => return Err(AEVOOrderError::NoAcknowledgement.into()),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl OrderClient for AEVOOrderClient {
+    fn venue(&self) -> Venue {
+        Venue::Aevo
+    }
+
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderReport> {
+        let payload = self.build_order(request)?;
+
+        let response: OrderResponseAEVO = match &self.websocket {
+            Some(websocket) => {
+                self.websocket_request(websocket, "create_order", payload)
+                    .await
+            }
+            None => {
+                self.rest(
+                    Method::POST,
+                    "/orders",
+                    Some(serde_json::to_string(&payload)?),
+                )
+                .await
+            }
+        }
+        .with_context(|| format!("Failed to place AEVO order {}", request.client_id))?;
+
+        response.to_report(request.client_id, request.time_in_force)
+    }
+
+    async fn cancel_order(&self, order: &OrderReport) -> Result<()> {
+        match &self.websocket {
+            Some(websocket) => {
+                let data = CancelOrderDataAEVO {
+                    order_id: order.order_id.clone(),
+                };
+                self.websocket_request::<_, serde_json::Value>(websocket, "cancel_order", data)
+                    .await
+            }
+            None => {
+                self.rest::<serde_json::Value>(
+                    Method::DELETE,
+                    &format!("/orders/{}", order.order_id),
+                    None,
+                )
+                .await
+            }
+        }
+        .with_context(|| format!("Failed to cancel AEVO order {}", order.order_id))?;
+
+        Ok(())
+    }
+
+    async fn query_order(&self, order: &OrderReport) -> Result<OrderReport> {
+        let response: OrderResponseAEVO = self
+            .rest(Method::GET, &format!("/orders/{}", order.order_id), None)
+            .await?;
+
+        response.to_report(order.client_id, order.time_in_force)
+    }
+}
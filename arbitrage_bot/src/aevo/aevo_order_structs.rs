@@ -0,0 +1,137 @@
+use anyhow::{bail, Context, Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    instrument::{parse_decimal, Size},
+    order::{OrderReport, OrderStatus, TimeInForce},
+    venue::{Side, Venue},
+};
+
+///Prices and amounts of AEVO orders are integers with 6 decimals
+pub const AEVO_DECIMALS: u32 = 6;
+
+///Converts decimal to 6 decimal fixed point integer, rounding toward zero
+pub fn to_fixed(value: Decimal) -> Result<u128> {
+    let scaled = (value * Decimal::from(10u64.pow(AEVO_DECIMALS))).trunc();
+    u128::try_from(scaled).with_context(|| format!("Value {value} can not be sent to AEVO"))
+}
+
+///Body of REST `POST /orders` and data of websocket `create_order`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderPayloadAEVO {
+    pub instrument: String,
+    pub maker: String,
+    pub is_buy: bool,
+    pub amount: String,
+    pub limit_price: String,
+    pub salt: String,
+    pub signature: String,
+    pub timestamp: String,
+    pub post_only: bool,
+    pub reduce_only: bool,
+    pub time_in_force: String,
+}
+
+///Websocket request with id echoed in its response
+#[derive(Debug, Clone, Serialize)]
+pub struct OrderRequestWSAEVO<T> {
+    op: String,
+    id: u64,
+    data: T,
+}
+
+impl<T> OrderRequestWSAEVO<T> {
+    pub fn new(op: &str, id: u64, data: T) -> Self {
+        Self {
+            op: op.to_string(),
+            id,
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CancelOrderDataAEVO {
+    pub order_id: String,
+}
+
+///Order state returned by REST, websocket acknowledgements and `orders` channel
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderResponseAEVO {
+    pub order_id: String,
+    pub side: String,
+    pub amount: String,
+    #[serde(default)]
+    pub filled: Option<String>,
+    #[serde(default)]
+    pub avg_price: Option<String>,
+    pub order_status: String,
+}
+
+impl OrderResponseAEVO {
+    pub fn filled_qty(&self) -> Result<Size> {
+        match &self.filled {
+            Some(filled) => parse_decimal(filled),
+            None => Ok(Decimal::ZERO),
+        }
+    }
+
+    pub fn order_status(&self) -> Result<OrderStatus> {
+        let filled = self.filled_qty()?;
+        Ok(match self.order_status.as_str() {
+            "filled" => OrderStatus::Filled,
+            "cancelled" | "expired" => OrderStatus::Canceled,
+            "rejected" => OrderStatus::Rejected,
+            _ if filled > Decimal::ZERO => OrderStatus::PartiallyFilled,
+            _ => OrderStatus::Open,
+        })
+    }
+
+    ///Report of our order with state acknowledged by AEVO
+    pub fn to_report(&self, client_id: u32, time_in_force: TimeInForce) -> Result<OrderReport> {
+        let filled_qty = self.filled_qty()?;
+        Ok(OrderReport {
+            venue: Venue::Aevo,
+            order_id: self.order_id.clone(),
+            client_id,
+            side: match self.side.as_str() {
+                "buy" => Side::Buy,
+                "sell" => Side::Sell,
+                side => bail!("Unknown AEVO order side {side}"),
+            },
+            time_in_force,
+            status: self.order_status()?,
+            quantity: parse_decimal(&self.amount)?,
+            filled_qty,
+            avg_price: match &self.avg_price {
+                Some(price) if filled_qty > Decimal::ZERO => Some(parse_decimal(price)?),
+                _ => None,
+            },
+        })
+    }
+}
+
+///Websocket response matched to request by id
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResponseWSAEVO {
+    #[serde(default)]
+    pub id: Option<u64>,
+    #[serde(default)]
+    pub data: Option<serde_json::Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+///REST error body
+#[derive(Debug, Clone, Deserialize)]
+pub struct ErrorResponseAEVO {
+    pub error: String,
+}
+
+///Response of REST `GET /instrument/{instrument_name}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstrumentResponseAEVO {
+    pub instrument_id: String,
+    pub instrument_name: String,
+}
@@ -1,54 +1,127 @@
-use std::sync::Arc;
-
-use anyhow::Result;
-use futures::{SinkExt, StreamExt};
-use tokio::{net::TcpStream, sync::Mutex, task::JoinHandle};
-use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
-use url::Url;
+use std::{sync::Arc, time::Instant};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::warn;
+use tokio::{task::JoinHandle, time::timeout};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    feed::{
+        apply_routed_frame, spawn_supervised, BookUpdateNotifier, FeedHandle, FeedHealth,
+        VenueBooks, FEED_IDLE_TIMEOUT,
+    },
+    feed_source::FeedSource,
+    recording::FeedRecorder,
+    venue::Venue,
+};
 
-use super::aevo_structs::{
-    AuthPayloadAEVO, ChannelsPayloadAEVO, ChannelsResponseAEVO, OrderbookAEVO,
-    OrderbookAEVOResponse, OrderbookPayloadAEVO,
+use super::{
+    aevo_auth::{AEVOAuthError, AEVOCredentials, AuthResponseAEVO},
+    aevo_instrument::ORDERBOOK_CHANNEL_PREFIX,
+    aevo_structs::{
+        AuthPayloadAEVO, ChannelsPayloadAEVO, ChannelsResponseAEVO, OrderbookAEVO,
+        OrderbookPayloadAEVO,
+    },
 };
 
-pub struct AEVOWSAuthenticator<'a> {
-    pub wss_addr: &'a str,
+#[derive(Debug, Clone)]
+pub struct AEVOWSAuthenticator {
+    pub wss_addr: String,
+    credentials: AEVOCredentials,
 }
 
-impl<'a> AEVOWSAuthenticator<'a> {
-    pub fn new(wss_addr: &'a str) -> Self {
-        Self { wss_addr }
+impl AEVOWSAuthenticator {
+    pub fn new(wss_addr: &str, credentials: AEVOCredentials) -> Self {
+        Self {
+            wss_addr: wss_addr.to_string(),
+            credentials,
+        }
     }
 
-    fn generate_api_key(&self) -> (String, String) {
-        //Considering the fact, that we are simulating swaps we may as well simplify there
-        ("API_KEY".to_string(), "SECRET_KEY".to_string())
+    pub fn credentials(&self) -> &AEVOCredentials {
+        &self.credentials
     }
 
     fn generate_auth_message(&self) -> Message {
-        let (api_key, secret) = self.generate_api_key();
-        Message::Text(serde_json::to_string(&AuthPayloadAEVO::new(api_key, secret)).unwrap())
+        Message::Text(
+            serde_json::to_string(&AuthPayloadAEVO::new(
+                self.credentials.api_key.clone(),
+                self.credentials.api_secret.clone(),
+            ))
+            .unwrap(),
+        )
+    }
+
+    ///Waits for `auth` acknowledgement, skipping control frames
+    async fn receive_auth_response(source: &mut FeedSource) -> Result<()> {
+        while let Some(message) = source.next_frame().await {
+            match message? {
+                Message::Text(text) => {
+                    let response: AuthResponseAEVO = serde_json::from_str(&text)
+                        .map_err(|_| AEVOAuthError::UnexpectedResponse(text))?;
+                    return Ok(response.into_result()?);
+                }
+                Message::Ping(_) | Message::Pong(_) => {}
+                _ => break,
+            }
+        }
+
+        Err(AEVOAuthError::NoAcknowledgement.into())
     }
 
-    pub async fn authenticate(&self) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
-        let (mut websocket, _) =
-            tokio_tungstenite::connect_async(Url::parse(self.wss_addr)?).await?;
+    pub async fn authenticate(&self) -> Result<FeedSource> {
+        self.authenticate_source(FeedSource::connect(&self.wss_addr).await?)
+            .await
+    }
 
+    ///Authenticates over an already open source
+    pub async fn authenticate_source(&self, mut source: FeedSource) -> Result<FeedSource> {
         let auth_message = self.generate_auth_message();
 
-        websocket.send(auth_message).await?;
+        source.send(auth_message).await?;
 
-        Ok(websocket)
+        Self::receive_auth_response(&mut source).await?;
+
+        Ok(source)
     }
 }
 
+/// Orderbook feed of AEVO instruments
+///
+/// All instruments are subscribed over one connection with a single request
 pub struct AEVOWSOrderbookFeed {
-    wss_socket_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    source: FeedSource,
+    notifier: BookUpdateNotifier,
+    instruments: Vec<String>,
+    channels: Vec<String>,
+    recorder: Option<FeedRecorder>,
 }
 
 impl AEVOWSOrderbookFeed {
-    pub fn new(wss_socket_stream: WebSocketStream<MaybeTlsStream<TcpStream>>) -> Self {
-        Self { wss_socket_stream }
+    pub fn new(source: FeedSource, instruments: &[impl AsRef<str>]) -> Self {
+        Self {
+            source,
+            notifier: BookUpdateNotifier::default(),
+            instruments: instruments
+                .iter()
+                .map(|instrument| instrument.as_ref().to_string())
+                .collect(),
+            channels: vec![],
+            recorder: None,
+        }
+    }
+
+    ///Notifies subscribers about every applied update
+    pub fn with_notifier(mut self, notifier: BookUpdateNotifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    ///Writes every orderbook frame to the recording
+    pub fn with_recorder(mut self, recorder: Option<FeedRecorder>) -> Self {
+        self.recorder = recorder;
+        self
     }
 
     fn generate_channels_message(&self) -> Message {
@@ -59,21 +132,19 @@ impl AEVOWSOrderbookFeed {
         Message::Text(serde_json::to_string(&OrderbookPayloadAEVO::new(channels)).unwrap())
     }
 
-    async fn subscribe_for_feed(&mut self) -> Result<()> {
+    pub async fn subscribe_for_feed(&mut self) -> Result<()> {
         let channels_message = self.generate_channels_message();
 
-        self.wss_socket_stream.send(channels_message).await?;
+        self.source.send(channels_message).await?;
 
-        let search_channel = if let Some(Ok(channels)) = self.wss_socket_stream.next().await {
+        let search_channels = if let Some(Ok(channels)) = self.source.next_frame().await {
             if let Message::Text(channels_text) = channels {
                 let channels_decoded: ChannelsResponseAEVO = serde_json::from_str(&channels_text)?;
 
-                //ToDo: Correct channel parsing
-                channels_decoded
-                    .data
-                    .into_iter()
-                    .find(|el| el.contains("ETH"))
-                    .unwrap()
+                self.instruments
+                    .iter()
+                    .map(|instrument| channels_decoded.find_orderbook_channel(instrument))
+                    .collect::<Result<Vec<_>, _>>()?
             } else {
                 anyhow::bail!("Wrond message format")
             }
@@ -81,40 +152,97 @@ impl AEVOWSOrderbookFeed {
             anyhow::bail!("Failed to receive channels")
         };
 
-        let orderbook_message = self.generate_orderbook_message(vec![search_channel]);
+        self.channels = search_channels;
+
+        self.request_snapshot(self.channels.clone()).await
+    }
+
+    ///Orderbook request is answered with a full snapshot of every requested channel
+    async fn request_snapshot(&mut self, channels: Vec<String>) -> Result<()> {
+        let orderbook_message = self.generate_orderbook_message(channels);
 
-        self.wss_socket_stream.send(orderbook_message).await?;
+        self.source.send(orderbook_message).await?;
 
         Ok(())
     }
 
-    pub async fn spawn_feed(
-        mut self,
-        orderbook_ref: Arc<Mutex<OrderbookAEVO>>,
-    ) -> Result<JoinHandle<Result<()>>> {
-        self.subscribe_for_feed().await?;
+    ///Processes feed frames until connection fails or is closed, updating the book of each instrument
+    pub async fn run(mut self, books: VenueBooks<OrderbookAEVO>) -> Result<()> {
+        while let Some(resp) = timeout(FEED_IDLE_TIMEOUT, self.source.next_frame())
+            .await
+            .context("AEVO feed is idle")?
+        {
+            let received_at = Instant::now();
+            let received_time = Utc::now();
+
+            match resp? {
+                Message::Text(feed_text) => {
+                    if let Some(recorder) = &self.recorder {
+                        recorder.record_or_warn(Venue::Aevo, received_time, &feed_text);
+                    }
 
-        let handle = tokio::spawn(async move {
-            while let Some(resp) = self.wss_socket_stream.next().await {
-                if let Ok(message) = resp {
-                    if let Message::Text(feed_text) = message {
-                        let feed_decoded: OrderbookAEVOResponse = serde_json::from_str(&feed_text)?;
-
-                        {
-                            let mut guard = orderbook_ref.lock().await;
-                            guard.apply_changes(feed_decoded)
-                        }
-                    } else {
-                        anyhow::bail!("Wrond message format")
+                    let Some((instrument, violation)) =
+                        apply_routed_frame(&books, &feed_text, received_at, received_time).await?
+                    else {
+                        continue;
+                    };
+                    self.notifier.notify(received_at);
+
+                    if let Some(violation) = violation {
+                        warn!(
+                            "AEVO {instrument} orderbook is stale ({violation:?}), requesting snapshot"
+                        );
+                        self.request_snapshot(vec![format!(
+                            "{ORDERBOOK_CHANNEL_PREFIX}{instrument}"
+                        )])
+                        .await?;
                     }
-                } else {
-                    anyhow::bail!("Failed to receive feed")
                 }
+                //Pings are answered by tungstenite itself
+                Message::Ping(_) | Message::Pong(_) => {}
+                Message::Close(frame) => anyhow::bail!("Connection closed: {frame:?}"),
+                _ => anyhow::bail!("Wrond message format"),
             }
+        }
 
-            Ok(())
-        });
+        Ok(())
+    }
 
-        Ok(handle)
+    pub async fn spawn_feed(
+        mut self,
+        books: VenueBooks<OrderbookAEVO>,
+    ) -> Result<JoinHandle<Result<()>>> {
+        self.subscribe_for_feed().await?;
+
+        Ok(tokio::spawn(self.run(books)))
+    }
+
+    /// Spawns feed of every instrument in `books` which survives disconnects
+    ///
+    /// Every session authenticates and subscribes again, books are reset in between
+    pub fn spawn_supervised(
+        authenticator: AEVOWSAuthenticator,
+        books: VenueBooks<OrderbookAEVO>,
+        recorder: Option<FeedRecorder>,
+    ) -> FeedHandle {
+        let authenticator = Arc::new(authenticator);
+        let instruments: Vec<String> = books.markets().map(str::to_string).collect();
+
+        spawn_supervised("AEVO", books.clone(), move |health, notifier| {
+            let authenticator = authenticator.clone();
+            let books = books.clone();
+            let instruments = instruments.clone();
+            let recorder = recorder.clone();
+
+            async move {
+                let mut feed = Self::new(authenticator.authenticate().await?, &instruments)
+                    .with_notifier(notifier)
+                    .with_recorder(recorder);
+                feed.subscribe_for_feed().await?;
+                health.send_replace(FeedHealth::Live);
+
+                feed.run(books).await
+            }
+        })
     }
 }
@@ -0,0 +1,168 @@
+use std::fmt;
+
+use anyhow::{ensure, Context, Result};
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
+
+pub const AEVO_SIGNING_KEY_ENV: &str = "AEVO_SIGNING_KEY";
+
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId)";
+const ORDER_TYPE: &str = "Order(address maker,bool isBuy,uint256 limitPrice,uint256 amount,uint256 salt,uint256 instrument,uint256 timestamp)";
+
+/// EIP-712 domain orders are signed for
+///
+/// AEVO mainnet uses `Aevo Mainnet` on chain 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SigningDomainAEVO {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+}
+
+impl Default for SigningDomainAEVO {
+    fn default() -> Self {
+        Self {
+            name: "Aevo Mainnet".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+        }
+    }
+}
+
+impl SigningDomainAEVO {
+    fn separator(&self) -> [u8; 32] {
+        keccak(&[
+            &keccak(&[DOMAIN_TYPE.as_bytes()]),
+            &keccak(&[self.name.as_bytes()]),
+            &keccak(&[self.version.as_bytes()]),
+            &uint256(self.chain_id as u128),
+        ])
+    }
+}
+
+///Order fields covered by signature, prices and amounts have 6 decimals
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderSignatureDataAEVO {
+    pub maker: [u8; 20],
+    pub is_buy: bool,
+    pub limit_price: u128,
+    pub amount: u128,
+    pub salt: u128,
+    pub instrument: u128,
+    pub timestamp: u128,
+}
+
+impl OrderSignatureDataAEVO {
+    fn struct_hash(&self) -> [u8; 32] {
+        let mut maker = [0u8; 32];
+        maker[12..].copy_from_slice(&self.maker);
+
+        keccak(&[
+            &keccak(&[ORDER_TYPE.as_bytes()]),
+            &maker,
+            &uint256(self.is_buy as u128),
+            &uint256(self.limit_price),
+            &uint256(self.amount),
+            &uint256(self.salt),
+            &uint256(self.instrument),
+            &uint256(self.timestamp),
+        ])
+    }
+}
+
+/// Key registered as signing key of an AEVO account
+///
+/// Private key never appears in `Debug` output
+#[derive(Clone)]
+pub struct AEVOSigner {
+    signing_key: SigningKey,
+    domain: SigningDomainAEVO,
+}
+
+impl fmt::Debug for AEVOSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AEVOSigner")
+            .field("address", &self.address())
+            .field("domain", &self.domain)
+            .field("signing_key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl AEVOSigner {
+    ///Signer from hex encoded 32 byte private key, `0x` prefix is optional
+    pub fn from_hex(private_key: &str, domain: SigningDomainAEVO) -> Result<Self> {
+        let bytes = hex::decode(private_key.trim().trim_start_matches("0x"))
+            .context("AEVO signing key is not valid hex")?;
+        let signing_key =
+            SigningKey::from_slice(&bytes).context("AEVO signing key is not a secp256k1 key")?;
+
+        Ok(Self {
+            signing_key,
+            domain,
+        })
+    }
+
+    ///Reads private key from `AEVO_SIGNING_KEY`
+    pub fn from_env(domain: SigningDomainAEVO) -> Result<Self> {
+        let private_key = std::env::var(AEVO_SIGNING_KEY_ENV)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .with_context(|| format!("AEVO credential {AEVO_SIGNING_KEY_ENV} is not configured"))?;
+
+        Self::from_hex(&private_key, domain)
+    }
+
+    ///Ethereum address of signing key, `0x` prefixed lowercase hex
+    pub fn address(&self) -> String {
+        let public_key = self.signing_key.verifying_key().to_encoded_point(false);
+        let hash = keccak(&[&public_key.as_bytes()[1..]]);
+        format!("0x{}", hex::encode(&hash[12..]))
+    }
+
+    ///EIP-712 digest of an order
+    pub fn order_digest(&self, order: &OrderSignatureDataAEVO) -> [u8; 32] {
+        keccak(&[b"\x19\x01", &self.domain.separator(), &order.struct_hash()])
+    }
+
+    ///Recoverable signature of an order, `0x` prefixed `r || s || v` with `v` of 27 or 28
+    pub fn sign_order(&self, order: &OrderSignatureDataAEVO) -> Result<String> {
+        let (signature, recovery_id) = self
+            .signing_key
+            .sign_prehash_recoverable(&self.order_digest(order))
+            .context("Failed to sign AEVO order")?;
+
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+
+        Ok(format!("0x{}", hex::encode(bytes)))
+    }
+}
+
+///Parses `0x` prefixed Ethereum address
+pub fn parse_address(address: &str) -> Result<[u8; 20]> {
+    let bytes = hex::decode(address.trim_start_matches("0x"))
+        .with_context(|| format!("Address {address:?} is not valid hex"))?;
+    ensure!(
+        bytes.len() == 20,
+        "Address {address:?} is not 20 bytes long"
+    );
+
+    let mut result = [0u8; 20];
+    result.copy_from_slice(&bytes);
+    Ok(result)
+}
+
+fn keccak(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn uint256(value: u128) -> [u8; 32] {
+    let mut result = [0u8; 32];
+    result[16..].copy_from_slice(&value.to_be_bytes());
+    result
+}
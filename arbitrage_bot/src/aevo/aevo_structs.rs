@@ -1,13 +1,32 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, fmt};
 
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::{
+    instrument::{parse_decimal, InstrumentSpec, Price, Size},
+    orderbook::{BookTimestamps, Levels, Orderbook},
+    venue::Venue,
+};
+
+use super::aevo_instrument::ORDERBOOK_CHANNEL_PREFIX;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct AuthDataAEVO {
     key: String,
     secret: String,
 }
 
+impl fmt::Debug for AuthDataAEVO {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuthDataAEVO")
+            .field("key", &self.key)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
 impl AuthDataAEVO {
     pub fn new(key: String, secret: String) -> Self {
         Self { key, secret }
@@ -68,6 +87,7 @@ pub struct OrderbookAEVOData {
     instrument_type: String,
     bids: Vec<(String, String, String)>,
     asks: Vec<(String, String, String)>,
+    ///Nanoseconds since epoch
     last_updated: String,
 }
 
@@ -77,69 +97,140 @@ pub struct OrderbookAEVOResponse {
     data: OrderbookAEVOData,
 }
 
-#[derive(Debug, Clone, Default)]
+///Parsed AEVO price level: (price, amount, implied volatility)
+pub type LevelAEVO = (Price, Size, f64);
+
+#[derive(Debug, Clone)]
 /// AEVO Orderbook struct
-/// 
-/// Storing BTreeMaps to make insert operations fast 
+///
+/// Storing BTreeMaps to make insert operations fast
 pub struct OrderbookAEVO {
-    pub bids: BTreeMap<u64, (u64, u64, f64)>,
-    pub asks: BTreeMap<u64, (u64, u64, f64)>,
+    pub bids: BTreeMap<Price, LevelAEVO>,
+    pub asks: BTreeMap<Price, LevelAEVO>,
+    pub spec: InstrumentSpec,
+    ///Set until first snapshot and on integrity violation, cleared by next snapshot
+    pub stale: bool,
+    pub timestamps: BookTimestamps,
+}
+
+impl Default for OrderbookAEVO {
+    fn default() -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            spec: InstrumentSpec::default(),
+            stale: true,
+            timestamps: BookTimestamps::default(),
+        }
+    }
 }
 
 impl OrderbookAEVO {
-    pub fn apply_changes(&mut self, resp: OrderbookAEVOResponse) {
+    pub fn new(spec: InstrumentSpec) -> Self {
+        Self {
+            spec,
+            ..Default::default()
+        }
+    }
+
+    fn parse_level((price, amount, iv): (String, String, String)) -> Result<(Price, LevelAEVO)> {
+        let price = parse_decimal(&price)?;
+        let amount = parse_decimal(&amount)?;
+        let iv = iv
+            .parse()
+            .with_context(|| format!("Invalid implied volatility {iv:?}"))?;
+
+        Ok((price, (price, amount, iv)))
+    }
+
+    fn parse_timestamp(nanos: &str) -> Result<DateTime<Utc>> {
+        let nanos = nanos
+            .parse()
+            .with_context(|| format!("Invalid AEVO timestamp {nanos:?}"))?;
+        Ok(DateTime::from_timestamp_nanos(nanos))
+    }
+}
+
+impl Orderbook for OrderbookAEVO {
+    type Update = OrderbookAEVOResponse;
+
+    fn update_market(update: &OrderbookAEVOResponse) -> Option<&str> {
+        update.channel.strip_prefix(ORDERBOOK_CHANNEL_PREFIX)
+    }
+
+    fn venue(&self) -> Venue {
+        Venue::Aevo
+    }
+
+    fn apply_changes(&mut self, resp: OrderbookAEVOResponse) -> Result<()> {
         if resp.data.r#type == "snapshot" {
-            self.asks = resp
-                .data
-                .asks
-                .into_iter()
-                .map(|(price, amount, iv)| {
-                    (
-                        price.clone().parse().unwrap(),
-                        (
-                            price.parse().unwrap(),
-                            amount.parse().unwrap(),
-                            iv.parse().unwrap(),
-                        ),
-                    )
-                })
-                .collect();
-            self.bids = resp
-                .data
-                .bids
-                .into_iter()
-                .map(|(price, amount, iv)| {
-                    (
-                        price.clone().parse().unwrap(),
-                        (
-                            price.parse().unwrap(),
-                            amount.parse().unwrap(),
-                            iv.parse().unwrap(),
-                        ),
-                    )
-                })
-                .collect();
-        } else {
-            for (price, amount, iv) in resp.data.asks {
-                self.asks.insert(
-                    price.clone().parse().unwrap(),
-                    (
-                        price.parse().unwrap(),
-                        amount.parse().unwrap(),
-                        iv.parse().unwrap(),
-                    ),
-                );
+            self.asks.clear();
+            self.bids.clear();
+            self.stale = false;
+        }
+        self.timestamps.exchange_time = Some(Self::parse_timestamp(&resp.data.last_updated)?);
+
+        //Zero amount removes price level
+        for level in resp.data.asks {
+            let (price, level) = Self::parse_level(level)?;
+            if level.1.is_zero() {
+                self.asks.remove(&price);
+            } else {
+                self.asks.insert(price, level);
             }
-            for (price, amount, iv) in resp.data.bids {
-                self.bids.insert(
-                    price.clone().parse().unwrap(),
-                    (
-                        price.parse().unwrap(),
-                        amount.parse().unwrap(),
-                        iv.parse().unwrap(),
-                    ),
-                );
+        }
+        for level in resp.data.bids {
+            let (price, level) = Self::parse_level(level)?;
+            if level.1.is_zero() {
+                self.bids.remove(&price);
+            } else {
+                self.bids.insert(price, level);
             }
         }
+
+        Ok(())
+    }
+
+    fn spec(&self) -> &InstrumentSpec {
+        &self.spec
+    }
+
+    fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    fn set_stale(&mut self, stale: bool) {
+        self.stale = stale;
+    }
+
+    fn reset(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.stale = true;
+        self.timestamps = BookTimestamps::default();
+    }
+
+    fn timestamps(&self) -> &BookTimestamps {
+        &self.timestamps
+    }
+
+    fn timestamps_mut(&mut self) -> &mut BookTimestamps {
+        &mut self.timestamps
+    }
+
+    fn bid_levels(&self) -> Levels<'_> {
+        Box::new(
+            self.bids
+                .iter()
+                .map(|(price, (_, size, _))| (*price, *size)),
+        )
+    }
+
+    fn ask_levels(&self) -> Levels<'_> {
+        Box::new(
+            self.asks
+                .iter()
+                .map(|(price, (_, size, _))| (*price, *size)),
+        )
     }
 }
@@ -1,2 +1,7 @@
+pub mod aevo_auth;
+pub mod aevo_instrument;
+pub mod aevo_order_client;
+pub mod aevo_order_structs;
 pub mod aevo_orderbook_feed;
+pub mod aevo_signer;
 pub mod aevo_structs;
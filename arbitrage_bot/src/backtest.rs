@@ -0,0 +1,738 @@
+use std::{fmt, path::PathBuf, time::Instant};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::Parser;
+use log::{debug, info, warn};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aevo::aevo_structs::OrderbookAEVO,
+    calculations::{compare_orderbooks, ArbitrageOpportunity},
+    config::{Cli, Config},
+    dxdy::dxdy_structs::OrderbookDXDY,
+    feed::apply_update,
+    orderbook::{BookFreshness, Orderbook},
+    paper_trading::{PaperTrade, PaperTrader},
+    portfolio::{Fill, Portfolio},
+    recording::{FrameUpdate, MergedFrames, RecordedFrame},
+    risk::{BookState, KillSwitch, RiskManager},
+    sizing::VenueTerms,
+    venue::Venue,
+};
+
+const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 3600.0;
+
+///Backtest settings
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BacktestConfig {
+    ///Spacing of equity curve samples, Sharpe ratio is computed from their returns
+    pub equity_interval_secs: u64,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            equity_interval_secs: 60,
+        }
+    }
+}
+
+///Command line flags of the backtest binary, override values from the config file
+#[derive(Debug, Clone, Parser)]
+#[command(version, about = "Backtests AEVO / dXdY arbitrage on recorded feeds")]
+pub struct BacktestCli {
+    ///Recordings made with --record, frames of all files are merged in timestamp order
+    #[arg(required = true)]
+    pub recordings: Vec<PathBuf>,
+    ///Path to TOML config file of the bot
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    ///Delay between detection and simulated fills, overrides paper.latency_ms
+    #[arg(long)]
+    pub latency_ms: Option<u64>,
+    ///Overrides paper.slippage_bps
+    #[arg(long)]
+    pub slippage_bps: Option<Decimal>,
+    ///Flat AEVO taker fee replacing configured tiers
+    #[arg(long)]
+    pub aevo_taker_bps: Option<Decimal>,
+    ///Flat dXdY taker fee replacing configured tiers
+    #[arg(long)]
+    pub dxdy_taker_bps: Option<Decimal>,
+    #[arg(long)]
+    pub capital: Option<Decimal>,
+    #[arg(long)]
+    pub min_profit: Option<Decimal>,
+    ///Overrides risk.min_edge_bps
+    #[arg(long)]
+    pub min_edge_bps: Option<Decimal>,
+    ///Writes full report including trades and equity curve as JSON
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl BacktestCli {
+    ///Bot configuration with backtest overrides applied
+    pub fn load_config(&self) -> Result<Config> {
+        let mut config = Config::load(&Cli {
+            config: self.config.clone(),
+            capital: self.capital,
+            min_profit: self.min_profit,
+            ..Cli::default()
+        })?;
+
+        if let Some(latency_ms) = self.latency_ms {
+            config.paper.latency_ms = latency_ms;
+        }
+        if let Some(slippage_bps) = self.slippage_bps {
+            config.paper.slippage_bps = slippage_bps;
+        }
+        if let Some(taker_bps) = self.aevo_taker_bps {
+            config.aevo.fees = config.aevo.fees.with_taker_bps(taker_bps);
+        }
+        if let Some(taker_bps) = self.dxdy_taker_bps {
+            config.dxdy.fees = config.dxdy.fees.with_taker_bps(taker_bps);
+        }
+        if let Some(min_edge_bps) = self.min_edge_bps {
+            config.risk.min_edge_bps = min_edge_bps;
+        }
+        config.validate().context("Invalid configuration")?;
+
+        Ok(config)
+    }
+}
+
+///Profit and loss of the strategy at a point of the recording
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EquityPoint {
+    pub time: DateTime<Utc>,
+    pub realized_p_l: Decimal,
+    pub unrealized_p_l: Decimal,
+}
+
+impl EquityPoint {
+    pub fn p_l(&self) -> Decimal {
+        self.realized_p_l + self.unrealized_p_l
+    }
+}
+
+///Outcome of a backtest
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestReport {
+    ///Receive time of the first and the last replayed frame
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub frames: u64,
+    ///Frames that could not be applied, their book was reset until next snapshot
+    pub rejected_frames: u64,
+    ///Profitable opportunities that passed risk checks
+    pub opportunities: u64,
+    pub blocked: u64,
+    ///Opportunities that could not be filled once latency elapsed
+    pub missed: u64,
+    pub expected_p_l: Decimal,
+    ///Sum of simulated trade profits after fees and slippage
+    pub realized_p_l: Decimal,
+    ///Portfolio profit and loss at the end, including inventory revaluation
+    pub final_p_l: Decimal,
+    pub fees: Decimal,
+    ///Share of trades with positive profit
+    pub hit_rate: Option<Decimal>,
+    ///Largest fall of profit and loss from a previous peak
+    pub max_drawdown: Decimal,
+    ///Annualized Sharpe ratio of equity curve returns on capital
+    pub sharpe: Option<f64>,
+    ///Breakdown by market pair
+    pub markets: Vec<MarketReport>,
+    ///Trades of all market pairs in execution order
+    pub trades: Vec<PaperTrade>,
+    ///Profit and loss sampled every `equity_interval_secs` from the first frame
+    pub equity: Vec<EquityPoint>,
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Period : {} - {}",
+            self.start.map(|time| time.to_rfc3339()).unwrap_or_default(),
+            self.end.map(|time| time.to_rfc3339()).unwrap_or_default()
+        )?;
+        writeln!(
+            f,
+            "Frames : {}, rejected : {}",
+            self.frames, self.rejected_frames
+        )?;
+        writeln!(
+            f,
+            "Opportunities : {}, blocked by risk : {}, missed : {}, trades : {}",
+            self.opportunities,
+            self.blocked,
+            self.missed,
+            self.trades.len()
+        )?;
+        writeln!(
+            f,
+            "Profit and loss : realized {}, expected {}, final {}, fees {}",
+            self.realized_p_l.round_dp(4),
+            self.expected_p_l.round_dp(4),
+            self.final_p_l.round_dp(4),
+            self.fees.round_dp(4)
+        )?;
+        if self.markets.len() > 1 {
+            for market in &self.markets {
+                writeln!(
+                    f,
+                    "{} : opportunities {}, trades {}, realized {}, final {}",
+                    market.market,
+                    market.opportunities,
+                    market.trades,
+                    market.realized_p_l.round_dp(4),
+                    market.final_p_l.round_dp(4)
+                )?;
+            }
+        }
+        write!(
+            f,
+            "Hit rate : {}, max drawdown : {}, Sharpe : {}",
+            self.hit_rate
+                .map(|rate| rate.round_dp(4).to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            self.max_drawdown.round_dp(4),
+            self.sharpe
+                .map(|sharpe| format!("{sharpe:.2}"))
+                .unwrap_or_else(|| "-".to_string())
+        )
+    }
+}
+
+///Opportunity waiting for execution latency to elapse
+#[derive(Debug, Clone)]
+struct PendingTrade {
+    opportunity: ArbitrageOpportunity,
+    execute_at: DateTime<Utc>,
+}
+
+///Results of one market pair within a backtest
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketReport {
+    ///Traded pair, e.g. `ETH-PERP/ETH-USD`
+    pub market: String,
+    pub opportunities: u64,
+    pub trades: usize,
+    ///Profit realized by portfolio positions of the pair
+    pub realized_p_l: Decimal,
+    ///Portfolio profit and loss at the end, including inventory revaluation
+    pub final_p_l: Decimal,
+}
+
+/// Books and accounts of one market pair
+///
+/// Every pair trades its own inventory, as `main_loop` does
+#[derive(Debug)]
+struct MarketBacktest {
+    name: String,
+    aevo_instrument: String,
+    dxdy_market: String,
+    capital: Decimal,
+    min_profit: Decimal,
+    latency: TimeDelta,
+    freshness: BookFreshness,
+    aevo_terms: VenueTerms,
+    dxdy_terms: VenueTerms,
+    orderbook_aevo: OrderbookAEVO,
+    orderbook_dxdy: OrderbookDXDY,
+    trader: PaperTrader,
+    portfolio: Portfolio,
+    risk: RiskManager,
+    pending: Option<PendingTrade>,
+    trades: Vec<PaperTrade>,
+    opportunities: u64,
+    blocked: u64,
+    missed: u64,
+    expected_p_l: Decimal,
+}
+
+impl MarketBacktest {
+    ///`config` is expected to be narrowed to the pair with `Config::for_market`
+    fn new(config: &Config, name: String) -> Result<Self> {
+        let mut paper = config.paper.clone();
+        //Trades are part of the report, nothing is appended to the live trade log
+        paper.trade_log = None;
+        let trader = PaperTrader::new(&paper, config.aevo.fees.clone(), config.dxdy.fees.clone())?
+            .with_market(&name);
+
+        Ok(Self {
+            name,
+            aevo_instrument: config.aevo.instrument.clone(),
+            dxdy_market: config.dxdy.market.clone(),
+            capital: config.capital,
+            min_profit: config.min_profit,
+            latency: TimeDelta::from_std(trader.latency())?,
+            freshness: config.risk.freshness(),
+            aevo_terms: config.aevo.terms(),
+            dxdy_terms: config.dxdy.terms(),
+            orderbook_aevo: OrderbookAEVO::new(config.aevo.spec()),
+            orderbook_dxdy: OrderbookDXDY::new(config.dxdy.spec()),
+            trader,
+            portfolio: Portfolio::new([
+                (Venue::Aevo, config.paper.aevo),
+                (Venue::Dxdy, config.paper.dxdy),
+            ]),
+            //Kill switch of a backtest never engages
+            risk: RiskManager::new(config.risk.clone(), KillSwitch::default()),
+            pending: None,
+            trades: Vec::new(),
+            opportunities: 0,
+            blocked: 0,
+            missed: 0,
+            expected_p_l: Decimal::ZERO,
+        })
+    }
+
+    fn symbol(&self, venue: Venue) -> &str {
+        match venue {
+            Venue::Aevo => &self.aevo_instrument,
+            Venue::Dxdy => &self.dxdy_market,
+        }
+    }
+
+    fn apply(
+        &mut self,
+        frame: &RecordedFrame,
+        update: FrameUpdate,
+        received_at: Instant,
+    ) -> Result<()> {
+        let violation = match update {
+            FrameUpdate::Aevo(update) => apply_update(
+                &mut self.orderbook_aevo,
+                update,
+                received_at,
+                frame.received_time,
+            ),
+            FrameUpdate::Dxdy(update) => apply_update(
+                &mut self.orderbook_dxdy,
+                update,
+                received_at,
+                frame.received_time,
+            ),
+        }?;
+        if let Some(violation) = violation {
+            debug!(
+                "{} {} orderbook is stale ({violation:?})",
+                frame.venue,
+                self.symbol(frame.venue)
+            );
+        }
+
+        self.portfolio.mark_book(&self.orderbook_aevo);
+        self.portfolio.mark_book(&self.orderbook_dxdy);
+
+        Ok(())
+    }
+
+    fn reset(&mut self, venue: Venue) {
+        match venue {
+            Venue::Aevo => self.orderbook_aevo.reset(),
+            Venue::Dxdy => self.orderbook_dxdy.reset(),
+        }
+    }
+
+    ///Fills the pending trade once its latency elapsed
+    fn execute_due(&mut self, now: DateTime<Utc>) {
+        if let Some(pending) = self.pending.take_if(|pending| pending.execute_at <= now) {
+            self.execute(pending);
+        }
+    }
+
+    fn evaluate(&mut self, now: DateTime<Utc>) {
+        if self.pending.is_some()
+            || !self.freshness.allows_at(
+                &self.orderbook_aevo.timestamps,
+                &self.orderbook_dxdy.timestamps,
+                now,
+            )
+        {
+            return;
+        }
+
+        let opportunity = compare_orderbooks(
+            &self.orderbook_aevo,
+            &self
+                .aevo_terms
+                .clone()
+                .with_inventory(self.portfolio.available(Venue::Aevo)),
+            &self.orderbook_dxdy,
+            &self
+                .dxdy_terms
+                .clone()
+                .with_inventory(self.portfolio.available(Venue::Dxdy)),
+            self.capital,
+        );
+        let mut opportunity = match opportunity {
+            Some(opportunity)
+                if opportunity.is_profitable() && opportunity.net_profit >= self.min_profit =>
+            {
+                opportunity
+            }
+            _ => return,
+        };
+        opportunity.detected_at = now;
+
+        let books = [
+            BookState::read_at(&self.orderbook_aevo, now),
+            BookState::read_at(&self.orderbook_dxdy, now),
+        ];
+        if let Err(violation) = self
+            .risk
+            .check_at(&opportunity, &self.portfolio, &books, now)
+        {
+            debug!(
+                "{}: {:?} of {} blocked by risk check at {now} : {violation}",
+                self.name, opportunity.direction, opportunity.net_profit
+            );
+            self.blocked += 1;
+            return;
+        }
+
+        self.opportunities += 1;
+        self.expected_p_l += opportunity.net_profit;
+        let pending = PendingTrade {
+            opportunity,
+            execute_at: now + self.latency,
+        };
+        if self.latency.is_zero() {
+            self.execute(pending);
+        } else {
+            self.pending = Some(pending);
+        }
+    }
+
+    fn execute(&mut self, pending: PendingTrade) {
+        let opportunity = &pending.opportunity;
+        let trade = match opportunity.direction.buy_venue() {
+            Venue::Aevo => self.trader.fill_at(
+                opportunity,
+                &self.orderbook_aevo,
+                &self.orderbook_dxdy,
+                pending.execute_at,
+            ),
+            Venue::Dxdy => self.trader.fill_at(
+                opportunity,
+                &self.orderbook_dxdy,
+                &self.orderbook_aevo,
+                pending.execute_at,
+            ),
+        };
+
+        let Some(trade) = trade else {
+            self.missed += 1;
+            return;
+        };
+        debug!(
+            "Trade {} of {} at {}: bought {} at {:?}, sold {} at {:?}, profit and loss : {}",
+            trade.id,
+            self.name,
+            trade.executed_at,
+            trade.buy.quantity,
+            trade.buy.avg_price,
+            trade.sell.quantity,
+            trade.sell.avg_price,
+            trade.realized_profit
+        );
+        for leg in [&trade.buy, &trade.sell] {
+            if let Some(fill) = Fill::from_leg(leg) {
+                self.portfolio.apply(&fill);
+            }
+        }
+        self.trades.push(trade);
+    }
+
+    fn p_l(&self) -> (Decimal, Decimal) {
+        (
+            self.portfolio.realized_p_l(),
+            self.portfolio.unrealized_p_l(),
+        )
+    }
+
+    fn report(&self) -> MarketReport {
+        let (realized, unrealized) = self.p_l();
+
+        MarketReport {
+            market: self.name.clone(),
+            opportunities: self.opportunities,
+            trades: self.trades.len(),
+            realized_p_l: realized,
+            final_p_l: realized + unrealized,
+        }
+    }
+}
+
+/// Replays recorded frames through the detection, sizing and risk logic of `main_loop`
+///
+/// Time is taken from receive times of frames, so results do not depend on replay speed.
+/// Every frame is applied to the book of its market, frames of other markets are skipped.
+/// Books are evaluated after every frame, no new opportunity is searched for on a pair while
+/// its trade waits for latency, just like the live loop waits for paper execution.
+/// Fills are simulated by `PaperTrader` against books as they are once latency elapsed.
+#[derive(Debug)]
+pub struct Backtester {
+    markets: Vec<MarketBacktest>,
+    capital: Decimal,
+    equity_interval: TimeDelta,
+    now: Option<DateTime<Utc>>,
+    start: Option<DateTime<Utc>>,
+    next_sample: Option<DateTime<Utc>>,
+    equity: Vec<EquityPoint>,
+    frames: u64,
+    rejected_frames: u64,
+}
+
+impl Backtester {
+    pub fn new(config: &Config) -> Result<Self> {
+        let markets = config
+            .markets()
+            .iter()
+            .map(|market| MarketBacktest::new(&config.for_market(market), market.name()))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            capital: markets.iter().map(|market| market.capital).sum(),
+            markets,
+            equity_interval: TimeDelta::seconds(config.backtest.equity_interval_secs as i64),
+            now: None,
+            start: None,
+            next_sample: None,
+            equity: Vec::new(),
+            frames: 0,
+            rejected_frames: 0,
+        })
+    }
+
+    ///Portfolio of the first market pair
+    pub fn portfolio(&self) -> &Portfolio {
+        &self.markets[0].portfolio
+    }
+
+    ///Applies frame to the book of its market and evaluates books at its receive time
+    pub fn process(&mut self, frame: &RecordedFrame) -> Result<()> {
+        //Frames are expected in order, late ones do not move the clock back
+        let now = self
+            .now
+            .map_or(frame.received_time, |now| now.max(frame.received_time));
+
+        //Equity of grid points passed since the previous frame did not change
+        self.sample_equity(now);
+
+        //Trades are filled against books as they were before this frame arrived
+        for market in &mut self.markets {
+            market.execute_due(now);
+        }
+
+        let received_at = Instant::now();
+        //Frame is decoded once, its market routes the update to the book
+        let applied = match FrameUpdate::decode(frame) {
+            Ok(update) => {
+                let target = update.market().and_then(|symbol| {
+                    self.markets
+                        .iter_mut()
+                        .find(|market| market.symbol(frame.venue) == symbol)
+                });
+                match target {
+                    Some(market) => market
+                        .apply(frame, update, received_at)
+                        .inspect_err(|_| market.reset(frame.venue)),
+                    //Frames not tied to a market or of untracked markets change no book
+                    None => Ok(()),
+                }
+            }
+            //Market of an unreadable frame is unknown, live feed would reset all books of the venue
+            Err(e) => {
+                for market in &mut self.markets {
+                    market.reset(frame.venue);
+                }
+                Err(e)
+            }
+        };
+        self.frames += 1;
+        self.now = Some(now);
+        self.start.get_or_insert(now);
+
+        //Live feed reconnects on such errors, books wait for the next snapshot
+        if let Err(e) = applied {
+            warn!("{} frame rejected at {now} : {e:#}", frame.venue);
+            self.rejected_frames += 1;
+        }
+
+        for market in &mut self.markets {
+            market.evaluate(now);
+        }
+
+        Ok(())
+    }
+
+    ///Executes trades still waiting for latency and summarizes the run
+    pub fn finish(mut self) -> BacktestReport {
+        //Books do not change after the last frame, so trades fill against them
+        for market in &mut self.markets {
+            if let Some(pending) = market.pending.take() {
+                market.execute(pending);
+            }
+        }
+        //Run closes on the next grid point, so the final equity keeps sample spacing
+        if let Some(now) = self.now {
+            self.sample_equity(now);
+        }
+        if let Some(next_sample) = self.next_sample {
+            self.push_equity(next_sample);
+        }
+
+        let mut trades: Vec<PaperTrade> = self
+            .markets
+            .iter()
+            .flat_map(|market| market.trades.iter().cloned())
+            .collect();
+        trades.sort_by_key(|trade| trade.executed_at);
+        let sum = |value: fn(&MarketBacktest) -> Decimal| -> Decimal {
+            self.markets.iter().map(value).sum()
+        };
+        let count =
+            |value: fn(&MarketBacktest) -> u64| -> u64 { self.markets.iter().map(value).sum() };
+
+        BacktestReport {
+            start: self.start,
+            end: self.now,
+            frames: self.frames,
+            rejected_frames: self.rejected_frames,
+            opportunities: count(|market| market.opportunities),
+            blocked: count(|market| market.blocked),
+            missed: count(|market| market.missed),
+            expected_p_l: sum(|market| market.expected_p_l),
+            realized_p_l: sum(|market| market.trader.realized_p_l()),
+            final_p_l: sum(|market| {
+                let (realized, unrealized) = market.p_l();
+                realized + unrealized
+            }),
+            fees: trades
+                .iter()
+                .map(|trade| trade.buy.fee + trade.sell.fee)
+                .sum(),
+            hit_rate: hit_rate(&trades),
+            max_drawdown: max_drawdown(&self.equity),
+            sharpe: sharpe_ratio(&self.equity, self.capital, self.equity_interval),
+            markets: self.markets.iter().map(MarketBacktest::report).collect(),
+            trades,
+            equity: self.equity,
+        }
+    }
+
+    /// Samples equity on the interval grid starting at the first frame
+    ///
+    /// Every grid point before `now` gets equity as it is after the frames received so far,
+    /// so equity is carried forward through quiet periods and samples stay evenly spaced
+    fn sample_equity(&mut self, now: DateTime<Utc>) {
+        let mut next_sample = *self.next_sample.get_or_insert(now);
+        while next_sample < now {
+            self.push_equity(next_sample);
+            next_sample += self.equity_interval;
+        }
+        self.next_sample = Some(next_sample);
+    }
+
+    ///Equity of all market pairs together
+    fn push_equity(&mut self, time: DateTime<Utc>) {
+        let (realized_p_l, unrealized_p_l) = self.markets.iter().map(MarketBacktest::p_l).fold(
+            (Decimal::ZERO, Decimal::ZERO),
+            |(realized, unrealized), (market_realized, market_unrealized)| {
+                (realized + market_realized, unrealized + market_unrealized)
+            },
+        );
+        let point = EquityPoint {
+            time,
+            realized_p_l,
+            unrealized_p_l,
+        };
+        match self.equity.last_mut() {
+            Some(last) if last.time == time => *last = point,
+            _ => self.equity.push(point),
+        }
+    }
+}
+
+///Replays frames in given order and reports the result
+pub fn run_backtest(
+    config: &Config,
+    frames: impl IntoIterator<Item = Result<RecordedFrame>>,
+) -> Result<BacktestReport> {
+    let mut backtester = Backtester::new(config)?;
+    for frame in frames {
+        backtester.process(&frame?)?;
+    }
+
+    Ok(backtester.finish())
+}
+
+///Merges recordings in timestamp order and backtests them
+pub fn backtest_recordings(config: &Config, paths: &[PathBuf]) -> Result<BacktestReport> {
+    info!("Backtesting {} recordings", paths.len());
+    run_backtest(config, MergedFrames::open(paths)?)
+}
+
+///Share of trades with positive profit, `None` without trades
+pub fn hit_rate(trades: &[PaperTrade]) -> Option<Decimal> {
+    if trades.is_empty() {
+        return None;
+    }
+    let winners = trades
+        .iter()
+        .filter(|trade| trade.realized_profit > Decimal::ZERO)
+        .count();
+
+    Some(Decimal::from(winners) / Decimal::from(trades.len()))
+}
+
+///Largest fall of profit and loss below its running peak
+pub fn max_drawdown(equity: &[EquityPoint]) -> Decimal {
+    let mut peak = Decimal::ZERO;
+    let mut drawdown = Decimal::ZERO;
+    for point in equity {
+        peak = peak.max(point.p_l());
+        drawdown = drawdown.max(peak - point.p_l());
+    }
+
+    drawdown
+}
+
+/// Annualized Sharpe ratio of returns between equity samples
+///
+/// Returns are profit and loss changes relative to `capital`, risk free rate is zero.
+/// `None` with less than two returns or when returns do not vary.
+pub fn sharpe_ratio(equity: &[EquityPoint], capital: Decimal, interval: TimeDelta) -> Option<f64> {
+    let capital = capital.to_f64().filter(|capital| *capital > 0.0)?;
+    let returns: Vec<f64> = equity
+        .windows(2)
+        .filter_map(|pair| (pair[1].p_l() - pair[0].p_l()).to_f64())
+        .map(|change| change / capital)
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+
+    let count = returns.len() as f64;
+    let mean = returns.iter().sum::<f64>() / count;
+    let variance = returns
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+    let deviation = variance.sqrt();
+    if deviation <= f64::EPSILON {
+        return None;
+    }
+
+    let periods_per_year = SECONDS_PER_YEAR / interval.num_seconds().max(1) as f64;
+    Some(mean / deviation * periods_per_year.sqrt())
+}
@@ -0,0 +1,24 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use arbitrage_bot::backtest::{backtest_recordings, BacktestCli};
+use clap::Parser;
+
+fn main() -> Result<()> {
+    let cli = BacktestCli::parse();
+    let config = cli.load_config()?;
+
+    env_logger::Builder::new()
+        .parse_filters(&config.log_level)
+        .init();
+
+    let report = backtest_recordings(&config, &cli.recordings)?;
+    println!("{report}");
+
+    if let Some(path) = &cli.output {
+        fs::write(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("Failed to write backtest report {}", path.display()))?;
+    }
+
+    Ok(())
+}
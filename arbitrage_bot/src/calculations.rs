@@ -1,155 +1,232 @@
-use std::{cmp, sync::Arc};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::sync::Arc;
 use tokio::sync::Mutex;
 
-///Price delta after arbitrage operation
-pub type PriceDelta = u64;
-
-use crate::{aevo::aevo_structs::OrderbookAEVO, dxdy::dxdy_structs::OrderbookDXDY};
-
-impl OrderbookAEVO {
-    ///Matches with all bids to buy as much asset as possible with balance
-    ///
-    /// Assuming that our sum is relatively small, small enough, to be fully spent
-    pub fn buy_as_much_as_possible(&self, balance: u64) -> u64 {
-        let mut asset_balance = 0;
-        let mut curr_balacne = balance;
-
-        for (price, (_, size, _)) in self.bids.iter().rev() {
-            if price * size <= curr_balacne {
-                asset_balance += size;
-                curr_balacne -= price * size;
-            } else {
-                asset_balance += curr_balacne / price;
-                curr_balacne = 0;
-            }
-
-            if curr_balacne == 0 {
-                break;
-            }
-        }
+use crate::{
+    fees::BPS,
+    instrument::{Price, Size},
+    orderbook::{BookFreshness, Orderbook},
+    sizing::{optimal_size, SizingResult, VenueTerms},
+    venue::{Side, Venue},
+};
+
+///Which venue we buy on and which we sell on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ArbitrageDirection {
+    BuyAevoSellDxdy,
+    BuyDxdySellAevo,
+}
 
-        asset_balance
+impl ArbitrageDirection {
+    pub fn from_venues(buy_venue: Venue, sell_venue: Venue) -> Option<Self> {
+        match (buy_venue, sell_venue) {
+            (Venue::Aevo, Venue::Dxdy) => Some(Self::BuyAevoSellDxdy),
+            (Venue::Dxdy, Venue::Aevo) => Some(Self::BuyDxdySellAevo),
+            _ => None,
+        }
     }
 
-    ///Matches with all asks to sell as much asset as possible with asset_balance
-    ///
-    /// Assuming that our sum is relatively small, small enough, to be fully spent
-    pub fn sell_as_much_as_possible(&self, asset_balance: u64) -> u64 {
-        let mut balance = 0;
-        let mut curr_asset_balacne = asset_balance;
-
-        for (price, (_, size, _)) in self.asks.iter() {
-            if *size <= curr_asset_balacne {
-                balance += size * price;
-                curr_asset_balacne -= size;
-            } else {
-                balance += curr_asset_balacne * price;
-                curr_asset_balacne = 0;
-            }
-
-            if curr_asset_balacne == 0 {
-                break;
-            }
+    pub fn buy_venue(&self) -> Venue {
+        match self {
+            Self::BuyAevoSellDxdy => Venue::Aevo,
+            Self::BuyDxdySellAevo => Venue::Dxdy,
         }
+    }
 
-        balance
+    pub fn sell_venue(&self) -> Venue {
+        match self {
+            Self::BuyAevoSellDxdy => Venue::Dxdy,
+            Self::BuyDxdySellAevo => Venue::Aevo,
+        }
     }
 }
 
-impl OrderbookDXDY {
-    ///Matches with all bids to buy as much asset as possible with balance
-    ///
-    /// Assuming that our sum is relatively small, small enough, to be fully spent
-    pub fn buy_as_much_as_possible(&self, balance: u64) -> u64 {
-        let mut asset_balance = 0;
-        let mut curr_balacne = balance;
-
-        for (price, (_, size)) in self.bids.iter().rev() {
-            if price * size <= curr_balacne {
-                asset_balance += size;
-                curr_balacne -= price * size;
-            } else {
-                asset_balance += curr_balacne / price;
-                curr_balacne = 0;
-            }
-
-            if curr_balacne == 0 {
-                break;
-            }
-        }
+///Single taker order of an arbitrage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ArbitrageLeg {
+    pub venue: Venue,
+    pub side: Side,
+    pub quantity: Size,
+    pub avg_price: Option<Price>,
+    pub worst_price: Option<Price>,
+    ///Quote amount paid or received before fees
+    pub notional: Decimal,
+    pub fee: Decimal,
+}
+
+/// Evaluated arbitrage between two venues
+///
+/// Profit is signed, negative values mean that arbitrage loses money
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitrageOpportunity {
+    pub direction: ArbitrageDirection,
+    pub buy: ArbitrageLeg,
+    pub sell: ArbitrageLeg,
+    ///Balance available for the buy leg including its fee
+    pub capital: Decimal,
+    ///Sell proceeds minus buy cost, before fees
+    pub gross_profit: Decimal,
+    pub fees: Decimal,
+    pub net_profit: Decimal,
+    ///Net profit in basis points of buy cost including its fee
+    pub net_edge_bps: Decimal,
+    ///Profit curve of the size search, empty when no size is profitable
+    pub sizing: SizingResult,
+    pub detected_at: DateTime<Utc>,
+}
+
+impl ArbitrageOpportunity {
+    pub fn is_profitable(&self) -> bool {
+        self.net_profit > Decimal::ZERO
+    }
+}
 
-        asset_balance
+/// Evaluates buying on one venue and selling bought asset on another
+///
+/// Trade is sized to maximise profit after taker fees within capital, inventory and order limits.
+/// When no size is profitable, whole capital is evaluated to report the signed loss.
+/// For simplicity sake we assume, that we want to have only USDC after operation,
+/// so asset that can not be sold is not valued.
+pub fn evaluate_direction<B: Orderbook, S: Orderbook>(
+    buy_book: &B,
+    buy_terms: &VenueTerms,
+    sell_book: &S,
+    sell_terms: &VenueTerms,
+    capital: Decimal,
+) -> Option<ArbitrageOpportunity> {
+    let direction = ArbitrageDirection::from_venues(buy_book.venue(), sell_book.venue())?;
+    let buy_fees = &buy_terms.fees;
+    let sell_fees = &sell_terms.fees;
+
+    //Buy leg can not spend more than USDC held on buy venue
+    let capital = buy_terms.buy_capital(capital);
+    if capital <= Decimal::ZERO {
+        return None;
     }
 
-    ///Matches with all asks to sell as much asset as possible with asset_balance
-    ///
-    /// Assuming that our sum is relatively small, small enough, to be fully spent
-    pub fn sell_as_much_as_possible(&self, asset_balance: u64) -> u64 {
-        let mut balance = 0;
-        let mut curr_asset_balacne = asset_balance;
-
-        for (price, (_, size)) in self.asks.iter() {
-            if *size <= curr_asset_balacne {
-                balance += size * price;
-                curr_asset_balacne -= size;
-            } else {
-                balance += curr_asset_balacne * price;
-                curr_asset_balacne = 0;
-            }
-
-            if curr_asset_balacne == 0 {
-                break;
-            }
+    let sizing = optimal_size(buy_book, buy_terms, sell_book, sell_terms, capital);
+
+    let buy_fill = if sizing.is_empty() {
+        let fill = buy_book.simulate_buy(capital / (Decimal::ONE + buy_fees.taker_rate()));
+        //Loss is reported only for quantity that could actually be sold
+        match sell_terms.max_sell_qty() {
+            Some(max_qty) if fill.filled_qty > max_qty => buy_book.simulate_buy_quantity(max_qty),
+            _ => fill,
         }
+    } else {
+        buy_book.simulate_buy_quantity(sizing.quantity)
+    };
+    let sell_fill = sell_book.simulate_sell(buy_fill.filled_qty);
 
-        balance
+    if buy_fill.is_empty() || sell_fill.is_empty() {
+        return None;
     }
+
+    let buy = ArbitrageLeg {
+        venue: buy_book.venue(),
+        side: Side::Buy,
+        quantity: buy_fill.filled_qty,
+        avg_price: buy_fill.avg_price,
+        worst_price: buy_fill.worst_price,
+        notional: buy_fill.notional,
+        fee: buy_fees.taker_fee(buy_fill.notional),
+    };
+    let sell = ArbitrageLeg {
+        venue: sell_book.venue(),
+        side: Side::Sell,
+        quantity: sell_fill.filled_qty,
+        avg_price: sell_fill.avg_price,
+        worst_price: sell_fill.worst_price,
+        notional: sell_fill.notional,
+        fee: sell_fees.taker_fee(sell_fill.notional),
+    };
+
+    let gross_profit = sell.notional - buy.notional;
+    let fees = buy.fee + sell.fee;
+    let net_profit = gross_profit - fees;
+
+    Some(ArbitrageOpportunity {
+        direction,
+        buy,
+        sell,
+        capital,
+        gross_profit,
+        fees,
+        net_profit,
+        net_edge_bps: (net_profit / (buy.notional + buy.fee) * BPS).round_dp(2),
+        sizing,
+        detected_at: Utc::now(),
+    })
 }
 
-pub async fn check_orderbooks(
-    orderbook_aevo: Arc<Mutex<OrderbookAEVO>>,
-    orderbook_dxdy: Arc<Mutex<OrderbookDXDY>>,
-    balance: u64,
-) -> (PriceDelta, i8) {
-    //For simplicity sake let`s assume, that we want to have only USDC after operation
-    //There is 2 possible variants
-    {
-        //Locking orderbooks
-        let orderbook_aevo = orderbook_aevo.lock().await;
-        let orderbook_dxdy = orderbook_dxdy.lock().await;
-        //Buy asset on AEVO sell on dXdY
-        let left_buy_right_sell = orderbook_dxdy
-            .sell_as_much_as_possible(orderbook_aevo.buy_as_much_as_possible(balance));
-        //Buy asset on dXdY sell on AEVO
-        let right_buy_left_sell = orderbook_aevo
-            .sell_as_much_as_possible(orderbook_dxdy.buy_as_much_as_possible(balance));
-
-        let left_right_delta;
-        let is_left_right_profitable;
-        let right_left_delta;
-        let is_right_left_profitable;
-
-        if balance > left_buy_right_sell {
-            left_right_delta = balance - left_buy_right_sell;
-            is_left_right_profitable = false;
-        } else {
-            left_right_delta = left_buy_right_sell;
-            is_left_right_profitable = true;
-        }
+///Searches for arbitrage between any two venues
+///
+/// Left and right books may belong to any venues implementing `Orderbook`.
+/// Returns the best direction after taker fees, `None` if books can not be compared
+/// because they are stale, too old or updated too far apart in time.
+pub async fn check_orderbooks<L: Orderbook, R: Orderbook>(
+    orderbook_left: Arc<Mutex<L>>,
+    orderbook_right: Arc<Mutex<R>>,
+    left_terms: &VenueTerms,
+    right_terms: &VenueTerms,
+    capital: Decimal,
+    freshness: BookFreshness,
+) -> Option<ArbitrageOpportunity> {
+    //Locking orderbooks
+    let orderbook_left = orderbook_left.lock().await;
+    let orderbook_right = orderbook_right.lock().await;
+
+    //Prices of books updated far apart may never have coexisted
+    if !freshness.allows(orderbook_left.timestamps(), orderbook_right.timestamps()) {
+        return None;
+    }
 
-        if balance > right_buy_left_sell {
-            right_left_delta = balance - right_buy_left_sell;
-            is_right_left_profitable = false;
-        } else {
-            right_left_delta = right_buy_left_sell;
-            is_right_left_profitable = true;
-        }
+    compare_orderbooks(
+        &*orderbook_left,
+        left_terms,
+        &*orderbook_right,
+        right_terms,
+        capital,
+    )
+}
 
-        match (is_left_right_profitable, is_right_left_profitable) {
-            (false, false) => (cmp::min(left_right_delta, right_left_delta), -1),
-            (true, true) => (cmp::max(left_right_delta, right_left_delta), 1),
-            (true, false) => (left_right_delta, 1),
-            (false, true) => (right_left_delta, 1),
-        }
+/// Best direction between two books, without any freshness limits
+///
+/// Shared by `check_orderbooks` and backtests, which check freshness at simulated time
+pub fn compare_orderbooks<L: Orderbook, R: Orderbook>(
+    orderbook_left: &L,
+    left_terms: &VenueTerms,
+    orderbook_right: &R,
+    right_terms: &VenueTerms,
+    capital: Decimal,
+) -> Option<ArbitrageOpportunity> {
+    //Stale books are waiting for resnapshot, nothing to compare
+    if orderbook_left.is_stale() || orderbook_right.is_stale() {
+        return None;
     }
+
+    //There is 2 possible variants
+    //Buy asset on left venue sell on right
+    let left_buy_right_sell = evaluate_direction(
+        orderbook_left,
+        left_terms,
+        orderbook_right,
+        right_terms,
+        capital,
+    );
+    //Buy asset on right venue sell on left
+    let right_buy_left_sell = evaluate_direction(
+        orderbook_right,
+        right_terms,
+        orderbook_left,
+        left_terms,
+        capital,
+    );
+
+    [left_buy_right_sell, right_buy_left_sell]
+        .into_iter()
+        .flatten()
+        .max_by_key(|opportunity| opportunity.net_profit)
 }
@@ -0,0 +1,679 @@
+use std::{collections::HashSet, path::PathBuf, str::FromStr};
+
+use anyhow::{ensure, Context, Result};
+use clap::Parser;
+use log::LevelFilter;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    aevo::{
+        aevo_auth::AEVOCredentials,
+        aevo_instrument::InstrumentAEVO,
+        aevo_order_client::{AEVOOrderError, OrderTransportAEVO},
+    },
+    backtest::BacktestConfig,
+    execution::ExecutionConfig,
+    fees::FeeSchedule,
+    instrument::InstrumentSpec,
+    paper_trading::PaperConfig,
+    portfolio::PortfolioConfig,
+    risk::RiskConfig,
+    sizing::VenueTerms,
+};
+
+pub const AEVO_ACCOUNT_ENV: &str = "AEVO_ACCOUNT";
+
+///Command line flags, override values from the config file
+#[derive(Debug, Clone, Default, Parser)]
+#[command(version, about = "AEVO / dXdY arbitrage bot")]
+pub struct Cli {
+    ///Path to TOML config file
+    #[arg(short, long)]
+    pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub aevo_ws_url: Option<String>,
+    ///AEVO instrument symbol, e.g. ETH-PERP, not allowed with [[markets]]
+    #[arg(long)]
+    pub aevo_instrument: Option<String>,
+    #[arg(long)]
+    pub dxdy_ws_url: Option<String>,
+    ///dXdY market, e.g. ETH-USD, not allowed with [[markets]]
+    #[arg(long)]
+    pub dxdy_market: Option<String>,
+    ///Capital in USDC deployed per arbitrage check
+    #[arg(long)]
+    pub capital: Option<Decimal>,
+    ///Maximum time between orderbook checkups without book updates, in milliseconds
+    #[arg(long)]
+    pub check_interval_ms: Option<u64>,
+    ///Delay coalescing bursts of book updates into one checkup, in milliseconds
+    #[arg(long)]
+    pub debounce_ms: Option<u64>,
+    ///Minimal profit in USDC to consider arbitrage
+    #[arg(long)]
+    pub min_profit: Option<Decimal>,
+    ///Log filter in env_logger format, e.g. info or arbitrage_bot=debug
+    #[arg(long)]
+    pub log_level: Option<String>,
+    ///Place real orders, requires venue credentials
+    #[arg(long)]
+    pub live: bool,
+    ///Record raw feed frames to this gzip file, later runs add numbered segments next to it
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+    ///Replay feed frames recorded with --record instead of connecting to venues
+    #[arg(long)]
+    pub replay: Option<PathBuf>,
+    ///Replay speed, 1 is real time, 0 is as fast as possible
+    #[arg(long)]
+    pub replay_speed: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AEVOConfig {
+    pub ws_url: String,
+    pub instrument: String,
+    pub tick_size: Decimal,
+    pub step_size: Decimal,
+    ///Falls back to `AEVO_API_KEY` and `AEVO_API_SECRET` when absent
+    pub credentials: Option<AEVOCredentials>,
+    pub fees: FeeSchedule,
+    ///Largest quantity of a single order
+    pub max_order_qty: Option<Decimal>,
+    pub rest_url: String,
+    ///Account address orders are placed for, falls back to `AEVO_ACCOUNT` when absent
+    pub account: Option<String>,
+    pub order_transport: OrderTransportAEVO,
+}
+
+impl Default for AEVOConfig {
+    fn default() -> Self {
+        Self {
+            ws_url: "wss://ws.aevo.xyz".to_string(),
+            instrument: "ETH-PERP".to_string(),
+            tick_size: dec!(0.01),
+            step_size: dec!(0.01),
+            credentials: None,
+            fees: FeeSchedule::aevo(),
+            max_order_qty: None,
+            rest_url: "https://api.aevo.xyz".to_string(),
+            account: None,
+            order_transport: OrderTransportAEVO::default(),
+        }
+    }
+}
+
+impl AEVOConfig {
+    pub fn spec(&self) -> InstrumentSpec {
+        InstrumentSpec::new(self.tick_size, self.step_size)
+    }
+
+    pub fn terms(&self) -> VenueTerms {
+        VenueTerms::new(self.fees.clone(), self.max_order_qty)
+    }
+
+    pub fn credentials(&self) -> Result<AEVOCredentials> {
+        match &self.credentials {
+            Some(credentials) => Ok(credentials.clone()),
+            None => Ok(AEVOCredentials::from_env()?),
+        }
+    }
+
+    pub fn account(&self) -> Result<String> {
+        self.account
+            .clone()
+            .or_else(|| std::env::var(AEVO_ACCOUNT_ENV).ok())
+            .filter(|account| !account.is_empty())
+            .ok_or_else(|| AEVOOrderError::MissingAccount.into())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DXDYConfig {
+    pub ws_url: String,
+    pub market: String,
+    pub tick_size: Decimal,
+    pub step_size: Decimal,
+    pub fees: FeeSchedule,
+    ///Largest quantity of a single order
+    pub max_order_qty: Option<Decimal>,
+    ///Validator REST endpoint receiving signed transactions
+    pub node_url: String,
+    pub indexer_url: String,
+    pub chain_id: String,
+    pub subaccount_number: u32,
+    ///Blocks an IOC order stays valid for
+    pub good_til_blocks: u32,
+    ///Seconds a limit order stays on the book
+    pub order_ttl_secs: u64,
+}
+
+impl Default for DXDYConfig {
+    fn default() -> Self {
+        Self {
+            ws_url: "wss://indexer.dydx.trade/v4/ws".to_string(),
+            market: "ETH-USD".to_string(),
+            tick_size: dec!(0.1),
+            step_size: dec!(0.001),
+            fees: FeeSchedule::dxdy(),
+            max_order_qty: None,
+            node_url: "https://dydx-ops-rest.kingnodes.com".to_string(),
+            indexer_url: "https://indexer.dydx.trade".to_string(),
+            chain_id: "dydx-mainnet-1".to_string(),
+            subaccount_number: 0,
+            good_til_blocks: 20,
+            order_ttl_secs: 60,
+        }
+    }
+}
+
+impl DXDYConfig {
+    pub fn spec(&self) -> InstrumentSpec {
+        InstrumentSpec::new(self.tick_size, self.step_size)
+    }
+
+    pub fn terms(&self) -> VenueTerms {
+        VenueTerms::new(self.fees.clone(), self.max_order_qty)
+    }
+}
+
+///Instrument of one venue within a market pair
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketVenueConfig {
+    ///AEVO instrument such as `BTC-PERP` or dXdY market such as `BTC-USD`
+    pub symbol: String,
+    pub tick_size: Decimal,
+    pub step_size: Decimal,
+    ///Largest quantity of a single order
+    #[serde(default)]
+    pub max_order_qty: Option<Decimal>,
+}
+
+/// Markets of both venues traded against each other
+///
+/// Every pair has its own books, inventory and risk limits, only the kill switch is shared.
+/// Balances and position limits are held in the base asset of the pair.
+/// When several pairs are listed, each must set `capital` and the balances of the
+/// trading mode, as well as `max_position` once `[risk]` limits positions,
+/// top level values are used only by a single pair.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketConfig {
+    pub aevo: MarketVenueConfig,
+    pub dxdy: MarketVenueConfig,
+    ///Falls back to top level `capital` for a single pair
+    #[serde(default)]
+    pub capital: Option<Decimal>,
+    ///Paper trading balances, fall back to balances of `[paper]` for a single pair
+    #[serde(default)]
+    pub paper: Option<PortfolioConfig>,
+    ///Live trading inventory, falls back to `[portfolio]` for a single pair
+    #[serde(default)]
+    pub portfolio: Option<PortfolioConfig>,
+    ///Position limit of the pair, falls back to `risk.max_position` for a single pair
+    #[serde(default)]
+    pub max_position: Option<Decimal>,
+}
+
+impl MarketConfig {
+    ///Name used in logs, e.g. `ETH-PERP/ETH-USD`
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.aevo.symbol, self.dxdy.symbol)
+    }
+}
+
+/// Bot configuration
+///
+/// Loaded from TOML file, CLI flags take precedence over file values
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub aevo: AEVOConfig,
+    pub dxdy: DXDYConfig,
+    pub capital: Decimal,
+    pub check_interval_ms: u64,
+    pub debounce_ms: u64,
+    pub min_profit: Decimal,
+    pub log_level: String,
+    ///Places orders on venues instead of paper trading
+    pub live: bool,
+    ///Gzip file receiving raw frames of both feeds
+    pub record: Option<PathBuf>,
+    ///Recording replayed instead of live feeds
+    pub replay: Option<PathBuf>,
+    pub replay_speed: f64,
+    pub paper: PaperConfig,
+    pub execution: ExecutionConfig,
+    ///Inventory the strategy may trade with in live mode
+    pub portfolio: PortfolioConfig,
+    pub risk: RiskConfig,
+    pub backtest: BacktestConfig,
+    ///Market pairs, the single pair of `aevo.instrument` and `dxdy.market` when empty
+    pub markets: Vec<MarketConfig>,
+    ///CLI flags selecting the single pair, they can not be combined with `markets`
+    #[serde(skip)]
+    market_flags: Vec<&'static str>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            aevo: AEVOConfig::default(),
+            dxdy: DXDYConfig::default(),
+            capital: dec!(1000),
+            check_interval_ms: 5000,
+            debounce_ms: 0,
+            min_profit: Decimal::ZERO,
+            log_level: "info".to_string(),
+            live: false,
+            record: None,
+            replay: None,
+            replay_speed: 1.0,
+            paper: PaperConfig::default(),
+            execution: ExecutionConfig::default(),
+            portfolio: PortfolioConfig::default(),
+            risk: RiskConfig::default(),
+            backtest: BacktestConfig::default(),
+            markets: Vec::new(),
+            market_flags: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    ///Reads config file if given, applies CLI overrides and validates result
+    pub fn load(cli: &Cli) -> Result<Self> {
+        let mut config = match &cli.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read config file {}", path.display()))?;
+                Self::from_toml(&text)
+                    .with_context(|| format!("Failed to parse config file {}", path.display()))?
+            }
+            None => Self::default(),
+        };
+
+        config.apply_cli(cli.clone());
+        config.validate().context("Invalid configuration")?;
+
+        Ok(config)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    ///Configured market pairs, or the pair of venue sections
+    pub fn markets(&self) -> Vec<MarketConfig> {
+        if !self.markets.is_empty() {
+            return self.markets.clone();
+        }
+
+        vec![MarketConfig {
+            aevo: MarketVenueConfig {
+                symbol: self.aevo.instrument.clone(),
+                tick_size: self.aevo.tick_size,
+                step_size: self.aevo.step_size,
+                max_order_qty: self.aevo.max_order_qty,
+            },
+            dxdy: MarketVenueConfig {
+                symbol: self.dxdy.market.clone(),
+                tick_size: self.dxdy.tick_size,
+                step_size: self.dxdy.step_size,
+                max_order_qty: self.dxdy.max_order_qty,
+            },
+            capital: None,
+            paper: None,
+            portfolio: None,
+            max_position: None,
+        }]
+    }
+
+    /// Configuration trading only `market`
+    ///
+    /// Venue sections, capital, balances and position limit are replaced by values of the pair,
+    /// so that components built for a single market can be reused for every pair
+    pub fn for_market(&self, market: &MarketConfig) -> Self {
+        let mut config = self.clone();
+
+        config.aevo.instrument = market.aevo.symbol.clone();
+        config.aevo.tick_size = market.aevo.tick_size;
+        config.aevo.step_size = market.aevo.step_size;
+        config.aevo.max_order_qty = market.aevo.max_order_qty;
+        config.dxdy.market = market.dxdy.symbol.clone();
+        config.dxdy.tick_size = market.dxdy.tick_size;
+        config.dxdy.step_size = market.dxdy.step_size;
+        config.dxdy.max_order_qty = market.dxdy.max_order_qty;
+        if let Some(capital) = market.capital {
+            config.capital = capital;
+        }
+        if let Some(paper) = &market.paper {
+            config.paper.aevo = paper.aevo;
+            config.paper.dxdy = paper.dxdy;
+        }
+        if let Some(portfolio) = &market.portfolio {
+            config.portfolio = portfolio.clone();
+        }
+        if let Some(max_position) = market.max_position {
+            config.risk.max_position = Some(max_position);
+        }
+        config.markets = vec![market.clone()];
+
+        config
+    }
+
+    fn apply_cli(&mut self, cli: Cli) {
+        if let Some(ws_url) = cli.aevo_ws_url {
+            self.aevo.ws_url = ws_url;
+        }
+        if let Some(instrument) = cli.aevo_instrument {
+            self.aevo.instrument = instrument;
+            self.market_flags.push("--aevo-instrument");
+        }
+        if let Some(ws_url) = cli.dxdy_ws_url {
+            self.dxdy.ws_url = ws_url;
+        }
+        if let Some(market) = cli.dxdy_market {
+            self.dxdy.market = market;
+            self.market_flags.push("--dxdy-market");
+        }
+        if let Some(capital) = cli.capital {
+            self.capital = capital;
+        }
+        if let Some(check_interval_ms) = cli.check_interval_ms {
+            self.check_interval_ms = check_interval_ms;
+        }
+        if let Some(debounce_ms) = cli.debounce_ms {
+            self.debounce_ms = debounce_ms;
+        }
+        if let Some(min_profit) = cli.min_profit {
+            self.min_profit = min_profit;
+        }
+        if let Some(log_level) = cli.log_level {
+            self.log_level = log_level;
+        }
+        if cli.live {
+            self.live = true;
+        }
+        if let Some(record) = cli.record {
+            self.record = Some(record);
+        }
+        if let Some(replay) = cli.replay {
+            self.replay = Some(replay);
+        }
+        if let Some(replay_speed) = cli.replay_speed {
+            self.replay_speed = replay_speed;
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        validate_ws_url("aevo.ws_url", &self.aevo.ws_url)?;
+        validate_ws_url("dxdy.ws_url", &self.dxdy.ws_url)?;
+        validate_http_url("aevo.rest_url", &self.aevo.rest_url)?;
+        validate_http_url("dxdy.node_url", &self.dxdy.node_url)?;
+        validate_http_url("dxdy.indexer_url", &self.dxdy.indexer_url)?;
+
+        InstrumentAEVO::from_str(&self.aevo.instrument).context("aevo.instrument")?;
+        ensure!(
+            !self.dxdy.market.is_empty(),
+            "dxdy.market must not be empty"
+        );
+
+        for (name, value) in [
+            ("aevo.tick_size", self.aevo.tick_size),
+            ("aevo.step_size", self.aevo.step_size),
+            ("dxdy.tick_size", self.dxdy.tick_size),
+            ("dxdy.step_size", self.dxdy.step_size),
+            ("capital", self.capital),
+        ] {
+            ensure!(
+                value > Decimal::ZERO,
+                "{name} must be positive, got {value}"
+            );
+        }
+
+        for (name, value) in [
+            ("aevo.max_order_qty", self.aevo.max_order_qty),
+            ("dxdy.max_order_qty", self.dxdy.max_order_qty),
+            ("risk.max_trade_notional", self.risk.max_trade_notional),
+            ("risk.max_position", self.risk.max_position),
+            ("risk.max_daily_loss", self.risk.max_daily_loss),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| Some((name, value?)))
+        {
+            ensure!(
+                value > Decimal::ZERO,
+                "{name} must be positive, got {value}"
+            );
+        }
+
+        ensure!(
+            (1..=20).contains(&self.dxdy.good_til_blocks),
+            "dxdy.good_til_blocks must be between 1 and 20, got {}",
+            self.dxdy.good_til_blocks
+        );
+        ensure!(
+            self.dxdy.order_ttl_secs > 0,
+            "dxdy.order_ttl_secs must be positive"
+        );
+
+        ensure!(
+            self.check_interval_ms > 0,
+            "check_interval_ms must be positive"
+        );
+        ensure!(
+            self.debounce_ms < self.check_interval_ms,
+            "debounce_ms must be less than check_interval_ms"
+        );
+        ensure!(
+            self.min_profit >= Decimal::ZERO,
+            "min_profit must not be negative, got {}",
+            self.min_profit
+        );
+
+        if self.replay.is_some() {
+            ensure!(!self.live, "live trading can not run on replayed feeds");
+            ensure!(
+                self.record.is_none(),
+                "replayed feeds can not be recorded again"
+            );
+        }
+        ensure!(
+            self.replay_speed.is_finite() && self.replay_speed >= 0.0,
+            "replay_speed must not be negative, got {}",
+            self.replay_speed
+        );
+
+        ensure!(
+            self.paper.slippage_bps >= Decimal::ZERO,
+            "paper.slippage_bps must not be negative, got {}",
+            self.paper.slippage_bps
+        );
+        for (name, balance) in [
+            ("paper.aevo", self.paper.aevo),
+            ("paper.dxdy", self.paper.dxdy),
+            ("portfolio.aevo", self.portfolio.aevo),
+            ("portfolio.dxdy", self.portfolio.dxdy),
+        ] {
+            ensure!(
+                balance.usdc >= Decimal::ZERO && balance.base >= Decimal::ZERO,
+                "{name} balances must not be negative, got {balance:?}"
+            );
+        }
+
+        ensure!(
+            self.execution.correction_slippage_bps >= Decimal::ZERO,
+            "execution.correction_slippage_bps must not be negative, got {}",
+            self.execution.correction_slippage_bps
+        );
+        ensure!(
+            self.execution.poll_interval_ms > 0,
+            "execution.poll_interval_ms must be positive"
+        );
+
+        ensure!(
+            self.risk.max_book_age_ms > 0,
+            "risk.max_book_age_ms must be positive"
+        );
+        ensure!(
+            self.risk.max_book_skew_ms != Some(0),
+            "risk.max_book_skew_ms must be positive"
+        );
+        ensure!(
+            self.risk.min_edge_bps >= Decimal::ZERO,
+            "risk.min_edge_bps must not be negative, got {}",
+            self.risk.min_edge_bps
+        );
+
+        ensure!(
+            self.backtest.equity_interval_secs > 0,
+            "backtest.equity_interval_secs must be positive"
+        );
+
+        ensure!(
+            self.markets.is_empty() || self.market_flags.is_empty(),
+            "{} can not be combined with [[markets]] of the config file",
+            self.market_flags.join(", ")
+        );
+        let mut aevo_symbols = HashSet::new();
+        let mut dxdy_symbols = HashSet::new();
+        for (index, market) in self.markets.iter().enumerate() {
+            validate_market(market).with_context(|| format!("markets[{index}]"))?;
+            //Pairs falling back to top level values would each trade the whole collateral
+            if self.markets.len() > 1 {
+                let (name, inventory) = if self.live {
+                    ("portfolio", &market.portfolio)
+                } else {
+                    ("paper", &market.paper)
+                };
+                ensure!(
+                    market.capital.is_some() && inventory.is_some(),
+                    "markets[{index}] must set capital and {name}, several markets can not share top level collateral"
+                );
+                //Top level limit is in units of a single base asset
+                ensure!(
+                    self.risk.max_position.is_none() || market.max_position.is_some(),
+                    "markets[{index}] must set max_position, risk.max_position does not fit every base asset"
+                );
+            }
+            ensure!(
+                aevo_symbols.insert(&market.aevo.symbol),
+                "AEVO instrument {} is listed twice in markets",
+                market.aevo.symbol
+            );
+            ensure!(
+                dxdy_symbols.insert(&market.dxdy.symbol),
+                "dXdY market {} is listed twice in markets",
+                market.dxdy.symbol
+            );
+        }
+
+        self.aevo.fees.validate().context("aevo.fees")?;
+        self.dxdy.fees.validate().context("dxdy.fees")?;
+
+        validate_log_level(&self.log_level)?;
+
+        Ok(())
+    }
+}
+
+fn validate_market(market: &MarketConfig) -> Result<()> {
+    InstrumentAEVO::from_str(&market.aevo.symbol).context("aevo.symbol")?;
+    ensure!(
+        !market.dxdy.symbol.is_empty(),
+        "dxdy.symbol must not be empty"
+    );
+
+    for (name, value) in [
+        ("aevo.tick_size", Some(market.aevo.tick_size)),
+        ("aevo.step_size", Some(market.aevo.step_size)),
+        ("aevo.max_order_qty", market.aevo.max_order_qty),
+        ("dxdy.tick_size", Some(market.dxdy.tick_size)),
+        ("dxdy.step_size", Some(market.dxdy.step_size)),
+        ("dxdy.max_order_qty", market.dxdy.max_order_qty),
+        ("capital", market.capital),
+        ("max_position", market.max_position),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?)))
+    {
+        ensure!(
+            value > Decimal::ZERO,
+            "{name} must be positive, got {value}"
+        );
+    }
+
+    for (name, balances) in [("paper", &market.paper), ("portfolio", &market.portfolio)] {
+        let Some(balances) = balances else {
+            continue;
+        };
+        for balance in [balances.aevo, balances.dxdy] {
+            ensure!(
+                balance.usdc >= Decimal::ZERO && balance.base >= Decimal::ZERO,
+                "{name} balances must not be negative, got {balance:?}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+///Checks env_logger directives such as `info`, `arbitrage_bot` or `info,arbitrage_bot=debug`
+fn validate_log_level(value: &str) -> Result<()> {
+    for directive in value.split(',').filter(|directive| !directive.is_empty()) {
+        match directive.split_once('=') {
+            Some((module, level)) => {
+                ensure!(
+                    is_module_path(module),
+                    "log_level {value:?} has invalid module {module:?}"
+                );
+                LevelFilter::from_str(level)
+                    .with_context(|| format!("log_level {value:?} has invalid level {level:?}"))?;
+            }
+            //Bare directive is either a level or a module logged at every level
+            None => ensure!(
+                LevelFilter::from_str(directive).is_ok() || is_module_path(directive),
+                "log_level {value:?} has invalid directive {directive:?}"
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+///Rust module path such as `arbitrage_bot::feed`
+fn is_module_path(value: &str) -> bool {
+    value.split("::").all(|segment| {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+fn validate_ws_url(name: &str, value: &str) -> Result<()> {
+    validate_url(name, value, &["ws", "wss"])
+}
+
+fn validate_http_url(name: &str, value: &str) -> Result<()> {
+    validate_url(name, value, &["http", "https"])
+}
+
+fn validate_url(name: &str, value: &str, schemes: &[&str]) -> Result<()> {
+    let url = Url::parse(value).with_context(|| format!("{name} {value:?} is not a valid URL"))?;
+
+    ensure!(
+        schemes.contains(&url.scheme()),
+        "{name} must use {} scheme, got {value:?}",
+        schemes.join(" or ")
+    );
+
+    Ok(())
+}
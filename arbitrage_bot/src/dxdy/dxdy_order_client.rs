@@ -0,0 +1,385 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::Utc;
+use log::debug;
+use prost::Message;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+use crate::{
+    config::DXDYConfig,
+    order::{OrderClient, OrderReport, OrderRequest, TimeInForce},
+    venue::{Side, Venue},
+};
+
+use super::{
+    dxdy_order_structs::{
+        AccountResponseDXDY, BroadcastRequestDXDY, BroadcastResponseDXDY, FillsResponseDXDY,
+        HeightResponseDXDY, MarketParamsDXDY, OrderResponseDXDY, PerpetualMarketsResponseDXDY,
+    },
+    dxdy_proto::{
+        Any, AuthInfo, CancelGoodTil, Fee, GoodTil, ModeInfo, ModeInfoSingle, MsgCancelOrder,
+        MsgPlaceOrder, Order, OrderId, OrderSideProto, PubKey, SignDoc, SignerInfo, SubaccountId,
+        TimeInForceProto, TxBody, TxRaw, MSG_CANCEL_ORDER_TYPE_URL, MSG_PLACE_ORDER_TYPE_URL,
+        ORDER_FLAGS_LONG_TERM, ORDER_FLAGS_SHORT_TERM, SECP256K1_PUB_KEY_TYPE_URL,
+        SIGN_MODE_DIRECT,
+    },
+    dxdy_wallet::DXDYWallet,
+};
+
+const BROADCAST_MODE: &str = "BROADCAST_MODE_SYNC";
+
+#[derive(Debug, Error)]
+pub enum DXDYOrderError {
+    #[error("dXdY market {0} not found")]
+    MarketNotFound(String),
+    #[error("dXdY rejected transaction with code {code}: {log}")]
+    Rejected { code: u32, log: String },
+}
+
+/// Places orders on dYdX v4 chain
+///
+/// Orders are signed with wallet key and broadcast through a validator REST endpoint,
+/// their state is read back from the indexer.
+/// IOC orders are short term orders valid for `good_til_blocks` blocks,
+/// limit orders are long term orders valid for `order_ttl_secs`.
+#[derive(Debug)]
+pub struct DXDYOrderClient {
+    http: Client,
+    node_url: String,
+    indexer_url: String,
+    chain_id: String,
+    market: String,
+    subaccount_number: u32,
+    good_til_blocks: u32,
+    order_ttl: Duration,
+    params: MarketParamsDXDY,
+    wallet: DXDYWallet,
+    account_number: u64,
+    ///Locked for the whole broadcast, so signed sequences are never reused.
+    ///Shared by clients of all markets of the account
+    sequence: Arc<Mutex<u64>>,
+}
+
+impl DXDYOrderClient {
+    ///Fetches market parameters and account state required to sign orders
+    pub async fn connect(config: &DXDYConfig, wallet: DXDYWallet) -> Result<Self> {
+        let http = Client::new();
+        let node_url = config.node_url.trim_end_matches('/').to_string();
+        let indexer_url = config.indexer_url.trim_end_matches('/').to_string();
+
+        let params = fetch_market_params(&http, &indexer_url, &config.market).await?;
+
+        let account: AccountResponseDXDY = get_json(
+            &http,
+            &format!(
+                "{node_url}/cosmos/auth/v1beta1/accounts/{}",
+                wallet.address()
+            ),
+        )
+        .await?;
+        let account_number = account
+            .account
+            .account_number
+            .parse()
+            .context("Invalid dXdY account number")?;
+        let sequence = account
+            .account
+            .sequence
+            .parse()
+            .context("Invalid dXdY account sequence")?;
+
+        debug!(
+            "dXdY order client for {} on {}, account {account_number}, sequence {sequence}",
+            wallet.address(),
+            config.market
+        );
+
+        Ok(Self {
+            http,
+            node_url,
+            indexer_url,
+            chain_id: config.chain_id.clone(),
+            market: config.market.clone(),
+            subaccount_number: config.subaccount_number,
+            good_til_blocks: config.good_til_blocks,
+            order_ttl: Duration::from_secs(config.order_ttl_secs),
+            params,
+            wallet,
+            account_number,
+            sequence: Arc::new(Mutex::new(sequence)),
+        })
+    }
+
+    /// Client of another market of the same account
+    ///
+    /// Account sequence is shared, so orders of both clients never reuse it
+    pub async fn for_market(&self, config: &DXDYConfig) -> Result<Self> {
+        let params = fetch_market_params(&self.http, &self.indexer_url, &config.market).await?;
+
+        Ok(Self {
+            http: self.http.clone(),
+            node_url: self.node_url.clone(),
+            indexer_url: self.indexer_url.clone(),
+            chain_id: config.chain_id.clone(),
+            market: config.market.clone(),
+            subaccount_number: config.subaccount_number,
+            good_til_blocks: config.good_til_blocks,
+            order_ttl: Duration::from_secs(config.order_ttl_secs),
+            params,
+            wallet: self.wallet.clone(),
+            account_number: self.account_number,
+            sequence: self.sequence.clone(),
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        self.wallet.address()
+    }
+
+    fn order_id(&self, client_id: u32, time_in_force: TimeInForce) -> OrderId {
+        OrderId {
+            subaccount_id: Some(SubaccountId {
+                owner: self.wallet.address().to_string(),
+                number: self.subaccount_number,
+            }),
+            client_id,
+            order_flags: match time_in_force {
+                TimeInForce::Ioc => ORDER_FLAGS_SHORT_TERM,
+                TimeInForce::Gtc => ORDER_FLAGS_LONG_TERM,
+            },
+            clob_pair_id: self.params.clob_pair_id,
+        }
+    }
+
+    async fn height(&self) -> Result<u32> {
+        let response: HeightResponseDXDY =
+            get_json(&self.http, &format!("{}/v4/height", self.indexer_url)).await?;
+        response.height()
+    }
+
+    ///Expiry of a new order, block height for short term and unix time for long term orders
+    async fn good_til(&self, time_in_force: TimeInForce) -> Result<GoodTil> {
+        Ok(match time_in_force {
+            TimeInForce::Ioc => GoodTil::Block(self.height().await? + self.good_til_blocks),
+            TimeInForce::Gtc => GoodTil::BlockTime(
+                (Utc::now().timestamp() as u64 + self.order_ttl.as_secs()) as u32,
+            ),
+        })
+    }
+
+    ///Builds order message as it is signed and broadcast
+    pub async fn build_order(&self, request: &OrderRequest) -> Result<Order> {
+        Ok(Order {
+            order_id: Some(self.order_id(request.client_id, request.time_in_force)),
+            side: match request.side {
+                Side::Buy => OrderSideProto::Buy,
+                Side::Sell => OrderSideProto::Sell,
+            } as i32,
+            quantums: self.params.quantums(request.quantity)?,
+            subticks: self.params.subticks(request.price, request.side)?,
+            good_til: Some(self.good_til(request.time_in_force).await?),
+            time_in_force: match request.time_in_force {
+                TimeInForce::Ioc => TimeInForceProto::Ioc,
+                TimeInForce::Gtc => TimeInForceProto::Unspecified,
+            } as i32,
+            reduce_only: request.reduce_only,
+            client_metadata: 0,
+        })
+    }
+
+    /// Signs transaction with a single message and broadcasts it
+    ///
+    /// Short term orders are not sequence checked by validators, so sequence only
+    /// advances after long term messages. Returns transaction hash.
+    async fn broadcast(&self, message: Any, long_term: bool) -> Result<String> {
+        let mut sequence = self.sequence.lock().await;
+
+        let body_bytes = TxBody {
+            messages: vec![message],
+            memo: String::new(),
+            timeout_height: 0,
+        }
+        .encode_to_vec();
+        let auth_info_bytes = AuthInfo {
+            signer_infos: vec![SignerInfo {
+                public_key: Some(Any::pack(
+                    SECP256K1_PUB_KEY_TYPE_URL,
+                    &PubKey {
+                        key: self.wallet.public_key(),
+                    },
+                )),
+                mode_info: Some(ModeInfo {
+                    single: Some(ModeInfoSingle {
+                        mode: SIGN_MODE_DIRECT,
+                    }),
+                }),
+                sequence: *sequence,
+            }],
+            //Order messages are free of gas fees on dYdX
+            fee: Some(Fee {
+                amount: Vec::new(),
+                gas_limit: 0,
+            }),
+        }
+        .encode_to_vec();
+        let sign_doc = SignDoc {
+            body_bytes: body_bytes.clone(),
+            auth_info_bytes: auth_info_bytes.clone(),
+            chain_id: self.chain_id.clone(),
+            account_number: self.account_number,
+        };
+        let tx = TxRaw {
+            body_bytes,
+            auth_info_bytes,
+            signatures: vec![self.wallet.sign(&sign_doc.encode_to_vec())],
+        };
+
+        let request = BroadcastRequestDXDY {
+            tx_bytes: BASE64.encode(tx.encode_to_vec()),
+            mode: BROADCAST_MODE.to_string(),
+        };
+        let response: BroadcastResponseDXDY = self
+            .http
+            .post(format!("{}/cosmos/tx/v1beta1/txs", self.node_url))
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("Invalid dXdY broadcast response")?;
+
+        let tx_response = response.tx_response;
+        if tx_response.code != 0 {
+            return Err(DXDYOrderError::Rejected {
+                code: tx_response.code,
+                log: tx_response.raw_log,
+            }
+            .into());
+        }
+
+        if long_term {
+            *sequence += 1;
+        }
+
+        Ok(tx_response.txhash)
+    }
+}
+
+#[async_trait]
+impl OrderClient for DXDYOrderClient {
+    fn venue(&self) -> Venue {
+        Venue::Dxdy
+    }
+
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderReport> {
+        let order = self.build_order(request).await?;
+        let message = Any::pack(
+            MSG_PLACE_ORDER_TYPE_URL,
+            &MsgPlaceOrder { order: Some(order) },
+        );
+
+        let txhash = self
+            .broadcast(message, request.time_in_force == TimeInForce::Gtc)
+            .await
+            .with_context(|| format!("Failed to place dXdY order {}", request.client_id))?;
+
+        Ok(OrderReport::pending(Venue::Dxdy, txhash, request))
+    }
+
+    async fn cancel_order(&self, order: &OrderReport) -> Result<()> {
+        let good_til = match self.good_til(order.time_in_force).await? {
+            GoodTil::Block(block) => CancelGoodTil::Block(block),
+            GoodTil::BlockTime(time) => CancelGoodTil::BlockTime(time),
+        };
+        let message = Any::pack(
+            MSG_CANCEL_ORDER_TYPE_URL,
+            &MsgCancelOrder {
+                order_id: Some(self.order_id(order.client_id, order.time_in_force)),
+                good_til: Some(good_til),
+            },
+        );
+
+        self.broadcast(message, order.time_in_force == TimeInForce::Gtc)
+            .await
+            .with_context(|| format!("Failed to cancel dXdY order {}", order.client_id))?;
+
+        Ok(())
+    }
+
+    async fn query_order(&self, order: &OrderReport) -> Result<OrderReport> {
+        let account_query = format!(
+            "address={}&subaccountNumber={}",
+            self.wallet.address(),
+            self.subaccount_number
+        );
+
+        let orders: Vec<OrderResponseDXDY> = get_json(
+            &self.http,
+            &format!(
+                "{}/v4/orders?{account_query}&ticker={}",
+                self.indexer_url, self.market
+            ),
+        )
+        .await?;
+
+        //Indexer has not seen the order yet
+        let Some(indexed) = orders
+            .into_iter()
+            .find(|indexed| indexed.client_id == order.client_id.to_string())
+        else {
+            return Ok(order.clone());
+        };
+
+        let fills: FillsResponseDXDY = get_json(
+            &self.http,
+            &format!(
+                "{}/v4/fills?{account_query}&market={}&marketType=PERPETUAL",
+                self.indexer_url, self.market
+            ),
+        )
+        .await?;
+
+        Ok(OrderReport {
+            status: indexed.order_status()?,
+            filled_qty: indexed.filled_qty()?,
+            avg_price: fills.avg_price(&indexed.id)?,
+            order_id: indexed.id,
+            ..order.clone()
+        })
+    }
+}
+
+async fn fetch_market_params(
+    http: &Client,
+    indexer_url: &str,
+    market: &str,
+) -> Result<MarketParamsDXDY> {
+    let markets: PerpetualMarketsResponseDXDY = get_json(
+        http,
+        &format!("{indexer_url}/v4/perpetualMarkets?ticker={market}"),
+    )
+    .await?;
+
+    Ok(*markets
+        .markets
+        .get(market)
+        .ok_or_else(|| DXDYOrderError::MarketNotFound(market.to_string()))?)
+}
+
+async fn get_json<T: DeserializeOwned>(http: &Client, url: &str) -> Result<T> {
+    http.get(url)
+        .send()
+        .await
+        .with_context(|| format!("Request to {url} failed"))?
+        .error_for_status()?
+        .json()
+        .await
+        .with_context(|| format!("Invalid response from {url}"))
+}
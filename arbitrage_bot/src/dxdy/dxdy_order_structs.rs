@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use anyhow::{ensure, Context, Result};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    instrument::{parse_decimal, Price, Size},
+    order::OrderStatus,
+    venue::Side,
+};
+
+///Quote amounts on dYdX chain are in millionths of USDC
+const QUOTE_QUANTUMS_ATOMIC_RESOLUTION: i32 = -6;
+
+/// Integer representation rules of a perpetual market
+///
+/// Sizes are sent as base quantums, prices as subticks per quantum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarketParamsDXDY {
+    #[serde(deserialize_with = "deserialize_u32_string")]
+    pub clob_pair_id: u32,
+    pub atomic_resolution: i32,
+    pub quantum_conversion_exponent: i32,
+    pub step_base_quantums: u64,
+    pub subticks_per_tick: u64,
+}
+
+impl MarketParamsDXDY {
+    ///Size in base quantums, rounded down to step
+    pub fn quantums(&self, size: Size) -> Result<u64> {
+        let quantums = (size * pow10(-self.atomic_resolution)).floor();
+        to_multiple(quantums, self.step_base_quantums, RoundingStrategy::ToZero)
+            .with_context(|| format!("Size {size} can not be represented in quantums"))
+    }
+
+    ///Price in subticks, buys round up and sells round down to a tick
+    pub fn subticks(&self, price: Price, side: Side) -> Result<u64> {
+        let exponent = self.atomic_resolution
+            - self.quantum_conversion_exponent
+            - QUOTE_QUANTUMS_ATOMIC_RESOLUTION;
+        let strategy = match side {
+            Side::Buy => RoundingStrategy::AwayFromZero,
+            Side::Sell => RoundingStrategy::ToZero,
+        };
+        to_multiple(price * pow10(exponent), self.subticks_per_tick, strategy)
+            .with_context(|| format!("Price {price} can not be represented in subticks"))
+    }
+}
+
+fn pow10(exponent: i32) -> Decimal {
+    if exponent >= 0 {
+        Decimal::from(10u64.pow(exponent as u32))
+    } else {
+        Decimal::new(1, exponent.unsigned_abs())
+    }
+}
+
+fn to_multiple(value: Decimal, step: u64, strategy: RoundingStrategy) -> Result<u64> {
+    ensure!(step > 0, "step must be positive");
+    let step = Decimal::from(step);
+    let steps = (value / step).round_dp_with_strategy(0, strategy);
+    let value = u64::try_from(steps * step)?;
+    ensure!(value > 0, "value rounds to zero");
+    Ok(value)
+}
+
+fn deserialize_u32_string<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u32, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    value.parse().map_err(serde::de::Error::custom)
+}
+
+///Response of indexer `/v4/perpetualMarkets`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PerpetualMarketsResponseDXDY {
+    pub markets: HashMap<String, MarketParamsDXDY>,
+}
+
+///Response of indexer `/v4/height`
+#[derive(Debug, Clone, Deserialize)]
+pub struct HeightResponseDXDY {
+    pub height: String,
+}
+
+impl HeightResponseDXDY {
+    pub fn height(&self) -> Result<u32> {
+        self.height
+            .parse()
+            .with_context(|| format!("Invalid block height {:?}", self.height))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BaseAccountDXDY {
+    pub address: String,
+    pub account_number: String,
+    pub sequence: String,
+}
+
+///Response of node `/cosmos/auth/v1beta1/accounts/{address}`
+#[derive(Debug, Clone, Deserialize)]
+pub struct AccountResponseDXDY {
+    pub account: BaseAccountDXDY,
+}
+
+///Body of node `/cosmos/tx/v1beta1/txs`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastRequestDXDY {
+    pub tx_bytes: String,
+    pub mode: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TxResponseDXDY {
+    pub code: u32,
+    pub txhash: String,
+    #[serde(default)]
+    pub raw_log: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BroadcastResponseDXDY {
+    pub tx_response: TxResponseDXDY,
+}
+
+///Order as reported by indexer `/v4/orders`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderResponseDXDY {
+    pub id: String,
+    pub client_id: String,
+    pub size: String,
+    pub total_filled: String,
+    pub status: String,
+}
+
+impl OrderResponseDXDY {
+    pub fn filled_qty(&self) -> Result<Size> {
+        parse_decimal(&self.total_filled)
+    }
+
+    pub fn order_status(&self) -> Result<OrderStatus> {
+        let filled = self.filled_qty()?;
+        Ok(match self.status.as_str() {
+            "FILLED" => OrderStatus::Filled,
+            "CANCELED" | "BEST_EFFORT_CANCELED" => OrderStatus::Canceled,
+            _ if filled > Decimal::ZERO => OrderStatus::PartiallyFilled,
+            _ => OrderStatus::Open,
+        })
+    }
+}
+
+///Fill as reported by indexer `/v4/fills`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FillDXDY {
+    pub order_id: Option<String>,
+    pub price: String,
+    pub size: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FillsResponseDXDY {
+    pub fills: Vec<FillDXDY>,
+}
+
+impl FillsResponseDXDY {
+    ///Volume weighted price of fills of given order
+    pub fn avg_price(&self, order_id: &str) -> Result<Option<Price>> {
+        let mut size = Decimal::ZERO;
+        let mut notional = Decimal::ZERO;
+        for fill in self
+            .fills
+            .iter()
+            .filter(|fill| fill.order_id.as_deref() == Some(order_id))
+        {
+            let fill_size = parse_decimal(&fill.size)?;
+            size += fill_size;
+            notional += fill_size * parse_decimal(&fill.price)?;
+        }
+
+        Ok((size > Decimal::ZERO).then(|| notional / size))
+    }
+}
@@ -1,84 +1,231 @@
-use std::sync::Arc;
-
-use anyhow::Result;
-use futures::{SinkExt, StreamExt};
-use tokio::{net::TcpStream, sync::Mutex, task::JoinHandle};
-use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
-use url::Url;
-
-use super::dxdy_structs::{OrderbookDXDY, OrderbookDXDYResponse, OrderbookPayloadDXDY};
-
-pub struct DXDYWSAuthenticator<'a> {
-    pub wss_addr: &'a str,
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use log::warn;
+use tokio::{task::JoinHandle, time::timeout};
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::{
+    feed::{
+        spawn_supervised, BookUpdateNotifier, FeedHandle, FeedHealth, VenueBooks, FEED_IDLE_TIMEOUT,
+    },
+    feed_source::FeedSource,
+    orderbook::Orderbook,
+    recording::FeedRecorder,
+    venue::Venue,
+};
+
+use super::{
+    dxdy_sequence::{DXDYFeedStats, DXDYSequenceTracker, SequenceCheck},
+    dxdy_structs::{OrderbookDXDY, OrderbookDXDYResponse, OrderbookPayloadDXDY},
+};
+
+pub struct DXDYWSAuthenticator {
+    pub wss_addr: String,
 }
 
-impl<'a> DXDYWSAuthenticator<'a> {
-    pub fn new(wss_addr: &'a str) -> Self {
-        Self { wss_addr }
+impl DXDYWSAuthenticator {
+    pub fn new(wss_addr: &str) -> Self {
+        Self {
+            wss_addr: wss_addr.to_string(),
+        }
     }
 
-    pub async fn authenticate(&self) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
-        let (websocket, _) = tokio_tungstenite::connect_async(Url::parse(self.wss_addr)?).await?;
+    pub async fn authenticate(&self) -> Result<FeedSource> {
+        let source = FeedSource::connect(&self.wss_addr).await?;
 
         //No authorization requests detailed in docs
 
-        Ok(websocket)
+        Ok(source)
     }
 }
 
+/// Orderbook feed of dXdY markets
+///
+/// Every market is a separate subscription over one connection
 pub struct DXDYWSOrderbookFeed {
-    wss_socket_stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    source: FeedSource,
+    notifier: BookUpdateNotifier,
+    markets: Vec<String>,
+    sequence: DXDYSequenceTracker,
+    stats: Arc<DXDYFeedStats>,
+    recorder: Option<FeedRecorder>,
 }
 
 impl DXDYWSOrderbookFeed {
-    pub fn new(wss_socket_stream: WebSocketStream<MaybeTlsStream<TcpStream>>) -> Self {
-        Self { wss_socket_stream }
+    pub fn new(source: FeedSource, markets: &[impl AsRef<str>]) -> Self {
+        Self {
+            source,
+            notifier: BookUpdateNotifier::default(),
+            markets: markets
+                .iter()
+                .map(|market| market.as_ref().to_string())
+                .collect(),
+            sequence: DXDYSequenceTracker::default(),
+            stats: Arc::new(DXDYFeedStats::default()),
+            recorder: None,
+        }
     }
 
-    fn generate_orderbook_message(&self) -> Message {
-        Message::Text(serde_json::to_string(&OrderbookPayloadDXDY::default()).unwrap())
+    ///Shares recovery counters with the caller
+    pub fn with_stats(mut self, stats: Arc<DXDYFeedStats>) -> Self {
+        self.stats = stats;
+        self
     }
 
-    async fn subscribe_for_feed(&mut self) -> Result<()> {
-        let orderbook_message = self.generate_orderbook_message();
+    ///Notifies subscribers about every applied update
+    pub fn with_notifier(mut self, notifier: BookUpdateNotifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
 
-        self.wss_socket_stream.send(orderbook_message).await?;
+    ///Writes every frame to the recording
+    pub fn with_recorder(mut self, recorder: Option<FeedRecorder>) -> Self {
+        self.recorder = recorder;
+        self
+    }
+
+    fn generate_orderbook_message(market: &str) -> Message {
+        Message::Text(serde_json::to_string(&OrderbookPayloadDXDY::subscribe(market)).unwrap())
+    }
+
+    fn generate_unsubscribe_message(market: &str) -> Message {
+        Message::Text(serde_json::to_string(&OrderbookPayloadDXDY::unsubscribe(market)).unwrap())
+    }
+
+    pub async fn subscribe_for_feed(&mut self) -> Result<()> {
+        for market in self.markets.clone() {
+            let orderbook_message = Self::generate_orderbook_message(&market);
+
+            self.source.send(orderbook_message).await?;
+        }
 
         Ok(())
     }
 
-    pub async fn spawn_feed(
-        mut self,
-        orderbook_ref: Arc<Mutex<OrderbookDXDY>>,
-    ) -> Result<JoinHandle<Result<()>>> {
-        self.subscribe_for_feed().await?;
+    ///Fresh snapshot is only sent on subscription, so we have to subscribe again
+    async fn resubscribe(&mut self, market: &str) -> Result<()> {
+        self.stats.resubscriptions.fetch_add(1, Ordering::Relaxed);
+
+        let unsubscribe_message = Self::generate_unsubscribe_message(market);
+
+        self.source.send(unsubscribe_message).await?;
 
-        let handle = tokio::spawn(async move {
-            while let Some(resp) = self.wss_socket_stream.next().await {
-                if let Ok(message) = resp {
-                    match message {
-                        Message::Text(feed_text) => {
-                            let feed_decoded: OrderbookDXDYResponse =
-                                serde_json::from_str(&feed_text)?;
-
-                            {
-                                let mut guard = orderbook_ref.lock().await;
-                                guard.apply_changes(feed_decoded)
-                            }
+        let orderbook_message = Self::generate_orderbook_message(market);
+
+        self.source.send(orderbook_message).await?;
+
+        Ok(())
+    }
+
+    /// Processes feed frames until connection fails or is closed, updating the book of each market
+    ///
+    /// Message ids are shared by all subscriptions, so a lost message may belong to any market
+    /// and every book is resubscribed after a gap
+    pub async fn run(mut self, books: VenueBooks<OrderbookDXDY>) -> Result<()> {
+        while let Some(resp) = timeout(FEED_IDLE_TIMEOUT, self.source.next_frame())
+            .await
+            .context("dXdY feed is idle")?
+        {
+            let received_at = Instant::now();
+            let received_time = Utc::now();
+
+            match resp? {
+                Message::Text(feed_text) => {
+                    if let Some(recorder) = &self.recorder {
+                        recorder.record_or_warn(Venue::Dxdy, received_time, &feed_text);
+                    }
+
+                    let feed_decoded: OrderbookDXDYResponse = serde_json::from_str(&feed_text)?;
+
+                    let sequence = match feed_decoded.message_id {
+                        Some(message_id) => self.sequence.observe(message_id),
+                        None => SequenceCheck::InOrder,
+                    };
+
+                    let violation = match books.route(&feed_decoded) {
+                        Some((market, book)) => {
+                            let market = market.to_string();
+                            let mut guard = book.lock().await;
+                            guard.apply_changes(feed_decoded)?;
+                            guard.record_receipt(received_at, received_time);
+                            let violation = guard.flag_if_broken();
+                            drop(guard);
+                            self.notifier.notify(received_at);
+                            violation.map(|violation| (market, violation))
                         }
-                        Message::Ping(_) => {
-                            self.wss_socket_stream.send(Message::Pong(vec![])).await?
+                        //Connection acknowledgements carry no market
+                        None => None,
+                    };
+
+                    if !sequence.is_in_order() {
+                        self.stats.sequence_gaps.fetch_add(1, Ordering::Relaxed);
+                        warn!(
+                            "dXdY message sequence broken ({sequence:?}), resubscribing. Gaps so far: {}",
+                            self.stats.sequence_gaps()
+                        );
+                        //Books missed an update, they are only good after fresh snapshots
+                        for (market, book) in books.iter() {
+                            book.lock().await.set_stale(true);
+                            self.resubscribe(market).await?;
                         }
-                        _ => anyhow::bail!("Unavaited message format"),
+                    } else if let Some((market, violation)) = violation {
+                        warn!("dXdY {market} orderbook is stale ({violation:?}), resubscribing");
+                        self.resubscribe(&market).await?;
                     }
-                } else {
-                    anyhow::bail!("Failed to receive feed")
                 }
+                //Pings are answered by tungstenite itself
+                Message::Ping(_) | Message::Pong(_) => {}
+                Message::Close(frame) => anyhow::bail!("Connection closed: {frame:?}"),
+                _ => anyhow::bail!("Unavaited message format"),
             }
+        }
 
-            Ok(())
-        });
+        Ok(())
+    }
 
-        Ok(handle)
+    pub async fn spawn_feed(
+        mut self,
+        books: VenueBooks<OrderbookDXDY>,
+    ) -> Result<JoinHandle<Result<()>>> {
+        self.subscribe_for_feed().await?;
+
+        Ok(tokio::spawn(self.run(books)))
+    }
+
+    /// Spawns feed of every market in `books` which survives disconnects
+    ///
+    /// Every session connects and subscribes again, books are reset in between
+    pub fn spawn_supervised(
+        authenticator: DXDYWSAuthenticator,
+        books: VenueBooks<OrderbookDXDY>,
+        stats: Arc<DXDYFeedStats>,
+        recorder: Option<FeedRecorder>,
+    ) -> FeedHandle {
+        let authenticator = Arc::new(authenticator);
+        let markets: Vec<String> = books.markets().map(str::to_string).collect();
+
+        spawn_supervised("dXdY", books.clone(), move |health, notifier| {
+            let authenticator = authenticator.clone();
+            let books = books.clone();
+            let markets = markets.clone();
+            let stats = stats.clone();
+            let recorder = recorder.clone();
+
+            async move {
+                let mut feed = Self::new(authenticator.authenticate().await?, &markets)
+                    .with_stats(stats)
+                    .with_notifier(notifier)
+                    .with_recorder(recorder);
+                feed.subscribe_for_feed().await?;
+                health.send_replace(FeedHealth::Live);
+
+                feed.run(books).await
+            }
+        })
     }
 }
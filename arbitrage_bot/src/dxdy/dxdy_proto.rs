@@ -0,0 +1,206 @@
+//! Protobuf messages of dYdX v4 chain needed to place and cancel orders
+//!
+//! Field numbers follow `dydxprotocol/clob` and `cosmos/tx/v1beta1` definitions
+
+use prost::{Message, Oneof};
+
+pub const MSG_PLACE_ORDER_TYPE_URL: &str = "/dydxprotocol.clob.MsgPlaceOrder";
+pub const MSG_CANCEL_ORDER_TYPE_URL: &str = "/dydxprotocol.clob.MsgCancelOrder";
+pub const SECP256K1_PUB_KEY_TYPE_URL: &str = "/cosmos.crypto.secp256k1.PubKey";
+
+///`SIGN_MODE_DIRECT` of cosmos signing
+pub const SIGN_MODE_DIRECT: i32 = 1;
+
+///Short term orders live in memory of validators and expire by block height
+pub const ORDER_FLAGS_SHORT_TERM: u32 = 0;
+///Long term orders are stored on chain and expire by block time
+pub const ORDER_FLAGS_LONG_TERM: u32 = 64;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, prost::Enumeration)]
+#[repr(i32)]
+pub enum OrderSideProto {
+    Unspecified = 0,
+    Buy = 1,
+    Sell = 2,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, prost::Enumeration)]
+#[repr(i32)]
+pub enum TimeInForceProto {
+    ///Good til time
+    Unspecified = 0,
+    Ioc = 1,
+    PostOnly = 2,
+    FillOrKill = 3,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Any {
+    #[prost(string, tag = "1")]
+    pub type_url: String,
+    #[prost(bytes = "vec", tag = "2")]
+    pub value: Vec<u8>,
+}
+
+impl Any {
+    pub fn pack<M: Message>(type_url: &str, message: &M) -> Self {
+        Self {
+            type_url: type_url.to_string(),
+            value: message.encode_to_vec(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SubaccountId {
+    #[prost(string, tag = "1")]
+    pub owner: String,
+    #[prost(uint32, tag = "2")]
+    pub number: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct OrderId {
+    #[prost(message, optional, tag = "1")]
+    pub subaccount_id: Option<SubaccountId>,
+    #[prost(fixed32, tag = "2")]
+    pub client_id: u32,
+    #[prost(uint32, tag = "3")]
+    pub order_flags: u32,
+    #[prost(uint32, tag = "4")]
+    pub clob_pair_id: u32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Oneof)]
+pub enum GoodTil {
+    #[prost(uint32, tag = "5")]
+    Block(u32),
+    #[prost(fixed32, tag = "6")]
+    BlockTime(u32),
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Order {
+    #[prost(message, optional, tag = "1")]
+    pub order_id: Option<OrderId>,
+    #[prost(enumeration = "OrderSideProto", tag = "2")]
+    pub side: i32,
+    #[prost(uint64, tag = "3")]
+    pub quantums: u64,
+    #[prost(uint64, tag = "4")]
+    pub subticks: u64,
+    #[prost(oneof = "GoodTil", tags = "5, 6")]
+    pub good_til: Option<GoodTil>,
+    #[prost(enumeration = "TimeInForceProto", tag = "7")]
+    pub time_in_force: i32,
+    #[prost(bool, tag = "8")]
+    pub reduce_only: bool,
+    #[prost(uint32, tag = "9")]
+    pub client_metadata: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgPlaceOrder {
+    #[prost(message, optional, tag = "1")]
+    pub order: Option<Order>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Oneof)]
+pub enum CancelGoodTil {
+    #[prost(uint32, tag = "2")]
+    Block(u32),
+    #[prost(fixed32, tag = "3")]
+    BlockTime(u32),
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MsgCancelOrder {
+    #[prost(message, optional, tag = "1")]
+    pub order_id: Option<OrderId>,
+    #[prost(oneof = "CancelGoodTil", tags = "2, 3")]
+    pub good_til: Option<CancelGoodTil>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct PubKey {
+    #[prost(bytes = "vec", tag = "1")]
+    pub key: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TxBody {
+    #[prost(message, repeated, tag = "1")]
+    pub messages: Vec<Any>,
+    #[prost(string, tag = "2")]
+    pub memo: String,
+    #[prost(uint64, tag = "3")]
+    pub timeout_height: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ModeInfoSingle {
+    #[prost(int32, tag = "1")]
+    pub mode: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ModeInfo {
+    #[prost(message, optional, tag = "1")]
+    pub single: Option<ModeInfoSingle>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SignerInfo {
+    #[prost(message, optional, tag = "1")]
+    pub public_key: Option<Any>,
+    #[prost(message, optional, tag = "2")]
+    pub mode_info: Option<ModeInfo>,
+    #[prost(uint64, tag = "3")]
+    pub sequence: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Coin {
+    #[prost(string, tag = "1")]
+    pub denom: String,
+    #[prost(string, tag = "2")]
+    pub amount: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Fee {
+    #[prost(message, repeated, tag = "1")]
+    pub amount: Vec<Coin>,
+    #[prost(uint64, tag = "2")]
+    pub gas_limit: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AuthInfo {
+    #[prost(message, repeated, tag = "1")]
+    pub signer_infos: Vec<SignerInfo>,
+    #[prost(message, optional, tag = "2")]
+    pub fee: Option<Fee>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SignDoc {
+    #[prost(bytes = "vec", tag = "1")]
+    pub body_bytes: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub auth_info_bytes: Vec<u8>,
+    #[prost(string, tag = "3")]
+    pub chain_id: String,
+    #[prost(uint64, tag = "4")]
+    pub account_number: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TxRaw {
+    #[prost(bytes = "vec", tag = "1")]
+    pub body_bytes: Vec<u8>,
+    #[prost(bytes = "vec", tag = "2")]
+    pub auth_info_bytes: Vec<u8>,
+    #[prost(bytes = "vec", repeated, tag = "3")]
+    pub signatures: Vec<Vec<u8>>,
+}
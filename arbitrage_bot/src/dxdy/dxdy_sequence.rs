@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+///Outcome of checking message id of received message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {
+    InOrder,
+    ///Some messages between last seen and received were lost
+    Gap {
+        expected: u64,
+        received: u64,
+    },
+    ///Message is not newer than last seen one
+    OutOfOrder {
+        last: u64,
+        received: u64,
+    },
+}
+
+impl SequenceCheck {
+    pub fn is_in_order(&self) -> bool {
+        *self == SequenceCheck::InOrder
+    }
+}
+
+/// Tracks dXdY indexer message ids
+///
+/// Indexer increments `message_id` for every message sent over a connection,
+/// shared by all subscriptions, so any jump means that some update was lost.
+/// Tracker belongs to a single connection, every session starts a new one.
+#[derive(Debug, Clone, Default)]
+pub struct DXDYSequenceTracker {
+    last_message_id: Option<u64>,
+}
+
+impl DXDYSequenceTracker {
+    ///Checks received id against the last one, sequence continues from the newest id seen
+    pub fn observe(&mut self, message_id: u64) -> SequenceCheck {
+        let check = match self.last_message_id {
+            Some(last) if message_id <= last => SequenceCheck::OutOfOrder {
+                last,
+                received: message_id,
+            },
+            Some(last) if message_id != last + 1 => SequenceCheck::Gap {
+                expected: last + 1,
+                received: message_id,
+            },
+            _ => SequenceCheck::InOrder,
+        };
+
+        //Late messages do not move the sequence back
+        self.last_message_id = Some(
+            self.last_message_id
+                .map_or(message_id, |last| last.max(message_id)),
+        );
+
+        check
+    }
+}
+
+///Counters of dXdY feed recoveries, shared with the strategy loop
+#[derive(Debug, Default)]
+pub struct DXDYFeedStats {
+    pub sequence_gaps: AtomicU64,
+    pub resubscriptions: AtomicU64,
+}
+
+impl DXDYFeedStats {
+    pub fn sequence_gaps(&self) -> u64 {
+        self.sequence_gaps.load(Ordering::Relaxed)
+    }
+
+    pub fn resubscriptions(&self) -> u64 {
+        self.resubscriptions.load(Ordering::Relaxed)
+    }
+}
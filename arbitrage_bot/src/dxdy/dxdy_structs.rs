@@ -1,7 +1,14 @@
 use std::collections::BTreeMap;
 
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    instrument::{parse_decimal, InstrumentSpec, Price, Size},
+    orderbook::{BookTimestamps, Levels, Orderbook},
+    venue::Venue,
+};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderbookPayloadDXDY {
     r#type: String,
@@ -9,12 +16,20 @@ pub struct OrderbookPayloadDXDY {
     id: String,
 }
 
-impl Default for OrderbookPayloadDXDY {
-    fn default() -> Self {
+impl OrderbookPayloadDXDY {
+    pub fn subscribe(market: &str) -> Self {
         Self {
             r#type: "subscribe".to_string(),
             channel: "v4_orderbook".to_string(),
-            id: "ETH-USDC".to_string(),
+            id: market.to_string(),
+        }
+    }
+
+    ///Request to drop subscription, used before resubscribing for a fresh snapshot
+    pub fn unsubscribe(market: &str) -> Self {
+        Self {
+            r#type: "unsubscribe".to_string(),
+            ..Self::subscribe(market)
         }
     }
 }
@@ -25,65 +40,184 @@ pub struct PriceDataDXDY {
     size: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct OrderbookDXDYData {
+    #[serde(default)]
     bids: Vec<PriceDataDXDY>,
+    #[serde(default)]
     asks: Vec<PriceDataDXDY>,
 }
 
+///Single update or batch of updates for `channel_batch_data` messages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OrderbookDXDYContents {
+    Single(OrderbookDXDYData),
+    Batch(Vec<OrderbookDXDYData>),
+}
+
+impl Default for OrderbookDXDYContents {
+    fn default() -> Self {
+        Self::Single(OrderbookDXDYData::default())
+    }
+}
+
+impl OrderbookDXDYContents {
+    fn into_updates(self) -> Vec<OrderbookDXDYData> {
+        match self {
+            Self::Single(data) => vec![data],
+            Self::Batch(batch) => batch,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderbookDXDYResponse {
-    r#type: String,
-    contents: OrderbookDXDYData,
+    pub r#type: String,
+    ///Incremented by indexer for every message sent over the connection
+    #[serde(default)]
+    pub message_id: Option<u64>,
+    ///Subscription id, market name for orderbook channel
+    #[serde(default)]
+    pub id: Option<String>,
+    #[serde(default)]
+    contents: OrderbookDXDYContents,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+impl OrderbookDXDYResponse {
+    ///Whether message carries orderbook levels
+    pub fn is_orderbook_data(&self) -> bool {
+        matches!(
+            self.r#type.as_str(),
+            "subscribed" | "channel_data" | "channel_batch_data"
+        )
+    }
+}
+
+///Parsed dXdY price level: (price, size)
+pub type LevelDXDY = (Price, Size);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// dXdY Orderbook struct
-/// 
-/// Storing BTreeMaps to make insert operations fast 
+///
+/// Storing BTreeMaps to make insert operations fast
 pub struct OrderbookDXDY {
-    pub bids: BTreeMap<u64, (u64, u64)>,
-    pub asks: BTreeMap<u64, (u64, u64)>,
+    pub bids: BTreeMap<Price, LevelDXDY>,
+    pub asks: BTreeMap<Price, LevelDXDY>,
+    pub spec: InstrumentSpec,
+    ///Set until first snapshot and on integrity violation, cleared by next snapshot
+    pub stale: bool,
+    ///Orderbook messages carry no exchange time, only receipt is recorded
+    #[serde(skip)]
+    pub timestamps: BookTimestamps,
+}
+
+impl Default for OrderbookDXDY {
+    fn default() -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            spec: InstrumentSpec::default(),
+            stale: true,
+            timestamps: BookTimestamps::default(),
+        }
+    }
 }
 
 impl OrderbookDXDY {
-    pub fn apply_changes(&mut self, resp: OrderbookDXDYResponse) {
-        if resp.r#type == "subscribed" {
-            self.asks = resp
-                .contents
-                .asks
-                .into_iter()
-                .map(|item| {
-                    (
-                        item.price.clone().parse().unwrap(),
-                        (item.price.parse().unwrap(), item.size.parse().unwrap()),
-                    )
-                })
-                .collect();
-            self.bids = resp
-                .contents
-                .bids
-                .into_iter()
-                .map(|item| {
-                    (
-                        item.price.clone().parse().unwrap(),
-                        (item.price.parse().unwrap(), item.size.parse().unwrap()),
-                    )
-                })
-                .collect();
-        } else {
-            for item in resp.contents.asks {
-                self.asks.insert(
-                    item.price.clone().parse().unwrap(),
-                    (item.price.parse().unwrap(), item.size.parse().unwrap()),
-                );
+    pub fn new(spec: InstrumentSpec) -> Self {
+        Self {
+            spec,
+            ..Default::default()
+        }
+    }
+
+    fn parse_level(item: PriceDataDXDY) -> Result<(Price, LevelDXDY)> {
+        let price = parse_decimal(&item.price)?;
+        let size = parse_decimal(&item.size)?;
+
+        Ok((price, (price, size)))
+    }
+}
+
+impl Orderbook for OrderbookDXDY {
+    type Update = OrderbookDXDYResponse;
+
+    fn update_market(update: &OrderbookDXDYResponse) -> Option<&str> {
+        update.id.as_deref()
+    }
+
+    fn venue(&self) -> Venue {
+        Venue::Dxdy
+    }
+
+    fn apply_changes(&mut self, resp: OrderbookDXDYResponse) -> Result<()> {
+        match resp.r#type.as_str() {
+            "subscribed" => {
+                self.asks.clear();
+                self.bids.clear();
+                self.stale = false;
             }
-            for item in resp.contents.bids {
-                self.bids.insert(
-                    item.price.clone().parse().unwrap(),
-                    (item.price.parse().unwrap(), item.size.parse().unwrap()),
-                );
+            "channel_data" | "channel_batch_data" => {}
+            //Connection and unsubscription acknowledgements carry no levels
+            _ => return Ok(()),
+        }
+
+        for update in resp.contents.into_updates() {
+            //Zero size removes price level
+            for item in update.asks {
+                let (price, level) = Self::parse_level(item)?;
+                if level.1.is_zero() {
+                    self.asks.remove(&price);
+                } else {
+                    self.asks.insert(price, level);
+                }
+            }
+            for item in update.bids {
+                let (price, level) = Self::parse_level(item)?;
+                if level.1.is_zero() {
+                    self.bids.remove(&price);
+                } else {
+                    self.bids.insert(price, level);
+                }
             }
         }
+
+        Ok(())
+    }
+
+    fn spec(&self) -> &InstrumentSpec {
+        &self.spec
+    }
+
+    fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    fn set_stale(&mut self, stale: bool) {
+        self.stale = stale;
+    }
+
+    fn reset(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.stale = true;
+        self.timestamps = BookTimestamps::default();
+    }
+
+    fn timestamps(&self) -> &BookTimestamps {
+        &self.timestamps
+    }
+
+    fn timestamps_mut(&mut self) -> &mut BookTimestamps {
+        &mut self.timestamps
+    }
+
+    fn bid_levels(&self) -> Levels<'_> {
+        Box::new(self.bids.iter().map(|(price, (_, size))| (*price, *size)))
+    }
+
+    fn ask_levels(&self) -> Levels<'_> {
+        Box::new(self.asks.iter().map(|(price, (_, size))| (*price, *size)))
     }
 }
@@ -0,0 +1,79 @@
+use std::fmt;
+
+use anyhow::{Context, Result};
+use bech32::{ToBase32, Variant};
+use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+pub const DXDY_PRIVATE_KEY_ENV: &str = "DXDY_PRIVATE_KEY";
+
+///Bech32 prefix of dYdX chain addresses
+pub const ADDRESS_PREFIX: &str = "dydx";
+
+/// secp256k1 key of a dYdX account
+///
+/// Private key never appears in `Debug` output
+#[derive(Clone)]
+pub struct DXDYWallet {
+    signing_key: SigningKey,
+    address: String,
+}
+
+impl fmt::Debug for DXDYWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DXDYWallet")
+            .field("address", &self.address)
+            .field("signing_key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl DXDYWallet {
+    ///Wallet from hex encoded 32 byte private key, `0x` prefix is optional
+    pub fn from_hex(private_key: &str) -> Result<Self> {
+        let bytes = hex::decode(private_key.trim().trim_start_matches("0x"))
+            .context("dXdY private key is not valid hex")?;
+        let signing_key =
+            SigningKey::from_slice(&bytes).context("dXdY private key is not a secp256k1 key")?;
+
+        let public_key = signing_key.verifying_key().to_encoded_point(true);
+        let key_hash = Ripemd160::digest(Sha256::digest(public_key.as_bytes()));
+        let address = bech32::encode(ADDRESS_PREFIX, key_hash.to_base32(), Variant::Bech32)
+            .context("Failed to encode dXdY address")?;
+
+        Ok(Self {
+            signing_key,
+            address,
+        })
+    }
+
+    ///Reads private key from `DXDY_PRIVATE_KEY`
+    pub fn from_env() -> Result<Self> {
+        let private_key = std::env::var(DXDY_PRIVATE_KEY_ENV)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .with_context(|| format!("dXdY credential {DXDY_PRIVATE_KEY_ENV} is not configured"))?;
+
+        Self::from_hex(&private_key)
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    ///Compressed SEC1 public key
+    pub fn public_key(&self) -> Vec<u8> {
+        self.signing_key
+            .verifying_key()
+            .to_encoded_point(true)
+            .as_bytes()
+            .to_vec()
+    }
+
+    ///Low-S ECDSA signature of SHA-256 of the message, 64 bytes `r || s`
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        let signature: Signature = self.signing_key.sign(message);
+        signature.to_bytes().to_vec()
+    }
+}
@@ -1,2 +1,7 @@
+pub mod dxdy_order_client;
+pub mod dxdy_order_structs;
 pub mod dxdy_orderbook_feed;
+pub mod dxdy_proto;
+pub mod dxdy_sequence;
 pub mod dxdy_structs;
+pub mod dxdy_wallet;
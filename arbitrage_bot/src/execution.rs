@@ -0,0 +1,580 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use tokio::{sync::Mutex, time::Instant};
+
+use crate::{
+    calculations::{ArbitrageLeg, ArbitrageOpportunity},
+    fees::BPS,
+    instrument::{InstrumentSpec, Price, Size},
+    order::{OrderClient, OrderReport, OrderRequest},
+    orderbook::Orderbook,
+    portfolio::Portfolio,
+    venue::{Side, Venue},
+};
+
+///What to do when legs of an arbitrage filled different quantities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LegRiskPolicy {
+    ///Re-send lagging leg on its venue, unwind what remains after last retry
+    #[default]
+    Retry,
+    ///Close residual on the venue currently offering the best price
+    Hedge,
+    ///Reverse excess of the leg that filled more
+    Unwind,
+}
+
+///Execution settings of live trading
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutionConfig {
+    pub policy: LegRiskPolicy,
+    ///Correction orders sent for a single arbitrage before giving up
+    pub max_corrections: u32,
+    ///Price concession of correction orders
+    pub correction_slippage_bps: Decimal,
+    ///Time to wait for an order to reach final state before canceling it
+    pub fill_timeout_ms: u64,
+    pub poll_interval_ms: u64,
+    ///JSON lines file receiving every execution event
+    pub audit_log: Option<PathBuf>,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            policy: LegRiskPolicy::default(),
+            max_corrections: 3,
+            correction_slippage_bps: dec!(10),
+            fill_timeout_ms: 2000,
+            poll_interval_ms: 200,
+            audit_log: None,
+        }
+    }
+}
+
+///Kind of correction order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Correction {
+    Retry,
+    Hedge,
+    Unwind,
+    ///Reduce-only order closing a position after kill switch engaged
+    Flatten,
+}
+
+///Single step of an execution as written to audit log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum AuditEvent {
+    Submitted {
+        venue: Venue,
+        request: OrderRequest,
+        correction: Option<Correction>,
+    },
+    Rejected {
+        venue: Venue,
+        client_id: u32,
+        error: String,
+    },
+    Settled {
+        report: OrderReport,
+    },
+    CancelFailed {
+        report: OrderReport,
+        error: String,
+    },
+    ///Bought minus sold quantity, positive when we are long
+    Residual {
+        quantity: Size,
+    },
+    Finished {
+        outcome: ExecutionOutcome,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct AuditRecord<'a> {
+    execution_id: u64,
+    at: DateTime<Utc>,
+    event: &'a AuditEvent,
+}
+
+///How an execution ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ExecutionOutcome {
+    ///Both legs filled equal quantities
+    Complete,
+    ///Leg mismatch was closed by correction orders
+    Corrected,
+    ///Position remains open after all corrections
+    Unresolved,
+}
+
+///Result of executing an arbitrage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExecutionReport {
+    pub id: u64,
+    pub buy: Option<OrderReport>,
+    pub sell: Option<OrderReport>,
+    pub corrections: Vec<OrderReport>,
+    ///Bought minus sold quantity left open
+    pub residual: Size,
+    pub outcome: ExecutionOutcome,
+    pub events: Vec<AuditEvent>,
+}
+
+/// Executes both legs of arbitrage and handles leg risk
+///
+/// Legs are sent concurrently as IOC orders. After both are settled, quantity mismatch
+/// caused by partial fills or failed orders is closed according to `LegRiskPolicy`.
+/// Every step is recorded in the report and appended to the audit log.
+pub struct ExecutionCoordinator {
+    clients: HashMap<Venue, Box<dyn OrderClient>>,
+    config: ExecutionConfig,
+    audit_log: Option<File>,
+    next_execution_id: u64,
+    next_client_id: u32,
+}
+
+impl ExecutionCoordinator {
+    pub fn new(clients: Vec<Box<dyn OrderClient>>, config: ExecutionConfig) -> Result<Self> {
+        let audit_log = match &config.audit_log {
+            Some(path) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open audit log {}", path.display()))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            clients: clients
+                .into_iter()
+                .map(|client| (client.venue(), client))
+                .collect(),
+            config,
+            audit_log,
+            next_execution_id: 1,
+            //Client ids must not repeat across restarts
+            next_client_id: Utc::now().timestamp() as u32,
+        })
+    }
+
+    /// Executes opportunity, books are read when correction orders need current prices
+    ///
+    /// Returns `None` without sending any order when a leg has no price.
+    /// Errors are returned only for audit log failures or missing venue clients,
+    /// order failures are part of the report
+    pub async fn execute<L: Orderbook, R: Orderbook>(
+        &mut self,
+        opportunity: &ArbitrageOpportunity,
+        orderbook_left: Arc<Mutex<L>>,
+        orderbook_right: Arc<Mutex<R>>,
+    ) -> Result<Option<ExecutionReport>> {
+        let (Some(buy_request), Some(sell_request)) = (
+            self.leg_request(&opportunity.buy),
+            self.leg_request(&opportunity.sell),
+        ) else {
+            warn!(
+                "Execution of {:?} skipped, a leg has no price",
+                opportunity.direction
+            );
+            return Ok(None);
+        };
+
+        let mut execution = Execution::new(self.next_execution_id);
+        self.next_execution_id += 1;
+
+        let buy_client = self.client(opportunity.buy.venue)?;
+        let sell_client = self.client(opportunity.sell.venue)?;
+
+        //Sending both legs at once, so neither waits for the other
+        let (buy, sell) = tokio::join!(
+            self.submit(buy_client, buy_request, None),
+            self.submit(sell_client, sell_request, None)
+        );
+        execution.events.extend(buy.1.into_iter().chain(sell.1));
+        execution.buy = buy.0;
+        execution.sell = sell.0;
+
+        let mut residual = filled(&execution.buy) - filled(&execution.sell);
+        let mut corrections = 0;
+
+        while !residual.is_zero() && corrections < self.config.max_corrections {
+            execution
+                .events
+                .push(AuditEvent::Residual { quantity: residual });
+
+            let correction = self.correction(corrections);
+            let quotes = Quotes::read(&orderbook_left, &orderbook_right).await;
+            let Some((venue, request)) =
+                self.correction_request(correction, residual, opportunity, &quotes)
+            else {
+                warn!("No price for {correction:?} of residual {residual}");
+                break;
+            };
+
+            let (report, events) = self
+                .submit(self.client(venue)?, request, Some(correction))
+                .await;
+            execution.events.extend(events);
+            if let Some(report) = report {
+                match report.side {
+                    Side::Buy => residual += report.filled_qty,
+                    Side::Sell => residual -= report.filled_qty,
+                }
+                execution.corrections.push(report);
+            }
+            corrections += 1;
+        }
+
+        let outcome = match (residual.is_zero(), execution.corrections.is_empty()) {
+            (true, true) => ExecutionOutcome::Complete,
+            (true, false) => ExecutionOutcome::Corrected,
+            (false, _) => ExecutionOutcome::Unresolved,
+        };
+        if outcome == ExecutionOutcome::Unresolved {
+            warn!("Execution {} left residual {residual} open", execution.id);
+            execution
+                .events
+                .push(AuditEvent::Residual { quantity: residual });
+        }
+        execution.events.push(AuditEvent::Finished { outcome });
+
+        self.write_audit(execution.id, &execution.events)?;
+
+        Ok(Some(ExecutionReport {
+            id: execution.id,
+            buy: execution.buy,
+            sell: execution.sell,
+            corrections: execution.corrections,
+            residual,
+            outcome,
+            events: execution.events,
+        }))
+    }
+
+    /// Closes exposure opened by the bot on every venue with IOC orders
+    ///
+    /// Deposited inventory is kept, orders are reduce-only when they shrink the held position.
+    /// They cross the top of book by the correction concession,
+    /// venues without a quote on the closing side are left open
+    pub async fn flatten<L: Orderbook, R: Orderbook>(
+        &mut self,
+        portfolio: &Portfolio,
+        orderbook_left: Arc<Mutex<L>>,
+        orderbook_right: Arc<Mutex<R>>,
+    ) -> Result<Vec<OrderReport>> {
+        let mut execution = Execution::new(self.next_execution_id);
+        self.next_execution_id += 1;
+
+        let quotes = Quotes::read(&orderbook_left, &orderbook_right).await;
+        let concession = self.config.correction_slippage_bps / BPS;
+        let mut venues: Vec<Venue> = self.clients.keys().copied().collect();
+        venues.sort_by_key(|venue| venue.to_string());
+
+        for venue in venues {
+            let position = portfolio.position(venue);
+            let exposure = position.exposure();
+            let (side, price) = if exposure > Decimal::ZERO {
+                (Side::Sell, Decimal::ONE - concession)
+            } else if exposure < Decimal::ZERO {
+                (Side::Buy, Decimal::ONE + concession)
+            } else {
+                continue;
+            };
+            let Some(quote) = quotes.price(venue, side) else {
+                warn!("No {venue} price to flatten exposure {exposure}");
+                continue;
+            };
+
+            let request = OrderRequest {
+                reduce_only: match side {
+                    Side::Sell => position.position > Decimal::ZERO,
+                    Side::Buy => position.position < Decimal::ZERO,
+                },
+                ..OrderRequest::ioc(
+                    self.client_id(),
+                    side,
+                    exposure.abs(),
+                    quotes.limit(venue, side, quote * price),
+                )
+            };
+            let (report, events) = self
+                .submit(self.client(venue)?, request, Some(Correction::Flatten))
+                .await;
+            execution.events.extend(events);
+            execution.corrections.extend(report);
+        }
+
+        self.write_audit(execution.id, &execution.events)?;
+
+        Ok(execution.corrections)
+    }
+
+    fn client(&self, venue: Venue) -> Result<&dyn OrderClient> {
+        self.clients
+            .get(&venue)
+            .map(|client| client.as_ref())
+            .with_context(|| format!("No order client for {venue}"))
+    }
+
+    fn client_id(&mut self) -> u32 {
+        self.next_client_id = self.next_client_id.wrapping_add(1);
+        self.next_client_id
+    }
+
+    ///IOC at worst price of simulated fill, so the whole simulated quantity may fill
+    fn leg_request(&mut self, leg: &ArbitrageLeg) -> Option<OrderRequest> {
+        let price = leg.worst_price.or(leg.avg_price)?;
+        Some(OrderRequest::ioc(
+            self.client_id(),
+            leg.side,
+            leg.quantity,
+            price,
+        ))
+    }
+
+    ///Retry policy unwinds with its last correction
+    fn correction(&self, corrections: u32) -> Correction {
+        match self.config.policy {
+            LegRiskPolicy::Retry if corrections + 1 < self.config.max_corrections => {
+                Correction::Retry
+            }
+            LegRiskPolicy::Retry | LegRiskPolicy::Unwind => Correction::Unwind,
+            LegRiskPolicy::Hedge => Correction::Hedge,
+        }
+    }
+
+    /// Order closing residual
+    ///
+    /// Positive residual means we bought more than sold, so it is closed by selling
+    fn correction_request(
+        &mut self,
+        correction: Correction,
+        residual: Size,
+        opportunity: &ArbitrageOpportunity,
+        quotes: &Quotes,
+    ) -> Option<(Venue, OrderRequest)> {
+        let quantity = residual.abs();
+        let concession = self.config.correction_slippage_bps / BPS;
+
+        let (venue, side, price) = match correction {
+            //Lagging leg is sent again with worse price
+            Correction::Retry => {
+                let lagging = if residual > Decimal::ZERO {
+                    &opportunity.sell
+                } else {
+                    &opportunity.buy
+                };
+                (lagging.venue, lagging.side, lagging.worst_price?)
+            }
+            Correction::Hedge => {
+                let side = if residual > Decimal::ZERO {
+                    Side::Sell
+                } else {
+                    Side::Buy
+                };
+                let (venue, price) = quotes.best(side)?;
+                (venue, side, price)
+            }
+            //Leg that filled more is reversed on its own venue
+            Correction::Unwind => {
+                let (venue, side) = if residual > Decimal::ZERO {
+                    (opportunity.buy.venue, Side::Sell)
+                } else {
+                    (opportunity.sell.venue, Side::Buy)
+                };
+                (venue, side, quotes.price(venue, side)?)
+            }
+            //Positions are flattened by `flatten`, not as leg correction
+            Correction::Flatten => return None,
+        };
+
+        let price = match side {
+            Side::Buy => price * (Decimal::ONE + concession),
+            Side::Sell => price * (Decimal::ONE - concession),
+        };
+        let price = quotes.limit(venue, side, price);
+
+        Some((
+            venue,
+            OrderRequest::ioc(self.client_id(), side, quantity, price),
+        ))
+    }
+
+    ///Places order and waits until it is final, canceling it on timeout
+    async fn submit(
+        &self,
+        client: &dyn OrderClient,
+        request: OrderRequest,
+        correction: Option<Correction>,
+    ) -> (Option<OrderReport>, Vec<AuditEvent>) {
+        let venue = client.venue();
+        let mut events = vec![AuditEvent::Submitted {
+            venue,
+            request,
+            correction,
+        }];
+
+        let mut report = match client.place_order(&request).await {
+            Ok(report) => report,
+            Err(e) => {
+                warn!("{venue} order {} failed : {e:#}", request.client_id);
+                events.push(AuditEvent::Rejected {
+                    venue,
+                    client_id: request.client_id,
+                    error: format!("{e:#}"),
+                });
+                return (None, events);
+            }
+        };
+
+        let deadline = Instant::now() + Duration::from_millis(self.config.fill_timeout_ms);
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        while !report.status.is_final() && Instant::now() < deadline {
+            tokio::time::sleep(poll_interval).await;
+            match client.query_order(&report).await {
+                Ok(latest) => report = latest,
+                Err(e) => warn!("{venue} order {} query failed : {e:#}", report.order_id),
+            }
+        }
+
+        if !report.status.is_final() {
+            if let Err(e) = client.cancel_order(&report).await {
+                events.push(AuditEvent::CancelFailed {
+                    report: report.clone(),
+                    error: format!("{e:#}"),
+                });
+            }
+            //Fills may have happened before cancel
+            if let Ok(latest) = client.query_order(&report).await {
+                report = latest;
+            }
+        }
+
+        info!("{venue} order settled : {report:?}");
+        events.push(AuditEvent::Settled {
+            report: report.clone(),
+        });
+
+        (Some(report), events)
+    }
+
+    fn write_audit(&mut self, execution_id: u64, events: &[AuditEvent]) -> Result<()> {
+        if let Some(file) = &mut self.audit_log {
+            let at = Utc::now();
+            for event in events {
+                let line = serde_json::to_string(&AuditRecord {
+                    execution_id,
+                    at,
+                    event,
+                })?;
+                writeln!(file, "{line}").context("Failed to write audit log")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+///Execution in progress
+struct Execution {
+    id: u64,
+    buy: Option<OrderReport>,
+    sell: Option<OrderReport>,
+    corrections: Vec<OrderReport>,
+    events: Vec<AuditEvent>,
+}
+
+impl Execution {
+    fn new(id: u64) -> Self {
+        Self {
+            id,
+            buy: None,
+            sell: None,
+            corrections: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+fn filled(report: &Option<OrderReport>) -> Size {
+    report
+        .as_ref()
+        .map_or(Decimal::ZERO, |report| report.filled_qty)
+}
+
+///Top of both books at the moment of correction
+struct Quotes {
+    prices: HashMap<(Venue, Side), Price>,
+    specs: HashMap<Venue, InstrumentSpec>,
+}
+
+impl Quotes {
+    async fn read<L: Orderbook, R: Orderbook>(left: &Arc<Mutex<L>>, right: &Arc<Mutex<R>>) -> Self {
+        let mut quotes = Self {
+            prices: HashMap::new(),
+            specs: HashMap::new(),
+        };
+        quotes.insert(&*left.lock().await);
+        quotes.insert(&*right.lock().await);
+        quotes
+    }
+
+    ///Buying takes best ask, selling takes best bid
+    fn insert<B: Orderbook>(&mut self, book: &B) {
+        self.specs.insert(book.venue(), *book.spec());
+        if book.is_stale() {
+            return;
+        }
+        if let Some((price, _)) = book.best_ask() {
+            self.prices.insert((book.venue(), Side::Buy), price);
+        }
+        if let Some((price, _)) = book.best_bid() {
+            self.prices.insert((book.venue(), Side::Sell), price);
+        }
+    }
+
+    ///Rounds limit price to a tick of the venue, buys up and sells down
+    fn limit(&self, venue: Venue, side: Side, price: Price) -> Price {
+        match (self.specs.get(&venue), side) {
+            (Some(spec), Side::Buy) => spec.ceil_price(price),
+            (Some(spec), Side::Sell) => spec.floor_price(price),
+            (None, _) => price,
+        }
+    }
+
+    fn price(&self, venue: Venue, side: Side) -> Option<Price> {
+        self.prices.get(&(venue, side)).copied()
+    }
+
+    ///Venue with lowest ask for buying or highest bid for selling
+    fn best(&self, side: Side) -> Option<(Venue, Price)> {
+        let quotes = self
+            .prices
+            .iter()
+            .filter(|((_, quote_side), _)| *quote_side == side)
+            .map(|((venue, _), price)| (*venue, *price));
+        match side {
+            Side::Buy => quotes.min_by_key(|(_, price)| *price),
+            Side::Sell => quotes.max_by_key(|(_, price)| *price),
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::{
+    cmp,
+    collections::BTreeMap,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::de::DeserializeOwned;
+use tokio::{
+    sync::{watch, Mutex},
+    task::JoinHandle,
+};
+
+use crate::orderbook::{BookIntegrity, Orderbook};
+
+///Maximum silence on a feed before connection is considered dead
+pub const FEED_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+///Connection state of a supervised feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedHealth {
+    ///Connecting for the first time
+    Connecting,
+    ///Subscribed, book is updated in real time
+    Live,
+    ///Connection lost, book is reset, waiting before next attempt
+    Reconnecting { attempt: u32 },
+}
+
+impl FeedHealth {
+    pub fn is_live(&self) -> bool {
+        *self == FeedHealth::Live
+    }
+}
+
+///Shared handle used by feed sessions to report their state
+pub type FeedHealthReporter = Arc<watch::Sender<FeedHealth>>;
+
+/// Exponential backoff between reconnection attempts
+///
+/// Delay doubles after each failed attempt up to `max`
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+    attempt: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(500), Duration::from_secs(30))
+    }
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            current: initial,
+            attempt: 0,
+        }
+    }
+
+    ///Returns delay before next attempt and increases it
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = cmp::min(self.current * 2, self.max);
+        self.attempt += 1;
+        delay
+    }
+
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+        self.attempt = 0;
+    }
+}
+
+///Notification about applied orderbook update
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookUpdate {
+    ///Number of updates applied since feed start
+    pub sequence: u64,
+    ///Local time when the frame carrying the update was received
+    pub received_at: Instant,
+}
+
+/// Publishes book update notifications to the strategy loop
+///
+/// Backed by `watch` channel, so slow consumer only sees the latest update
+#[derive(Debug, Clone)]
+pub struct BookUpdateNotifier {
+    sender: Arc<watch::Sender<Option<BookUpdate>>>,
+}
+
+impl Default for BookUpdateNotifier {
+    fn default() -> Self {
+        Self::channel().0
+    }
+}
+
+impl BookUpdateNotifier {
+    pub fn channel() -> (Self, watch::Receiver<Option<BookUpdate>>) {
+        let (sender, receiver) = watch::channel(None);
+
+        (
+            Self {
+                sender: Arc::new(sender),
+            },
+            receiver,
+        )
+    }
+
+    pub fn notify(&self, received_at: Instant) {
+        self.sender.send_modify(|update| {
+            let sequence = update.map_or(0, |update| update.sequence) + 1;
+            *update = Some(BookUpdate {
+                sequence,
+                received_at,
+            });
+        });
+    }
+}
+
+/// Books of one venue keyed by market
+///
+/// A venue feed serves all markets over one connection and routes every update
+/// to the book of its market
+#[derive(Debug)]
+pub struct VenueBooks<B> {
+    books: BTreeMap<String, Arc<Mutex<B>>>,
+}
+
+impl<B> Clone for VenueBooks<B> {
+    fn clone(&self) -> Self {
+        Self {
+            books: self.books.clone(),
+        }
+    }
+}
+
+impl<B> Default for VenueBooks<B> {
+    fn default() -> Self {
+        Self {
+            books: BTreeMap::new(),
+        }
+    }
+}
+
+impl<B: Orderbook> VenueBooks<B> {
+    ///Books of a single market
+    pub fn single(market: &str, book: Arc<Mutex<B>>) -> Self {
+        let mut books = Self::default();
+        books.insert(market, book);
+        books
+    }
+
+    pub fn insert(&mut self, market: &str, book: Arc<Mutex<B>>) {
+        self.books.insert(market.to_string(), book);
+    }
+
+    pub fn get(&self, market: &str) -> Option<&Arc<Mutex<B>>> {
+        self.books.get(market)
+    }
+
+    ///Markets in alphabetical order
+    pub fn markets(&self) -> impl Iterator<Item = &str> {
+        self.books.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Arc<Mutex<B>>)> {
+        self.books
+            .iter()
+            .map(|(market, book)| (market.as_str(), book))
+    }
+
+    ///Market and book an update is applied to, `None` for updates of no tracked market
+    pub fn route(&self, update: &B::Update) -> Option<(&str, &Arc<Mutex<B>>)> {
+        self.books
+            .get_key_value(B::update_market(update)?)
+            .map(|(market, book)| (market.as_str(), book))
+    }
+
+    pub async fn reset_all(&self) {
+        for book in self.books.values() {
+            book.lock().await.reset();
+        }
+    }
+}
+
+/// Applies raw text frame to the book of its market
+///
+/// Frames without a market or of an untracked market are skipped and give `None`,
+/// otherwise returns the market with the integrity violation reported by `apply_update`
+pub async fn apply_routed_frame<B: Orderbook>(
+    books: &VenueBooks<B>,
+    text: &str,
+    received_at: Instant,
+    received_time: DateTime<Utc>,
+) -> Result<Option<(String, Option<BookIntegrity>)>>
+where
+    B::Update: DeserializeOwned,
+{
+    let update: B::Update = serde_json::from_str(text)?;
+    let Some((market, book)) = books.route(&update) else {
+        return Ok(None);
+    };
+
+    let violation = apply_update(&mut *book.lock().await, update, received_at, received_time)?;
+
+    Ok(Some((market.to_string(), violation)))
+}
+
+/// Applies decoded update to a book the way live feeds do
+///
+/// Returns integrity violation when the book has just become stale
+pub fn apply_update<B: Orderbook>(
+    book: &mut B,
+    update: B::Update,
+    received_at: Instant,
+    received_time: DateTime<Utc>,
+) -> Result<Option<BookIntegrity>> {
+    book.apply_changes(update)?;
+    book.record_receipt(received_at, received_time);
+
+    Ok(book.flag_if_broken())
+}
+
+///Running supervised feed
+pub struct FeedHandle {
+    pub handle: JoinHandle<()>,
+    pub health: watch::Receiver<FeedHealth>,
+    pub updates: watch::Receiver<Option<BookUpdate>>,
+}
+
+/// Runs feed sessions forever, reconnecting with exponential backoff
+///
+/// Session is expected to connect, authenticate, subscribe, report `FeedHealth::Live`
+/// and then process frames until connection fails, notifying about every applied update.
+/// Books are reset after every session, so that strategy never sees data from a dead connection.
+pub fn spawn_supervised<B, S, Fut>(
+    name: &'static str,
+    books: VenueBooks<B>,
+    mut session: S,
+) -> FeedHandle
+where
+    B: Orderbook + Send + 'static,
+    S: FnMut(FeedHealthReporter, BookUpdateNotifier) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    let (health_tx, health_rx) = watch::channel(FeedHealth::Connecting);
+    let health_tx = Arc::new(health_tx);
+    let (notifier, updates_rx) = BookUpdateNotifier::channel();
+
+    let handle = tokio::spawn(async move {
+        let mut backoff = Backoff::default();
+
+        loop {
+            let result = session(health_tx.clone(), notifier.clone()).await;
+
+            //Successful session resets backoff
+            if health_tx.borrow().is_live() {
+                backoff.reset();
+            }
+
+            match result {
+                Ok(()) => warn!("{name} feed closed by server"),
+                Err(err) => warn!("{name} feed failed: {err:#}"),
+            }
+
+            books.reset_all().await;
+
+            let delay = backoff.next_delay();
+            health_tx.send_replace(FeedHealth::Reconnecting {
+                attempt: backoff.attempt(),
+            });
+
+            info!(
+                "{name} feed reconnecting in {delay:?}, attempt {}",
+                backoff.attempt()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    });
+
+    FeedHandle {
+        handle,
+        health: health_rx,
+        updates: updates_rx,
+    }
+}
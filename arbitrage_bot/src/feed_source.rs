@@ -0,0 +1,126 @@
+use std::{fmt, path::Path, pin::Pin};
+
+use anyhow::{Context, Result};
+use futures::{
+    channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    sink,
+    stream::{self, BoxStream},
+    Sink, SinkExt, StreamExt,
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+use url::Url;
+
+use crate::{recording::FrameReader, venue::Venue};
+
+type FrameSink = Pin<Box<dyn Sink<Message, Error = anyhow::Error> + Send>>;
+
+/// Frames a venue feed runs over: incoming frames and a sink for requests
+///
+/// Feeds only see this type, so the same feed logic runs over a venue socket,
+/// a local mock server, an in-memory channel or a recording
+pub struct FeedSource {
+    frames: BoxStream<'static, Result<Message>>,
+    sink: FrameSink,
+}
+
+impl fmt::Debug for FeedSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FeedSource").finish_non_exhaustive()
+    }
+}
+
+impl FeedSource {
+    ///Connects to a websocket server, `ws` and `wss` are supported
+    pub async fn connect(url: &str) -> Result<Self> {
+        let (websocket, _) = tokio_tungstenite::connect_async(Url::parse(url)?)
+            .await
+            .with_context(|| format!("Failed to connect to {url}"))?;
+
+        Ok(Self::websocket(websocket))
+    }
+
+    pub fn websocket<S>(websocket: WebSocketStream<S>) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (sink, frames) = websocket.split();
+
+        Self {
+            frames: frames
+                .map(|frame| frame.context("Failed to receive feed"))
+                .boxed(),
+            sink: Box::pin(sink.sink_map_err(anyhow::Error::from)),
+        }
+    }
+
+    /// Source driven from memory by the returned peer
+    ///
+    /// Source ends once the peer is closed or dropped
+    pub fn channel() -> (Self, FeedPeer) {
+        let (frames_tx, frames_rx) = mpsc::unbounded();
+        let (sent_tx, sent_rx) = mpsc::unbounded();
+
+        let source = Self {
+            frames: frames_rx.map(Ok).boxed(),
+            sink: Box::pin(sent_tx.sink_map_err(|_| anyhow::anyhow!("Feed peer is gone"))),
+        };
+        let peer = FeedPeer {
+            frames: frames_tx,
+            sent: sent_rx,
+        };
+
+        (source, peer)
+    }
+
+    /// Frames recorded for `venue` with `FeedRecorder`, delivered without delay
+    ///
+    /// Requests sent by the feed are discarded, source ends with the recording
+    pub fn recording(path: &Path, venue: Venue) -> Result<Self> {
+        let frames = FrameReader::open(path)?.filter_map(move |frame| match frame {
+            Ok(frame) if frame.venue == venue => Some(Ok(Message::Text(frame.text))),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        });
+
+        Ok(Self {
+            frames: stream::iter(frames).boxed(),
+            sink: Box::pin(sink::drain().sink_map_err(|never| match never {})),
+        })
+    }
+
+    ///Next incoming frame, `None` once the source is closed
+    pub async fn next_frame(&mut self) -> Option<Result<Message>> {
+        self.frames.next().await
+    }
+
+    pub async fn send(&mut self, message: Message) -> Result<()> {
+        self.sink.send(message).await
+    }
+}
+
+///Other end of an in-memory `FeedSource`, plays the venue in tests
+pub struct FeedPeer {
+    ///Frames delivered to the feed
+    pub frames: UnboundedSender<Message>,
+    ///Requests sent by the feed
+    pub sent: UnboundedReceiver<Message>,
+}
+
+impl FeedPeer {
+    pub fn send_text(&self, text: &str) -> Result<()> {
+        self.frames
+            .unbounded_send(Message::Text(text.to_string()))
+            .context("Feed source is gone")
+    }
+
+    ///Ends the source after frames already sent, as if the venue closed connection
+    pub fn close(&self) {
+        self.frames.close_channel();
+    }
+
+    ///Next request sent by the feed, `None` once the source is dropped
+    pub async fn next_sent(&mut self) -> Option<Message> {
+        self.sent.next().await
+    }
+}
@@ -0,0 +1,129 @@
+use anyhow::{ensure, Result};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+
+///Basis points in one unit
+pub const BPS: Decimal = dec!(10000);
+
+/// Fee rates applied from given 30 day trading volume
+///
+/// Negative maker rate is a rebate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeTier {
+    pub min_volume: Decimal,
+    pub maker_bps: Decimal,
+    pub taker_bps: Decimal,
+}
+
+impl FeeTier {
+    pub fn new(min_volume: Decimal, maker_bps: Decimal, taker_bps: Decimal) -> Self {
+        Self {
+            min_volume,
+            maker_bps,
+            taker_bps,
+        }
+    }
+}
+
+/// Venue fee schedule
+///
+/// Tier is selected by our 30 day trading volume on the venue
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeSchedule {
+    pub tiers: Vec<FeeTier>,
+    #[serde(default)]
+    pub volume_30d: Decimal,
+}
+
+impl FeeSchedule {
+    ///Published AEVO perpetual fees
+    pub fn aevo() -> Self {
+        Self {
+            tiers: vec![FeeTier::new(Decimal::ZERO, dec!(3), dec!(5))],
+            volume_30d: Decimal::ZERO,
+        }
+    }
+
+    ///Published dYdX v4 fee tiers
+    pub fn dxdy() -> Self {
+        Self {
+            tiers: vec![
+                FeeTier::new(Decimal::ZERO, dec!(1), dec!(5)),
+                FeeTier::new(dec!(1_000_000), dec!(1), dec!(4.5)),
+                FeeTier::new(dec!(5_000_000), dec!(0.5), dec!(4)),
+                FeeTier::new(dec!(25_000_000), Decimal::ZERO, dec!(3.5)),
+                FeeTier::new(dec!(125_000_000), Decimal::ZERO, dec!(3)),
+                FeeTier::new(dec!(1_250_000_000), dec!(-0.7), dec!(2.5)),
+            ],
+            volume_30d: Decimal::ZERO,
+        }
+    }
+
+    ///Schedule without any fees
+    pub fn zero() -> Self {
+        Self {
+            tiers: vec![FeeTier::new(Decimal::ZERO, Decimal::ZERO, Decimal::ZERO)],
+            volume_30d: Decimal::ZERO,
+        }
+    }
+
+    ///Single tier schedule charging `taker_bps`, maker rate of current tier is kept
+    pub fn with_taker_bps(&self, taker_bps: Decimal) -> Self {
+        Self {
+            tiers: vec![FeeTier::new(
+                Decimal::ZERO,
+                self.tier().maker_bps,
+                taker_bps,
+            )],
+            volume_30d: Decimal::ZERO,
+        }
+    }
+
+    ///Tier matching current 30 day volume
+    pub fn tier(&self) -> FeeTier {
+        self.tiers
+            .iter()
+            .filter(|tier| tier.min_volume <= self.volume_30d)
+            .max_by_key(|tier| tier.min_volume)
+            .copied()
+            .unwrap_or(FeeTier::new(Decimal::ZERO, Decimal::ZERO, Decimal::ZERO))
+    }
+
+    ///Taker fee as a fraction of notional
+    pub fn taker_rate(&self) -> Decimal {
+        self.tier().taker_bps / BPS
+    }
+
+    ///Maker fee as a fraction of notional, negative for rebates
+    pub fn maker_rate(&self) -> Decimal {
+        self.tier().maker_bps / BPS
+    }
+
+    pub fn taker_fee(&self, notional: Decimal) -> Decimal {
+        notional * self.taker_rate()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        ensure!(!self.tiers.is_empty(), "fee schedule has no tiers");
+        ensure!(
+            self.tiers.iter().any(|tier| tier.min_volume.is_zero()),
+            "fee schedule has no tier starting at zero volume"
+        );
+        for tier in &self.tiers {
+            ensure!(
+                tier.taker_bps >= Decimal::ZERO,
+                "taker fee must not be negative, got {} bps",
+                tier.taker_bps
+            );
+            ensure!(
+                tier.maker_bps > -tier.taker_bps,
+                "maker rebate must be smaller than taker fee"
+            );
+        }
+
+        Ok(())
+    }
+}
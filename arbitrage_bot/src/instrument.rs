@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+
+///Exact price representation
+pub type Price = Decimal;
+
+///Exact quantity representation
+pub type Size = Decimal;
+
+///Parses decimal string received from venue without losing precision
+pub fn parse_decimal(value: &str) -> Result<Decimal> {
+    Decimal::from_str(value)
+        .or_else(|_| Decimal::from_scientific(value))
+        .with_context(|| format!("Invalid decimal value {value:?}"))
+}
+
+/// Trading rules of a single instrument
+///
+/// Prices are multiples of `tick_size`, quantities are multiples of `step_size`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstrumentSpec {
+    pub tick_size: Price,
+    pub step_size: Size,
+}
+
+impl Default for InstrumentSpec {
+    fn default() -> Self {
+        Self {
+            tick_size: dec!(0.01),
+            step_size: dec!(0.001),
+        }
+    }
+}
+
+impl InstrumentSpec {
+    pub fn new(tick_size: Price, step_size: Size) -> Self {
+        Self {
+            tick_size,
+            step_size,
+        }
+    }
+
+    ///Rounds price down to the closest tick
+    pub fn floor_price(&self, price: Price) -> Price {
+        floor_to_multiple(price, self.tick_size)
+    }
+
+    ///Rounds price up to the closest tick
+    pub fn ceil_price(&self, price: Price) -> Price {
+        ceil_to_multiple(price, self.tick_size)
+    }
+
+    ///Rounds quantity down to the closest tradable step
+    pub fn floor_size(&self, size: Size) -> Size {
+        floor_to_multiple(size, self.step_size)
+    }
+}
+
+fn floor_to_multiple(value: Decimal, multiple: Decimal) -> Decimal {
+    if multiple.is_zero() {
+        return value;
+    }
+    (value / multiple).round_dp_with_strategy(0, RoundingStrategy::ToNegativeInfinity) * multiple
+}
+
+fn ceil_to_multiple(value: Decimal, multiple: Decimal) -> Decimal {
+    if multiple.is_zero() {
+        return value;
+    }
+    (value / multiple).round_dp_with_strategy(0, RoundingStrategy::ToPositiveInfinity) * multiple
+}
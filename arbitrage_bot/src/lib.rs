@@ -1,76 +1,579 @@
-use std::sync::Arc;
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
-use log::info;
+use anyhow::{Context, Result};
+use log::{debug, error, info, warn};
+use rust_decimal::Decimal;
 use tokio::sync::Mutex;
 
 use crate::{
     aevo::{
+        aevo_order_client::AEVOOrderClient,
         aevo_orderbook_feed::{AEVOWSAuthenticator, AEVOWSOrderbookFeed},
+        aevo_signer::{AEVOSigner, SigningDomainAEVO},
         aevo_structs::OrderbookAEVO,
     },
     calculations::check_orderbooks,
+    config::Config,
     dxdy::{
+        dxdy_order_client::DXDYOrderClient,
         dxdy_orderbook_feed::{DXDYWSAuthenticator, DXDYWSOrderbookFeed},
+        dxdy_sequence::DXDYFeedStats,
         dxdy_structs::OrderbookDXDY,
+        dxdy_wallet::DXDYWallet,
     },
+    execution::ExecutionCoordinator,
+    feed::VenueBooks,
+    fees::FeeSchedule,
+    metrics::{FeedLatency, LatencyStats},
+    order::OrderClient,
+    paper_trading::{PaperTrade, PaperTrader},
+    portfolio::{Fill, Portfolio},
+    recording::{spawn_replay, FeedRecorder},
+    risk::{BookState, KillSwitch, RiskManager},
+    sizing::VenueTerms,
+    venue::Venue,
 };
 
 pub mod aevo;
+pub mod backtest;
 pub mod calculations;
+pub mod config;
 pub mod dxdy;
+pub mod execution;
+pub mod feed;
+pub mod feed_source;
+pub mod fees;
+pub mod instrument;
+pub mod metrics;
+pub mod order;
+pub mod orderbook;
+pub mod paper_trading;
+pub mod portfolio;
+pub mod recording;
+pub mod risk;
+pub mod sizing;
+pub mod venue;
 
-pub async fn main_loop() -> Result<()> {
-    env_logger::init();
+///Number of decisions between latency reports
+const LATENCY_REPORT_INTERVAL: u64 = 1000;
 
-    info!("Starting main loop");
+/// Books, accounts and statistics of one traded market pair
+///
+/// Built from `Config::for_market`, so components written for a single market are reused as is
+struct MarketLoop {
+    name: String,
+    config: Config,
+    aevo_terms: VenueTerms,
+    dxdy_terms: VenueTerms,
+    orderbook_aevo_ref: Arc<Mutex<OrderbookAEVO>>,
+    orderbook_dxdy_ref: Arc<Mutex<OrderbookDXDY>>,
+    portfolio: Portfolio,
+    paper_trader: PaperTrader,
+    coordinator: Option<ExecutionCoordinator>,
+    risk: RiskManager,
+    expected_p_l: Decimal,
+    ///dXdY books carry no exchange time, so only AEVO feed latency is measured
+    aevo_latency: FeedLatency,
+}
+
+impl MarketLoop {
+    fn new(
+        config: Config,
+        coordinator: Option<ExecutionCoordinator>,
+        kill_switch: KillSwitch,
+    ) -> Result<Self> {
+        let name = format!("{}/{}", config.aevo.instrument, config.dxdy.market);
+        let paper_trader = PaperTrader::new(
+            &config.paper,
+            config.aevo.fees.clone(),
+            config.dxdy.fees.clone(),
+        )?
+        .with_market(&name);
 
-    let mut cumulative_p_l = 0;
-    let balance = 1000;
+        //Live trading spends deposited inventory, paper trading the simulated account
+        let balances = if config.live {
+            (config.portfolio.aevo, config.portfolio.dxdy)
+        } else {
+            (config.paper.aevo, config.paper.dxdy)
+        };
 
-    let orderbook_aevo = OrderbookAEVO::default();
-    let orderbook_dxdy = OrderbookDXDY::default();
+        Ok(Self {
+            name,
+            aevo_terms: config.aevo.terms(),
+            dxdy_terms: config.dxdy.terms(),
+            orderbook_aevo_ref: Arc::new(Mutex::new(OrderbookAEVO::new(config.aevo.spec()))),
+            orderbook_dxdy_ref: Arc::new(Mutex::new(OrderbookDXDY::new(config.dxdy.spec()))),
+            portfolio: Portfolio::new([(Venue::Aevo, balances.0), (Venue::Dxdy, balances.1)]),
+            paper_trader,
+            coordinator,
+            //Every trade passes risk checks, kill switch is shared by all markets
+            risk: RiskManager::new(config.risk.clone(), kill_switch),
+            expected_p_l: Decimal::ZERO,
+            aevo_latency: FeedLatency::default(),
+            config,
+        })
+    }
 
-    //Sharing orderbooks between threads
-    let orderbook_aevo_ref = Arc::new(Mutex::new(orderbook_aevo));
-    let orderbook_dxdy_ref = Arc::new(Mutex::new(orderbook_dxdy));
+    fn fees(&self, venue: Venue) -> &FeeSchedule {
+        match venue {
+            Venue::Aevo => &self.aevo_terms.fees,
+            Venue::Dxdy => &self.dxdy_terms.fees,
+        }
+    }
 
-    let aevo_auth = AEVOWSAuthenticator::new("wss://ws.aevo.xyz");
-    let dxdy_auth = DXDYWSAuthenticator::new("wss://indexer.dydx.trade/v4/ws");
+    ///Closes open positions once the kill switch is engaged
+    async fn halt(&mut self) -> Result<()> {
+        error!(
+            "Trading of {} halted by kill switch, portfolio : {}",
+            self.name, self.portfolio
+        );
+        match &mut self.coordinator {
+            Some(coordinator) if self.config.risk.flatten_on_kill => {
+                let reports = coordinator
+                    .flatten(
+                        &self.portfolio,
+                        self.orderbook_aevo_ref.clone(),
+                        self.orderbook_dxdy_ref.clone(),
+                    )
+                    .await?;
+                for order in &reports {
+                    if let Some(fill) = Fill::from_report(order, self.fees(order.venue)) {
+                        self.portfolio.apply(&fill);
+                    }
+                }
+                info!(
+                    "Flattened positions of {}, portfolio : {}",
+                    self.name, self.portfolio
+                );
+            }
+            None if self.config.risk.flatten_on_kill => {
+                warn!("Paper positions of {} are not flattened", self.name)
+            }
+            _ => {}
+        }
 
-    //Authentication
-    let aevo_channel = aevo_auth.authenticate().await?;
-    let dxdy_channel = dxdy_auth.authenticate().await?;
+        Ok(())
+    }
 
-    let aevo_feeder = AEVOWSOrderbookFeed::new(aevo_channel);
-    let dxdy_feeder = DXDYWSOrderbookFeed::new(dxdy_channel);
+    ///Searches both books of the pair for arbitrage and trades it
+    async fn evaluate(&mut self) -> Result<()> {
+        //Positions are valued at current mids
+        {
+            let orderbook_aevo = self.orderbook_aevo_ref.lock().await;
+            self.portfolio.mark_book(&*orderbook_aevo);
+            self.aevo_latency.observe(&orderbook_aevo.timestamps);
+        }
+        {
+            let orderbook_dxdy = self.orderbook_dxdy_ref.lock().await;
+            self.portfolio.mark_book(&*orderbook_dxdy);
+        }
 
-    //Spawning feed tasks
-    //They update orderbooks in real time
-    aevo_feeder.spawn_feed(orderbook_aevo_ref.clone()).await?;
-    dxdy_feeder.spawn_feed(orderbook_dxdy_ref.clone()).await?;
+        //No arbitrage is searched while a paper trade waits for its latency
+        if self.paper_trader.pending().is_some() {
+            self.fill_paper_trade().await?;
+            if self.paper_trader.pending().is_some() {
+                return Ok(());
+            }
+        }
 
-    loop {
-        //Timeout between orderbook checkups
-        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-
-        //Search for arbitrage posibilities
-        let (delta, sign) = check_orderbooks(
-            orderbook_aevo_ref.clone(),
-            orderbook_dxdy_ref.clone(),
-            balance,
+        //Search for arbitrage posibilities within inventory we still hold
+        let opportunity = check_orderbooks(
+            self.orderbook_aevo_ref.clone(),
+            self.orderbook_dxdy_ref.clone(),
+            &self
+                .aevo_terms
+                .clone()
+                .with_inventory(self.portfolio.available(Venue::Aevo)),
+            &self
+                .dxdy_terms
+                .clone()
+                .with_inventory(self.portfolio.available(Venue::Dxdy)),
+            self.config.capital,
+            self.config.risk.freshness(),
         )
         .await;
 
-        let mut p_l = 0;
+        //If p&l is positive after fees and above threshold, initiate trading
+        let opportunity = match opportunity {
+            Some(opportunity)
+                if opportunity.is_profitable()
+                    && opportunity.net_profit >= self.config.min_profit =>
+            {
+                opportunity
+            }
+            Some(opportunity) => {
+                debug!(
+                    "{}: best arbitrage {:?} is not profitable, net edge : {} bps",
+                    self.name, opportunity.direction, opportunity.net_edge_bps
+                );
+                return Ok(());
+            }
+            None => {
+                debug!("{}: orderbooks can not be compared", self.name);
+                return Ok(());
+            }
+        };
+
+        let books = [
+            BookState::read(&*self.orderbook_aevo_ref.lock().await),
+            BookState::read(&*self.orderbook_dxdy_ref.lock().await),
+        ];
+        if let Err(violation) = self.risk.check(&opportunity, &self.portfolio, &books) {
+            warn!(
+                "{}: {:?} of {} blocked by risk check : {violation}",
+                self.name, opportunity.direction, opportunity.net_profit
+            );
+            return Ok(());
+        }
+
+        self.expected_p_l += opportunity.net_profit;
+
+        info!(
+            "{}: {:?}: found buy {} at {:?}, sell {} at {:?}, fees {}",
+            self.name,
+            opportunity.direction,
+            opportunity.buy.quantity,
+            opportunity.buy.avg_price,
+            opportunity.sell.quantity,
+            opportunity.sell.avg_price,
+            opportunity.fees
+        );
+        info!(
+            "Expected profit and loss : {}, net edge : {} bps",
+            opportunity.net_profit, opportunity.net_edge_bps
+        );
+        debug!("Profit curve : {:?}", opportunity.sizing.curve);
+
+        if let Some(coordinator) = &mut self.coordinator {
+            let Some(report) = coordinator
+                .execute(
+                    &opportunity,
+                    self.orderbook_aevo_ref.clone(),
+                    self.orderbook_dxdy_ref.clone(),
+                )
+                .await?
+            else {
+                return Ok(());
+            };
+            info!(
+                "Execution {} of {} finished {:?}, residual : {}, corrections : {}",
+                report.id,
+                self.name,
+                report.outcome,
+                report.residual,
+                report.corrections.len()
+            );
+            for order in report
+                .buy
+                .iter()
+                .chain(&report.sell)
+                .chain(&report.corrections)
+            {
+                if let Some(fill) = Fill::from_report(order, self.fees(order.venue)) {
+                    self.portfolio.apply(&fill);
+                }
+            }
+            info!("Portfolio of {} : {}", self.name, self.portfolio);
+            return Ok(());
+        }
+
+        //Both legs are simulated against books as they are after execution latency
+        self.paper_trader.schedule(opportunity);
+        self.fill_paper_trade().await
+    }
+
+    ///Fills the scheduled paper trade once its latency elapsed
+    async fn fill_paper_trade(&mut self) -> Result<()> {
+        let trade = self
+            .paper_trader
+            .execute_due(
+                self.orderbook_aevo_ref.clone(),
+                self.orderbook_dxdy_ref.clone(),
+            )
+            .await?;
+        if let Some(trade) = trade {
+            self.book_paper_trade(&trade);
+        }
+
+        Ok(())
+    }
+
+    fn book_paper_trade(&mut self, trade: &PaperTrade) {
+        info!(
+            "Paper trade {} of {}: bought {} at {:?}, sold {} at {:?}, profit and loss : {}",
+            trade.id,
+            self.name,
+            trade.buy.quantity,
+            trade.buy.avg_price,
+            trade.sell.quantity,
+            trade.sell.avg_price,
+            trade.realized_profit
+        );
+        for leg in [&trade.buy, &trade.sell] {
+            if let Some(fill) = Fill::from_leg(leg) {
+                self.portfolio.apply(&fill);
+            }
+        }
+        info!(
+            "Cumulative profit and loss of {} : {}, expected : {}",
+            self.name,
+            self.paper_trader.realized_p_l(),
+            self.expected_p_l
+        );
+        info!("Portfolio of {} : {}", self.name, self.portfolio);
+        info!(
+            "Positions AEVO : {:?}, dXdY : {:?}",
+            self.portfolio.position(Venue::Aevo),
+            self.portfolio.position(Venue::Dxdy)
+        );
+    }
+
+    ///Logs AEVO feed latency since the last report
+    fn report_latency(&mut self) {
+        let latency = &mut self.aevo_latency.stats;
+        if latency.count > 0 {
+            info!("{} {} feed latency : {latency}", Venue::Aevo, self.name);
+            latency.reset();
+        }
+    }
+}
+
+/// Connects order clients of every market
+///
+/// dXdY clients are derived from one account connection, so they share its sequence
+async fn connect_coordinators(configs: &[Config]) -> Result<Vec<ExecutionCoordinator>> {
+    let Some(first) = configs.first() else {
+        return Ok(Vec::new());
+    };
+    let dxdy_account = DXDYOrderClient::connect(&first.dxdy, DXDYWallet::from_env()?).await?;
+
+    let mut coordinators = Vec::new();
+    for config in configs {
+        let aevo_auth = AEVOWSAuthenticator::new(&config.aevo.ws_url, config.aevo.credentials()?);
+        let aevo_signer = AEVOSigner::from_env(SigningDomainAEVO::default())?;
+        let clients: Vec<Box<dyn OrderClient>> = vec![
+            Box::new(AEVOOrderClient::connect(&aevo_auth, &config.aevo, aevo_signer).await?),
+            Box::new(dxdy_account.for_market(&config.dxdy).await?),
+        ];
+        coordinators.push(ExecutionCoordinator::new(
+            clients,
+            config.execution.clone(),
+        )?);
+    }
+
+    Ok(coordinators)
+}
+
+pub async fn main_loop(config: Config) -> Result<()> {
+    //Logger may already be installed by the embedding process
+    let _ = env_logger::Builder::new()
+        .parse_filters(&config.log_level)
+        .try_init();
+
+    info!("Starting main loop");
+
+    let market_configs: Vec<Config> = config
+        .markets()
+        .iter()
+        .map(|market| config.for_market(market))
+        .collect();
+
+    //Live mode places real orders, opportunities are paper traded otherwise
+    let coordinators: Vec<Option<ExecutionCoordinator>> = if config.live {
+        connect_coordinators(&market_configs)
+            .await?
+            .into_iter()
+            .map(Some)
+            .collect()
+    } else {
+        market_configs.iter().map(|_| None).collect()
+    };
+
+    //Kill switch halts trading of all markets
+    let kill_switch = KillSwitch::new(config.risk.kill_switch_file.clone());
+    kill_switch.listen_for_signal();
+
+    let mut markets = market_configs
+        .into_iter()
+        .zip(coordinators)
+        .map(|(config, coordinator)| MarketLoop::new(config, coordinator, kill_switch.clone()))
+        .collect::<Result<Vec<_>>>()?;
+
+    //Sharing orderbooks between threads, venue feeds route updates by market
+    let mut aevo_books = VenueBooks::default();
+    let mut dxdy_books = VenueBooks::default();
+    for market in &markets {
+        aevo_books.insert(
+            &market.config.aevo.instrument,
+            market.orderbook_aevo_ref.clone(),
+        );
+        dxdy_books.insert(
+            &market.config.dxdy.market,
+            market.orderbook_dxdy_ref.clone(),
+        );
+    }
+    info!(
+        "Trading {}",
+        markets
+            .iter()
+            .map(|market| market.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    let dxdy_stats = Arc::new(DXDYFeedStats::default());
+    let recorder = config
+        .record
+        .as_deref()
+        .map(FeedRecorder::create)
+        .transpose()?;
+    let (aevo_health, dxdy_health, mut aevo_updates, mut dxdy_updates, mut replay) =
+        if let Some(path) = &config.replay {
+            //Recorded frames replace venue connections
+            info!(
+                "Replaying {} at speed {}",
+                path.display(),
+                config.replay_speed
+            );
+            let replay = spawn_replay(path, config.replay_speed, aevo_books, dxdy_books)?;
+            (
+                replay.health.clone(),
+                replay.health,
+                replay.aevo_updates,
+                replay.dxdy_updates,
+                Some(replay.handle),
+            )
+        } else {
+            let aevo_auth =
+                AEVOWSAuthenticator::new(&config.aevo.ws_url, config.aevo.credentials()?);
+            let dxdy_auth = DXDYWSAuthenticator::new(&config.dxdy.ws_url);
+
+            //Spawning supervised feed tasks, one connection per venue serves all markets
+            //They authenticate, update orderbooks in real time and reconnect on failures
+            let aevo_feed =
+                AEVOWSOrderbookFeed::spawn_supervised(aevo_auth, aevo_books, recorder.clone());
+            let dxdy_feed = DXDYWSOrderbookFeed::spawn_supervised(
+                dxdy_auth,
+                dxdy_books,
+                dxdy_stats.clone(),
+                recorder.clone(),
+            );
+            (
+                aevo_feed.health,
+                dxdy_feed.health,
+                aevo_feed.updates,
+                dxdy_feed.updates,
+                None,
+            )
+        };
+
+    let check_interval = Duration::from_millis(config.check_interval_ms);
+    let debounce = Duration::from_millis(config.debounce_ms);
+    let mut decision_latency = LatencyStats::default();
+
+    loop {
+        if kill_switch.is_engaged() {
+            //Feed tasks keep running until the process exits, recording is completed now
+            if let Some(recorder) = &recorder {
+                recorder.finish()?;
+            }
+            for market in &mut markets {
+                market.halt().await?;
+            }
+            return Ok(());
+        }
+
+        //Pending paper trades wake the loop once their latency elapsed
+        let wake_at = markets
+            .iter()
+            .filter_map(|market| market.paper_trader.pending())
+            .map(|pending| pending.execute_at)
+            .fold(Instant::now() + check_interval, Instant::min);
+
+        //Books are reevaluated on every update, check interval is a fallback for quiet markets
+        let woken_by_update = tokio::select! {
+            changed = aevo_updates.changed() => {
+                changed.map(|_| true).context("AEVO feed task stopped")
+            }
+            changed = dxdy_updates.changed() => {
+                changed.map(|_| true).context("dXdY feed task stopped")
+            }
+            _ = tokio::time::sleep_until(wake_at.into()) => Ok(false),
+        };
+        //Replay closes update channels once the recording ends
+        let woken_by_update = match woken_by_update {
+            Ok(woken_by_update) => woken_by_update,
+            Err(e) => match replay.take() {
+                Some(replay) => {
+                    let frames = replay.await.context("Replay task panicked")??;
+                    info!("Replayed {frames} frames");
+                    for market in &markets {
+                        info!(
+                            "{}: expected profit and loss : {}, portfolio : {}",
+                            market.name, market.expected_p_l, market.portfolio
+                        );
+                    }
+                    return Ok(());
+                }
+                None => return Err(e),
+            },
+        };
+
+        //Coalescing bursts of updates into a single evaluation
+        if woken_by_update && !debounce.is_zero() {
+            tokio::time::sleep(debounce).await;
+        }
+
+        //Receive time of the latest update seen by this evaluation
+        let last_received_at = [
+            *aevo_updates.borrow_and_update(),
+            *dxdy_updates.borrow_and_update(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|update| update.received_at)
+        .max();
+
+        //Due paper trades are settled even during outages, stale books make them missed
+        for market in &mut markets {
+            market.fill_paper_trade().await?;
+        }
+
+        //Books of a reconnecting feed are empty or outdated
+        let aevo_health = *aevo_health.borrow();
+        let dxdy_health = *dxdy_health.borrow();
+        //Supervisors already warn about reconnects, updates of the other feed keep coming
+        if !aevo_health.is_live() || !dxdy_health.is_live() {
+            debug!(
+                "Skipping orderbook check, AEVO feed: {aevo_health:?}, dXdY feed: {dxdy_health:?}"
+            );
+            continue;
+        }
+
+        //Every pair is checked, an update of one venue may open arbitrage on any of its markets
+        for market in &mut markets {
+            market.evaluate().await?;
+        }
 
-        //If p&l is positive, initiate trading
-        if sign > 0 {
-            p_l = delta;
+        //Latency from message receipt to decision on all markets
+        if let (true, Some(received_at)) = (woken_by_update, last_received_at) {
+            decision_latency.record(received_at.elapsed());
+            if decision_latency.count % LATENCY_REPORT_INTERVAL == 0 {
+                info!("Decision latency : {decision_latency}");
+                decision_latency.reset();
+                for market in &mut markets {
+                    market.report_latency();
+                }
+            }
         }
-        cumulative_p_l += p_l;
 
-        info!("Profit and loss after last trade : {p_l}");
-        info!("Cumulative profit and loss : {cumulative_p_l}");
+        debug!(
+            "dXdY sequence gaps : {}, resubscriptions : {}",
+            dxdy_stats.sequence_gaps(),
+            dxdy_stats.resubscriptions()
+        );
     }
 }
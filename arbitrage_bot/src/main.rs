@@ -1,7 +1,13 @@
 use anyhow::Result;
-use arbitrage_bot::main_loop;
+use arbitrage_bot::{
+    config::{Cli, Config},
+    main_loop,
+};
+use clap::Parser;
 
 fn main() -> Result<()> {
+    let config = Config::load(&Cli::parse())?;
+
     actix::System::with_tokio_rt(|| {
         tokio::runtime::Builder::new_multi_thread()
             .worker_threads(num_cpus::get())
@@ -9,5 +15,5 @@ fn main() -> Result<()> {
             .build()
             .unwrap()
     })
-    .block_on(main_loop())
+    .block_on(main_loop(config))
 }
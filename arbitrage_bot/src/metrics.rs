@@ -0,0 +1,72 @@
+use std::{
+    cmp, fmt,
+    time::{Duration, Instant},
+};
+
+use crate::orderbook::BookTimestamps;
+
+///Running statistics of measured latencies
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LatencyStats {
+    pub count: u64,
+    pub total: Duration,
+    pub min: Option<Duration>,
+    pub max: Duration,
+    pub last: Duration,
+}
+
+impl LatencyStats {
+    pub fn record(&mut self, latency: Duration) {
+        self.count += 1;
+        self.total += latency;
+        self.min = Some(self.min.map_or(latency, |min| cmp::min(min, latency)));
+        self.max = cmp::max(self.max, latency);
+        self.last = latency;
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+impl fmt::Display for LatencyStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "count {}, mean {:?}, min {:?}, max {:?}, last {:?}",
+            self.count,
+            self.mean(),
+            self.min.unwrap_or_default(),
+            self.max,
+            self.last
+        )
+    }
+}
+
+/// Latency between exchange time and local receipt of book updates
+///
+/// Sampled whenever books are checked, every update is recorded at most once
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FeedLatency {
+    pub stats: LatencyStats,
+    last_received_at: Option<Instant>,
+}
+
+impl FeedLatency {
+    pub fn observe(&mut self, timestamps: &BookTimestamps) {
+        if timestamps.received_at == self.last_received_at {
+            return;
+        }
+        self.last_received_at = timestamps.received_at;
+        if let Some(latency) = timestamps.feed_latency() {
+            self.stats.record(latency);
+        }
+    }
+}
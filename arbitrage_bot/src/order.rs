@@ -0,0 +1,124 @@
+use std::fmt;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    instrument::{Price, Size},
+    venue::{Side, Venue},
+};
+
+///How long an order stays on the book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TimeInForce {
+    ///Fills what it can immediately, the rest is canceled
+    Ioc,
+    ///Rests on the book until filled or canceled
+    Gtc,
+}
+
+///Order to be placed on a venue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OrderRequest {
+    ///Our identifier of the order, unique per venue account
+    pub client_id: u32,
+    pub side: Side,
+    pub quantity: Size,
+    ///Limit price, IOC orders use worst acceptable price
+    pub price: Price,
+    pub time_in_force: TimeInForce,
+    pub reduce_only: bool,
+}
+
+impl OrderRequest {
+    pub fn ioc(client_id: u32, side: Side, quantity: Size, price: Price) -> Self {
+        Self {
+            client_id,
+            side,
+            quantity,
+            price,
+            time_in_force: TimeInForce::Ioc,
+            reduce_only: false,
+        }
+    }
+
+    pub fn limit(client_id: u32, side: Side, quantity: Size, price: Price) -> Self {
+        Self {
+            time_in_force: TimeInForce::Gtc,
+            ..Self::ioc(client_id, side, quantity, price)
+        }
+    }
+}
+
+///Lifecycle state of an order as reported by venue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OrderStatus {
+    ///Accepted for processing, not yet seen on the book
+    Pending,
+    Open,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+}
+
+impl OrderStatus {
+    ///Order will not change anymore
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Filled | Self::Canceled | Self::Rejected)
+    }
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+///State of a placed order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrderReport {
+    pub venue: Venue,
+    ///Venue identifier, transaction hash until venue assigns one
+    pub order_id: String,
+    pub client_id: u32,
+    pub side: Side,
+    pub time_in_force: TimeInForce,
+    pub status: OrderStatus,
+    pub quantity: Size,
+    pub filled_qty: Size,
+    pub avg_price: Option<Price>,
+}
+
+impl OrderReport {
+    pub fn pending(venue: Venue, order_id: String, request: &OrderRequest) -> Self {
+        Self {
+            venue,
+            order_id,
+            client_id: request.client_id,
+            side: request.side,
+            time_in_force: request.time_in_force,
+            status: OrderStatus::Pending,
+            quantity: request.quantity,
+            filled_qty: Decimal::ZERO,
+            avg_price: None,
+        }
+    }
+}
+
+/// Order execution on a single venue
+///
+/// Implemented per venue, so a mock server or a paper implementation can stand in
+#[async_trait]
+pub trait OrderClient: Send + Sync {
+    fn venue(&self) -> Venue;
+
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderReport>;
+
+    async fn cancel_order(&self, order: &OrderReport) -> Result<()>;
+
+    ///Fetches latest state of a previously placed order
+    async fn query_order(&self, order: &OrderReport) -> Result<OrderReport>;
+}
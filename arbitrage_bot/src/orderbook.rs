@@ -0,0 +1,317 @@
+use std::{
+    cmp,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+
+use crate::{
+    instrument::{InstrumentSpec, Price, Size},
+    venue::Venue,
+};
+
+///Single price level of an orderbook: (price, size)
+pub type Level = (Price, Size);
+
+///Iterator over price levels, ordered by ascending price
+pub type Levels<'a> = Box<dyn DoubleEndedIterator<Item = Level> + 'a>;
+
+///Result of orderbook consistency check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookIntegrity {
+    Healthy,
+    ///Best bid is at or above best ask
+    Crossed {
+        best_bid: Price,
+        best_ask: Price,
+    },
+    EmptyBids,
+    EmptyAsks,
+}
+
+impl BookIntegrity {
+    pub fn is_healthy(&self) -> bool {
+        *self == BookIntegrity::Healthy
+    }
+}
+
+///Timing of the last update applied to a book
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BookTimestamps {
+    ///Time the update was produced, as reported by the venue
+    pub exchange_time: Option<DateTime<Utc>>,
+    ///Local wall clock time the update was received
+    pub received_time: Option<DateTime<Utc>>,
+    ///Local monotonic time the update was received, used for ages
+    pub received_at: Option<Instant>,
+}
+
+impl BookTimestamps {
+    ///Time since the last update was received
+    pub fn age(&self) -> Option<Duration> {
+        self.received_at.map(|received_at| received_at.elapsed())
+    }
+
+    ///Time between the last receipt and `now` on the wall clock, used when time is simulated
+    pub fn age_at(&self, now: DateTime<Utc>) -> Option<Duration> {
+        Some((now - self.received_time?).to_std().unwrap_or_default())
+    }
+
+    ///Receive time minus exchange time, clock skew below zero counts as zero
+    pub fn feed_latency(&self) -> Option<Duration> {
+        let latency = self.received_time? - self.exchange_time?;
+        Some(latency.to_std().unwrap_or_default())
+    }
+
+    ///Time between receipts of last updates of two books
+    pub fn skew(&self, other: &Self) -> Option<Duration> {
+        let (left, right) = (self.received_at?, other.received_at?);
+        Some(cmp::max(left, right) - cmp::min(left, right))
+    }
+
+    ///Skew measured on the wall clock, used when time is simulated
+    pub fn skew_in_time(&self, other: &Self) -> Option<Duration> {
+        let (left, right) = (self.received_time?, other.received_time?);
+        Some(
+            (cmp::max(left, right) - cmp::min(left, right))
+                .to_std()
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// Limits on how old compared books may be
+///
+/// Absent limits are not checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BookFreshness {
+    ///Longest time since the last update of either book
+    pub max_age: Option<Duration>,
+    ///Longest time between last updates of both books
+    pub max_skew: Option<Duration>,
+}
+
+impl BookFreshness {
+    ///Books without receive time are never fresh once a limit is set
+    pub fn allows(&self, left: &BookTimestamps, right: &BookTimestamps) -> bool {
+        self.within(left.age(), right.age(), left.skew(right))
+    }
+
+    ///Same limits measured from receive times of replayed books at simulated `now`
+    pub fn allows_at(
+        &self,
+        left: &BookTimestamps,
+        right: &BookTimestamps,
+        now: DateTime<Utc>,
+    ) -> bool {
+        self.within(
+            left.age_at(now),
+            right.age_at(now),
+            left.skew_in_time(right),
+        )
+    }
+
+    fn within(
+        &self,
+        left_age: Option<Duration>,
+        right_age: Option<Duration>,
+        skew: Option<Duration>,
+    ) -> bool {
+        let within = |value: Option<Duration>, limit: Option<Duration>| match limit {
+            Some(limit) => value.is_some_and(|value| value <= limit),
+            None => true,
+        };
+
+        within(left_age, self.max_age)
+            && within(right_age, self.max_age)
+            && within(skew, self.max_skew)
+    }
+}
+
+/// Venue-agnostic orderbook
+///
+/// Implemented by every venue book, so that arbitrage calculations
+/// can be performed on any pair of venues
+pub trait Orderbook {
+    ///Raw update (snapshot or delta) received from the venue feed
+    type Update;
+
+    ///Market the update belongs to, `None` for frames not tied to a market
+    fn update_market(update: &Self::Update) -> Option<&str>;
+
+    ///Venue this book belongs to
+    fn venue(&self) -> Venue;
+
+    ///Applies snapshot or delta received from the feed
+    fn apply_changes(&mut self, update: Self::Update) -> Result<()>;
+
+    ///Tick and step size of the traded instrument
+    fn spec(&self) -> &InstrumentSpec;
+
+    ///Whether book content can not be trusted until next snapshot
+    fn is_stale(&self) -> bool;
+
+    fn set_stale(&mut self, stale: bool);
+
+    ///Drops all levels and timestamps, book stays stale until next snapshot
+    fn reset(&mut self);
+
+    ///Timing of the last applied update
+    fn timestamps(&self) -> &BookTimestamps;
+
+    fn timestamps_mut(&mut self) -> &mut BookTimestamps;
+
+    ///Records local receive time of the update just applied
+    fn record_receipt(&mut self, received_at: Instant, received_time: DateTime<Utc>) {
+        let timestamps = self.timestamps_mut();
+        timestamps.received_at = Some(received_at);
+        timestamps.received_time = Some(received_time);
+    }
+
+    ///Bid levels, ordered by ascending price
+    fn bid_levels(&self) -> Levels<'_>;
+
+    ///Ask levels, ordered by ascending price
+    fn ask_levels(&self) -> Levels<'_>;
+
+    ///Highest bid level
+    fn best_bid(&self) -> Option<Level> {
+        self.bid_levels().next_back()
+    }
+
+    ///Lowest ask level
+    fn best_ask(&self) -> Option<Level> {
+        self.ask_levels().next()
+    }
+
+    ///Detects crossed book and empty sides
+    fn check_integrity(&self) -> BookIntegrity {
+        match (self.best_bid(), self.best_ask()) {
+            (None, _) => BookIntegrity::EmptyBids,
+            (_, None) => BookIntegrity::EmptyAsks,
+            (Some((best_bid, _)), Some((best_ask, _))) if best_bid >= best_ask => {
+                BookIntegrity::Crossed { best_bid, best_ask }
+            }
+            _ => BookIntegrity::Healthy,
+        }
+    }
+
+    ///Checks integrity and flags broken book as stale
+    ///
+    /// Returns integrity violation only when the book has just become stale,
+    /// so that feed requests a single resnapshot
+    fn flag_if_broken(&mut self) -> Option<BookIntegrity> {
+        let integrity = self.check_integrity();
+
+        if integrity.is_healthy() || self.is_stale() {
+            return None;
+        }
+
+        self.set_stale(true);
+        Some(integrity)
+    }
+
+    ///Simulates market buy spending up to `balance`, consuming asks from the lowest price
+    fn simulate_buy(&self, balance: Decimal) -> TakerFill {
+        walk_levels(self.ask_levels(), TakerLimit::Quote(balance), self.spec())
+    }
+
+    ///Simulates market buy of `quantity`, consuming asks from the lowest price
+    fn simulate_buy_quantity(&self, quantity: Size) -> TakerFill {
+        walk_levels(self.ask_levels(), TakerLimit::Base(quantity), self.spec())
+    }
+
+    ///Simulates market sell of `quantity`, consuming bids from the highest price
+    fn simulate_sell(&self, quantity: Size) -> TakerFill {
+        walk_levels(
+            self.bid_levels().rev(),
+            TakerLimit::Base(quantity),
+            self.spec(),
+        )
+    }
+
+    ///Buys as much asset as possible with balance, returns bought quantity
+    ///
+    /// Assuming that our sum is relatively small, small enough, to be fully spent
+    fn buy_as_much_as_possible(&self, balance: Decimal) -> Size {
+        self.simulate_buy(balance).filled_qty
+    }
+
+    ///Sells as much of asset_balance as possible, returns received balance
+    ///
+    /// Assuming that our sum is relatively small, small enough, to be fully spent
+    fn sell_as_much_as_possible(&self, asset_balance: Size) -> Decimal {
+        self.simulate_sell(asset_balance).notional
+    }
+}
+
+///Result of a simulated taker order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TakerFill {
+    pub filled_qty: Size,
+    ///Quote amount paid for buy or received for sell, before fees
+    pub notional: Decimal,
+    pub avg_price: Option<Price>,
+    ///Price of the last consumed level
+    pub worst_price: Option<Price>,
+    pub levels_consumed: usize,
+}
+
+impl TakerFill {
+    pub fn is_empty(&self) -> bool {
+        self.filled_qty.is_zero()
+    }
+}
+
+///Amount limiting taker order
+#[derive(Debug, Clone, Copy)]
+enum TakerLimit {
+    ///Quote currency to spend
+    Quote(Decimal),
+    ///Base asset quantity to trade
+    Base(Size),
+}
+
+///Consumes levels in given order until limit is exhausted or book ends
+fn walk_levels(
+    levels: impl Iterator<Item = Level>,
+    mut limit: TakerLimit,
+    spec: &InstrumentSpec,
+) -> TakerFill {
+    let mut fill = TakerFill::default();
+
+    for (price, size) in levels {
+        let take = match limit {
+            TakerLimit::Quote(quote) if price * size <= quote => size,
+            TakerLimit::Quote(quote) => spec.floor_size(quote / price),
+            TakerLimit::Base(base) => cmp::min(size, base),
+        };
+
+        if take.is_zero() {
+            break;
+        }
+
+        fill.filled_qty += take;
+        fill.notional += take * price;
+        fill.worst_price = Some(price);
+        fill.levels_consumed += 1;
+
+        limit = match limit {
+            TakerLimit::Quote(quote) => TakerLimit::Quote(quote - take * price),
+            TakerLimit::Base(base) => TakerLimit::Base(base - take),
+        };
+
+        //Level was only partially consumed, so limit is exhausted
+        if take < size {
+            break;
+        }
+    }
+
+    if !fill.filled_qty.is_zero() {
+        fill.avg_price = Some(fill.notional / fill.filled_qty);
+    }
+
+    fill
+}
@@ -0,0 +1,293 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::debug;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{
+    calculations::{ArbitrageDirection, ArbitrageLeg, ArbitrageOpportunity},
+    fees::{FeeSchedule, BPS},
+    orderbook::{Orderbook, TakerFill},
+    portfolio::VenueBalance,
+    venue::{Side, Venue},
+};
+
+/// Paper trading settings
+///
+/// Selling requires base asset already held on the sell venue, so both venues start with inventory
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaperConfig {
+    ///Delay between detection and simulated execution of both legs
+    pub latency_ms: u64,
+    ///Price deterioration applied to every simulated fill
+    pub slippage_bps: Decimal,
+    pub aevo: VenueBalance,
+    pub dxdy: VenueBalance,
+    ///JSON lines file receiving every simulated trade
+    pub trade_log: Option<PathBuf>,
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            latency_ms: 50,
+            slippage_bps: dec!(1),
+            aevo: VenueBalance::new(dec!(1000), dec!(0.5)),
+            dxdy: VenueBalance::new(dec!(1000), dec!(0.5)),
+            trade_log: None,
+        }
+    }
+}
+
+///Simulated execution of both legs of an opportunity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PaperTrade {
+    pub id: u64,
+    ///Traded pair, e.g. `ETH-PERP/ETH-USD`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub market: Option<String>,
+    pub direction: ArbitrageDirection,
+    pub detected_at: DateTime<Utc>,
+    pub executed_at: DateTime<Utc>,
+    pub buy: ArbitrageLeg,
+    pub sell: ArbitrageLeg,
+    ///Net profit predicted at detection
+    pub expected_profit: Decimal,
+    ///Sell proceeds minus buy cost after fees and slippage
+    pub realized_profit: Decimal,
+}
+
+///Opportunity waiting for execution latency to elapse
+#[derive(Debug, Clone)]
+pub struct PendingPaperTrade {
+    pub opportunity: ArbitrageOpportunity,
+    pub execute_at: Instant,
+}
+
+/// Simulates trading opportunities against live orderbooks
+///
+/// Opportunities are scheduled and filled against books as they are once configured latency
+/// elapsed, so fills reflect how the market moved since detection. Nothing waits for the
+/// latency in between. Legs are limited by balances held on each venue.
+#[derive(Debug)]
+pub struct PaperTrader {
+    latency: Duration,
+    slippage_rate: Decimal,
+    fees: HashMap<Venue, FeeSchedule>,
+    balances: HashMap<Venue, VenueBalance>,
+    trades: Vec<PaperTrade>,
+    trade_log: Option<File>,
+    market: Option<String>,
+    pending: Option<PendingPaperTrade>,
+}
+
+impl PaperTrader {
+    pub fn new(
+        config: &PaperConfig,
+        aevo_fees: FeeSchedule,
+        dxdy_fees: FeeSchedule,
+    ) -> Result<Self> {
+        let trade_log = match &config.trade_log {
+            Some(path) => Some(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open trade log {}", path.display()))?,
+            ),
+            None => None,
+        };
+
+        Ok(Self {
+            latency: Duration::from_millis(config.latency_ms),
+            slippage_rate: config.slippage_bps / BPS,
+            fees: HashMap::from([(Venue::Aevo, aevo_fees), (Venue::Dxdy, dxdy_fees)]),
+            balances: HashMap::from([(Venue::Aevo, config.aevo), (Venue::Dxdy, config.dxdy)]),
+            trades: Vec::new(),
+            trade_log,
+            market: None,
+            pending: None,
+        })
+    }
+
+    ///Marks trades with the traded pair, so that trade logs of several markets can be told apart
+    pub fn with_market(mut self, market: &str) -> Self {
+        self.market = Some(market.to_string());
+        self
+    }
+
+    ///Delay between detection and execution
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    pub fn balance(&self, venue: Venue) -> VenueBalance {
+        self.balances.get(&venue).copied().unwrap_or_default()
+    }
+
+    pub fn trades(&self) -> &[PaperTrade] {
+        &self.trades
+    }
+
+    pub fn realized_p_l(&self) -> Decimal {
+        self.trades.iter().map(|trade| trade.realized_profit).sum()
+    }
+
+    ///Trade waiting for execution, if any
+    pub fn pending(&self) -> Option<&PendingPaperTrade> {
+        self.pending.as_ref()
+    }
+
+    ///Schedules opportunity for execution after configured latency, replacing any pending trade
+    pub fn schedule(&mut self, opportunity: ArbitrageOpportunity) {
+        self.pending = Some(PendingPaperTrade {
+            opportunity,
+            execute_at: Instant::now() + self.latency,
+        });
+    }
+
+    /// Fills pending trade against current books once its latency elapsed
+    ///
+    /// Returns `None` while nothing is due, when books became stale or balances allow no trade
+    pub async fn execute_due<L: Orderbook, R: Orderbook>(
+        &mut self,
+        orderbook_left: Arc<Mutex<L>>,
+        orderbook_right: Arc<Mutex<R>>,
+    ) -> Result<Option<PaperTrade>> {
+        let now = Instant::now();
+        let Some(pending) = self.pending.take_if(|pending| pending.execute_at <= now) else {
+            return Ok(None);
+        };
+        let opportunity = &pending.opportunity;
+
+        let orderbook_left = orderbook_left.lock().await;
+        let orderbook_right = orderbook_right.lock().await;
+
+        let trade = if orderbook_left.venue() == opportunity.direction.buy_venue() {
+            self.fill(opportunity, &*orderbook_left, &*orderbook_right)
+        } else {
+            self.fill(opportunity, &*orderbook_right, &*orderbook_left)
+        };
+
+        if let Some(trade) = &trade {
+            self.log_trade(trade)?;
+        }
+
+        Ok(trade)
+    }
+
+    ///Fills both legs immediately and updates balances
+    pub fn fill<B: Orderbook, S: Orderbook>(
+        &mut self,
+        opportunity: &ArbitrageOpportunity,
+        buy_book: &B,
+        sell_book: &S,
+    ) -> Option<PaperTrade> {
+        self.fill_at(opportunity, buy_book, sell_book, Utc::now())
+    }
+
+    ///Fills both legs at given execution time, used when time is simulated
+    pub fn fill_at<B: Orderbook, S: Orderbook>(
+        &mut self,
+        opportunity: &ArbitrageOpportunity,
+        buy_book: &B,
+        sell_book: &S,
+        executed_at: DateTime<Utc>,
+    ) -> Option<PaperTrade> {
+        if buy_book.is_stale() || sell_book.is_stale() {
+            debug!("Paper trade skipped, orderbooks are stale");
+            return None;
+        }
+
+        let buy_venue = buy_book.venue();
+        let sell_venue = sell_book.venue();
+        let buy_fees = self.fees.get(&buy_venue)?.clone();
+        let sell_fees = self.fees.get(&sell_venue)?.clone();
+        let buy_balance = self.balance(buy_venue);
+        let sell_balance = self.balance(sell_venue);
+
+        //We can not sell more than we hold on sell venue
+        let quantity = opportunity.buy.quantity.min(sell_balance.base);
+        let mut buy_fill = buy_book.simulate_buy_quantity(quantity);
+
+        //Buy leg including slippage and fee must fit into USDC held on buy venue
+        let cost_rate =
+            (Decimal::ONE + self.slippage_rate) * (Decimal::ONE + buy_fees.taker_rate());
+        if buy_fill.notional * cost_rate > buy_balance.usdc {
+            buy_fill = buy_book.simulate_buy(buy_balance.usdc / cost_rate);
+        }
+        let sell_fill = sell_book.simulate_sell(buy_fill.filled_qty);
+
+        if buy_fill.is_empty() || sell_fill.is_empty() {
+            debug!("Paper trade skipped, nothing can be filled with current balances");
+            return None;
+        }
+
+        let buy = self.leg(buy_venue, Side::Buy, &buy_fill, &buy_fees);
+        let sell = self.leg(sell_venue, Side::Sell, &sell_fill, &sell_fees);
+
+        if let Some(balance) = self.balances.get_mut(&buy_venue) {
+            balance.usdc -= buy.notional + buy.fee;
+            balance.base += buy.quantity;
+        }
+        if let Some(balance) = self.balances.get_mut(&sell_venue) {
+            balance.usdc += sell.notional - sell.fee;
+            balance.base -= sell.quantity;
+        }
+
+        let trade = PaperTrade {
+            id: self.trades.len() as u64 + 1,
+            market: self.market.clone(),
+            direction: opportunity.direction,
+            detected_at: opportunity.detected_at,
+            executed_at,
+            buy,
+            sell,
+            expected_profit: opportunity.net_profit,
+            realized_profit: (sell.notional - sell.fee) - (buy.notional + buy.fee),
+        };
+        self.trades.push(trade.clone());
+
+        Some(trade)
+    }
+
+    ///Turns simulated fill into a leg with slippage applied to its notional
+    fn leg(&self, venue: Venue, side: Side, fill: &TakerFill, fees: &FeeSchedule) -> ArbitrageLeg {
+        let slippage = match side {
+            Side::Buy => Decimal::ONE + self.slippage_rate,
+            Side::Sell => Decimal::ONE - self.slippage_rate,
+        };
+        let notional = fill.notional * slippage;
+
+        ArbitrageLeg {
+            venue,
+            side,
+            quantity: fill.filled_qty,
+            avg_price: fill.avg_price.map(|price| price * slippage),
+            worst_price: fill.worst_price.map(|price| price * slippage),
+            notional,
+            fee: fees.taker_fee(notional),
+        }
+    }
+
+    fn log_trade(&mut self, trade: &PaperTrade) -> Result<()> {
+        if let Some(file) = &mut self.trade_log {
+            let line = serde_json::to_string(trade)?;
+            writeln!(file, "{line}").context("Failed to write trade log")?;
+        }
+
+        Ok(())
+    }
+}
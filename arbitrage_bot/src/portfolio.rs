@@ -0,0 +1,263 @@
+use std::{collections::HashMap, fmt};
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    calculations::ArbitrageLeg,
+    fees::FeeSchedule,
+    instrument::{Price, Size},
+    order::OrderReport,
+    orderbook::Orderbook,
+    venue::{Side, Venue},
+};
+
+///Assets held on a single venue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VenueBalance {
+    pub usdc: Decimal,
+    ///Base asset of the traded market, `eth` is accepted for configs of the ETH market
+    #[serde(alias = "eth")]
+    pub base: Decimal,
+}
+
+impl VenueBalance {
+    pub fn new(usdc: Decimal, base: Decimal) -> Self {
+        Self { usdc, base }
+    }
+}
+
+/// Inventory deposited on venues for live trading
+///
+/// Paper trading starts from balances of `PaperConfig` instead
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PortfolioConfig {
+    pub aevo: VenueBalance,
+    pub dxdy: VenueBalance,
+}
+
+///Single fill applied to a venue position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fill {
+    pub venue: Venue,
+    pub side: Side,
+    pub quantity: Size,
+    pub price: Price,
+    pub fee: Decimal,
+}
+
+impl Fill {
+    pub fn from_leg(leg: &ArbitrageLeg) -> Option<Self> {
+        Some(Self {
+            venue: leg.venue,
+            side: leg.side,
+            quantity: leg.quantity,
+            price: leg.avg_price?,
+            fee: leg.fee,
+        })
+    }
+
+    ///Reports carry no fee, so it is charged at taker rate
+    pub fn from_report(report: &OrderReport, fees: &FeeSchedule) -> Option<Self> {
+        let price = report.avg_price?;
+        Some(Self {
+            venue: report.venue,
+            side: report.side,
+            quantity: report.filled_qty,
+            price,
+            fee: fees.taker_fee(report.filled_qty * price),
+        })
+    }
+}
+
+/// Collateral and base asset position held on a venue
+///
+/// Entry price is averaged while position grows, reductions realize profit
+/// against it. Fees are deducted from collateral and realized profit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct VenuePosition {
+    ///USDC after all fills and fees
+    pub collateral: Decimal,
+    ///Signed position, positive when long
+    pub position: Size,
+    ///Inventory deposited before trading, it is not exposure taken by the bot
+    pub initial: Size,
+    ///Average price of open position, `None` when flat or not yet marked
+    pub avg_entry: Option<Price>,
+    pub realized_p_l: Decimal,
+    pub fees: Decimal,
+}
+
+impl VenuePosition {
+    pub fn new(balance: VenueBalance) -> Self {
+        Self {
+            collateral: balance.usdc,
+            position: balance.base,
+            initial: balance.base,
+            ..Self::default()
+        }
+    }
+
+    pub fn apply(&mut self, side: Side, quantity: Size, price: Price, fee: Decimal) {
+        let signed = match side {
+            Side::Buy => quantity,
+            Side::Sell => -quantity,
+        };
+
+        self.collateral -= signed * price + fee;
+        self.fees += fee;
+        self.realized_p_l -= fee;
+
+        let reducing = self.position.is_sign_positive() != signed.is_sign_positive()
+            && !self.position.is_zero();
+        let closed = if reducing {
+            quantity.min(self.position.abs())
+        } else {
+            Decimal::ZERO
+        };
+
+        //Inventory without entry is valued at the price it is traded at
+        let entry = self.avg_entry.unwrap_or(price);
+        if !closed.is_zero() {
+            let direction = if self.position > Decimal::ZERO {
+                Decimal::ONE
+            } else {
+                -Decimal::ONE
+            };
+            self.realized_p_l += closed * (price - entry) * direction;
+        }
+
+        let position = self.position + signed;
+        self.avg_entry = if position.is_zero() {
+            None
+        } else if !reducing {
+            Some((entry * self.position.abs() + price * quantity) / position.abs())
+        } else if closed < quantity {
+            //Position flipped, remainder is opened at fill price
+            Some(price)
+        } else {
+            Some(entry)
+        };
+        self.position = position;
+    }
+
+    ///Profit of open position if it was closed at `mark`
+    pub fn unrealized_p_l(&self, mark: Price) -> Decimal {
+        match self.avg_entry {
+            Some(entry) => self.position * (mark - entry),
+            None => Decimal::ZERO,
+        }
+    }
+
+    ///Position opened by trading on top of deposited inventory
+    pub fn exposure(&self) -> Size {
+        self.position - self.initial
+    }
+
+    ///Assets that can be spent, short positions and negative collateral give nothing
+    pub fn available(&self) -> VenueBalance {
+        VenueBalance::new(
+            self.collateral.max(Decimal::ZERO),
+            self.position.max(Decimal::ZERO),
+        )
+    }
+}
+
+/// Positions on every venue, updated from fills and marked to book mids
+///
+/// Initial inventory has no entry price, it is valued at the first mark,
+/// so profit and loss is measured from the moment the bot started.
+#[derive(Debug, Clone, Default)]
+pub struct Portfolio {
+    positions: HashMap<Venue, VenuePosition>,
+    marks: HashMap<Venue, Price>,
+}
+
+impl Portfolio {
+    pub fn new(balances: impl IntoIterator<Item = (Venue, VenueBalance)>) -> Self {
+        Self {
+            positions: balances
+                .into_iter()
+                .map(|(venue, balance)| (venue, VenuePosition::new(balance)))
+                .collect(),
+            marks: HashMap::new(),
+        }
+    }
+
+    pub fn position(&self, venue: Venue) -> VenuePosition {
+        self.positions.get(&venue).copied().unwrap_or_default()
+    }
+
+    pub fn mark(&self, venue: Venue) -> Option<Price> {
+        self.marks.get(&venue).copied()
+    }
+
+    pub fn apply(&mut self, fill: &Fill) {
+        self.positions.entry(fill.venue).or_default().apply(
+            fill.side,
+            fill.quantity,
+            fill.price,
+            fill.fee,
+        );
+    }
+
+    ///Marks venue position to the mid of its book, stale or one-sided books are skipped
+    pub fn mark_book<B: Orderbook>(&mut self, book: &B) {
+        if book.is_stale() {
+            return;
+        }
+        if let (Some((bid, _)), Some((ask, _))) = (book.best_bid(), book.best_ask()) {
+            self.set_mark(book.venue(), (bid + ask) / Decimal::TWO);
+        }
+    }
+
+    pub fn set_mark(&mut self, venue: Venue, price: Price) {
+        self.marks.insert(venue, price);
+        let position = self.positions.entry(venue).or_default();
+        if position.avg_entry.is_none() && !position.position.is_zero() {
+            position.avg_entry = Some(price);
+        }
+    }
+
+    ///Assets the strategy can still spend on venue
+    pub fn available(&self, venue: Venue) -> VenueBalance {
+        self.position(venue).available()
+    }
+
+    pub fn realized_p_l(&self) -> Decimal {
+        self.positions
+            .values()
+            .map(|position| position.realized_p_l)
+            .sum()
+    }
+
+    ///Open positions of venues without a mark are not valued
+    pub fn unrealized_p_l(&self) -> Decimal {
+        self.positions
+            .iter()
+            .filter_map(|(venue, position)| Some(position.unrealized_p_l(self.mark(*venue)?)))
+            .sum()
+    }
+
+    ///Position summed across venues, zero when arbitrage legs are balanced
+    pub fn net_position(&self) -> Size {
+        self.positions
+            .values()
+            .map(|position| position.position)
+            .sum()
+    }
+}
+
+impl fmt::Display for Portfolio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "realized {}, unrealized {}, net position {}",
+            self.realized_p_l().round_dp(4),
+            self.unrealized_p_l().round_dp(4),
+            self.net_position()
+        )
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, ErrorKind, Lines, Write},
+    iter::Peekable,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex as StdMutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    aevo::aevo_structs::{OrderbookAEVO, OrderbookAEVOResponse},
+    dxdy::dxdy_structs::{OrderbookDXDY, OrderbookDXDYResponse},
+    feed::{apply_routed_frame, BookUpdate, BookUpdateNotifier, FeedHealth, VenueBooks},
+    orderbook::Orderbook,
+    venue::Venue,
+};
+
+///Raw websocket text frame as received from a venue feed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    pub venue: Venue,
+    pub received_time: DateTime<Utc>,
+    pub text: String,
+}
+
+/// Appends raw frames of all feeds to a gzip recording, one JSON line per frame
+///
+/// Every recorder writes a single gzip member into its own segment. The first run writes
+/// the given path, later runs add numbered segments next to it, see `recording_segments`.
+/// Frames are written and flushed by a dedicated thread, so feeds never wait for the disk,
+/// a crash loses at most frames still queued for it.
+/// `finish` completes the segment, it is also completed once the last clone is dropped.
+#[derive(Clone)]
+pub struct FeedRecorder {
+    writer: Arc<RecordingWriter>,
+}
+
+///Queue of the writer thread, closing it completes the segment
+struct RecordingWriter {
+    lines: StdMutex<Option<mpsc::Sender<String>>>,
+    thread: StdMutex<Option<thread::JoinHandle<Result<()>>>>,
+}
+
+impl FeedRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let segment = (0..)
+            .map(|index| segment_path(path, index))
+            .find(|segment| !segment.exists())
+            .context("No free recording segment")?;
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&segment)
+            .with_context(|| format!("Failed to open feed recording {}", segment.display()))?;
+        info!("Recording feeds to {}", segment.display());
+
+        let (lines, queue) = mpsc::channel();
+        let encoder = GzEncoder::new(file, Compression::default());
+        let thread = thread::Builder::new()
+            .name("feed-recorder".to_string())
+            .spawn(move || {
+                let written = write_frames(encoder, queue);
+                if let Err(e) = &written {
+                    warn!("Feed recording stopped : {e:#}");
+                }
+                written
+            })
+            .context("Failed to start feed recording writer")?;
+
+        Ok(Self {
+            writer: Arc::new(RecordingWriter {
+                lines: StdMutex::new(Some(lines)),
+                thread: StdMutex::new(Some(thread)),
+            }),
+        })
+    }
+
+    ///Queues frame for the writer, frames recorded after `finish` are dropped
+    pub fn record(&self, venue: Venue, received_time: DateTime<Utc>, text: &str) -> Result<()> {
+        let line = serde_json::to_string(&RecordedFrame {
+            venue,
+            received_time,
+            text: text.to_string(),
+        })?;
+
+        let lines = self
+            .writer
+            .lines
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Feed recorder is poisoned"))?;
+        let Some(lines) = lines.as_ref() else {
+            return Ok(());
+        };
+        lines
+            .send(line)
+            .map_err(|_| anyhow::anyhow!("Feed recording writer stopped"))
+    }
+
+    ///Recording failures are logged, they must not stop the feed
+    pub fn record_or_warn(&self, venue: Venue, received_time: DateTime<Utc>, text: &str) {
+        if let Err(e) = self.record(venue, received_time, text) {
+            warn!("{venue} frame not recorded : {e:#}");
+        }
+    }
+
+    ///Waits for queued frames and writes the gzip trailer, so the segment is complete for every reader
+    pub fn finish(&self) -> Result<()> {
+        self.writer.finish()
+    }
+}
+
+impl RecordingWriter {
+    fn finish(&self) -> Result<()> {
+        //Writer drains the closed queue before finishing the gzip member
+        self.lines
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Feed recorder is poisoned"))?
+            .take();
+        let thread = self
+            .thread
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Feed recorder is poisoned"))?
+            .take();
+        match thread {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| anyhow::anyhow!("Feed recording writer panicked"))?,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for RecordingWriter {
+    fn drop(&mut self) {
+        //Failures were already logged by the writer
+        let _ = self.finish();
+    }
+}
+
+///Writes queued lines until the queue is closed, then completes the gzip member
+fn write_frames(mut encoder: GzEncoder<File>, queue: mpsc::Receiver<String>) -> Result<()> {
+    for line in queue {
+        writeln!(encoder, "{line}").context("Failed to write feed recording")?;
+        encoder.flush().context("Failed to flush feed recording")?;
+    }
+    encoder
+        .finish()
+        .context("Failed to finish feed recording")?;
+
+    Ok(())
+}
+
+///Segment `index` of a recording, 0 is the recording path itself
+fn segment_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{index}"));
+    path.with_file_name(name)
+}
+
+///Existing segments of a recording in the order they were recorded
+pub fn recording_segments(path: &Path) -> Vec<PathBuf> {
+    (0..)
+        .map(|index| segment_path(path, index))
+        .take_while(|segment| segment.exists())
+        .collect()
+}
+
+/// Reads frames of all segments of a recording in recorded order
+///
+/// Segment left unfinished by a crash is read up to its last complete frame.
+/// Reading stops after the first error.
+pub struct FrameReader {
+    segments: VecDeque<PathBuf>,
+    segment: Option<(PathBuf, Lines<BufReader<MultiGzDecoder<File>>>)>,
+    done: bool,
+}
+
+impl FrameReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut reader = Self {
+            segments: recording_segments(path).into(),
+            segment: None,
+            done: false,
+        };
+        //Missing recording is reported right away
+        reader.segments.pop_front();
+        reader.segment = Some(Self::open_segment(path)?);
+
+        Ok(reader)
+    }
+
+    fn open_segment(path: &Path) -> Result<(PathBuf, Lines<BufReader<MultiGzDecoder<File>>>)> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open feed recording {}", path.display()))?;
+
+        Ok((
+            path.to_path_buf(),
+            BufReader::new(MultiGzDecoder::new(file)).lines(),
+        ))
+    }
+}
+
+impl Iterator for FrameReader {
+    type Item = Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let Some((path, lines)) = &mut self.segment else {
+                let path = self.segments.pop_front()?;
+                match Self::open_segment(&path) {
+                    Ok(segment) => self.segment = Some(segment),
+                    Err(e) => {
+                        self.done = true;
+                        return Some(Err(e));
+                    }
+                }
+                continue;
+            };
+
+            match lines.next() {
+                Some(Ok(line)) => {
+                    return Some(serde_json::from_str(&line).context("Invalid recorded frame"))
+                }
+                None => self.segment = None,
+                Some(Err(e)) if e.kind() == ErrorKind::UnexpectedEof => {
+                    warn!(
+                        "Feed recording {} is truncated, it was not finished",
+                        path.display()
+                    );
+                    self.segment = None;
+                }
+                Some(Err(e)) => {
+                    self.done = true;
+                    return Some(Err(e).with_context(|| {
+                        format!("Failed to read feed recording {}", path.display())
+                    }));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Frames of several recordings in receive time order
+///
+/// Each recording is already ordered, so the earliest pending frame is taken on every step.
+/// Frames received at the same time keep the order in which recordings were given.
+pub struct MergedFrames {
+    readers: Vec<Peekable<FrameReader>>,
+}
+
+impl MergedFrames {
+    pub fn open(paths: &[PathBuf]) -> Result<Self> {
+        Ok(Self {
+            readers: paths
+                .iter()
+                .map(|path| Ok(FrameReader::open(path)?.peekable()))
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+impl Iterator for MergedFrames {
+    type Item = Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut earliest: Option<(usize, DateTime<Utc>)> = None;
+        for (index, reader) in self.readers.iter_mut().enumerate() {
+            let received_time = match reader.peek() {
+                Some(Ok(frame)) => frame.received_time,
+                //Broken frames are reported right away
+                Some(Err(_)) => return reader.next(),
+                None => continue,
+            };
+            if earliest.is_none_or(|(_, earliest)| received_time < earliest) {
+                earliest = Some((index, received_time));
+            }
+        }
+
+        self.readers[earliest?.0].next()
+    }
+}
+
+///Recorded frame decoded into the book update of its venue
+#[derive(Debug)]
+pub enum FrameUpdate {
+    Aevo(OrderbookAEVOResponse),
+    Dxdy(OrderbookDXDYResponse),
+}
+
+impl FrameUpdate {
+    pub fn decode(frame: &RecordedFrame) -> Result<Self> {
+        Ok(match frame.venue {
+            Venue::Aevo => Self::Aevo(serde_json::from_str(&frame.text)?),
+            Venue::Dxdy => Self::Dxdy(serde_json::from_str(&frame.text)?),
+        })
+    }
+
+    ///Market the update belongs to, `None` for frames not tied to a market
+    pub fn market(&self) -> Option<&str> {
+        match self {
+            Self::Aevo(update) => OrderbookAEVO::update_market(update),
+            Self::Dxdy(update) => OrderbookDXDY::update_market(update),
+        }
+    }
+}
+
+///Running replay of a recording
+pub struct ReplayHandle {
+    ///Resolves to number of replayed frames
+    pub handle: JoinHandle<Result<u64>>,
+    pub health: watch::Receiver<FeedHealth>,
+    pub aevo_updates: watch::Receiver<Option<BookUpdate>>,
+    pub dxdy_updates: watch::Receiver<Option<BookUpdate>>,
+}
+
+/// Feeds recorded frames to books of their markets through `apply_changes`
+///
+/// Gaps between frames are divided by `speed`, 1 replays in real time,
+/// 0 replays as fast as possible. Update channels close once the recording ends.
+/// Sequence of dXdY messages is not checked, books are flagged only by integrity checks.
+/// Frames of markets without a book are skipped.
+pub fn spawn_replay(
+    path: &Path,
+    speed: f64,
+    aevo_books: VenueBooks<OrderbookAEVO>,
+    dxdy_books: VenueBooks<OrderbookDXDY>,
+) -> Result<ReplayHandle> {
+    let frames = FrameReader::open(path)?;
+    let (health_tx, health) = watch::channel(FeedHealth::Live);
+    let (aevo_notifier, aevo_updates) = BookUpdateNotifier::channel();
+    let (dxdy_notifier, dxdy_updates) = BookUpdateNotifier::channel();
+
+    let handle = tokio::spawn(async move {
+        let _health_tx = health_tx;
+        let mut replayed = 0;
+        let mut previous: Option<DateTime<Utc>> = None;
+
+        for frame in frames {
+            let frame = frame?;
+
+            if let Some(previous) = previous {
+                let gap = (frame.received_time - previous)
+                    .to_std()
+                    .unwrap_or_default();
+                let delay = replay_delay(gap, speed);
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+            }
+            previous = Some(frame.received_time);
+
+            let received_at = Instant::now();
+            let applied = match frame.venue {
+                Venue::Aevo => {
+                    apply_routed_frame(&aevo_books, &frame.text, received_at, frame.received_time)
+                        .await
+                }
+                Venue::Dxdy => {
+                    apply_routed_frame(&dxdy_books, &frame.text, received_at, frame.received_time)
+                        .await
+                }
+            }
+            .with_context(|| format!("Failed to replay {} frame {replayed}", frame.venue))?;
+            replayed += 1;
+
+            let Some((market, violation)) = applied else {
+                continue;
+            };
+            match frame.venue {
+                Venue::Aevo => aevo_notifier.notify(received_at),
+                Venue::Dxdy => dxdy_notifier.notify(received_at),
+            }
+            if let Some(violation) = violation {
+                warn!(
+                    "Replayed {} {market} orderbook is stale ({violation:?})",
+                    frame.venue
+                );
+            }
+        }
+
+        info!("Replay finished after {replayed} frames");
+        Ok(replayed)
+    });
+
+    Ok(ReplayHandle {
+        handle,
+        health,
+        aevo_updates,
+        dxdy_updates,
+    })
+}
+
+///Sleep between frames recorded `gap` apart
+fn replay_delay(gap: Duration, speed: f64) -> Duration {
+    if speed > 0.0 {
+        gap.div_f64(speed)
+    } else {
+        Duration::ZERO
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{error, warn};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+use crate::{
+    calculations::{ArbitrageLeg, ArbitrageOpportunity},
+    instrument::Size,
+    orderbook::{BookFreshness, BookIntegrity, Orderbook},
+    portfolio::Portfolio,
+    venue::{Side, Venue},
+};
+
+///Pre-trade limits, absent limits are not checked
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskConfig {
+    ///Largest quote notional of a single leg
+    pub max_trade_notional: Option<Decimal>,
+    ///Largest absolute position on any venue after a trade, in base asset of the pair
+    pub max_position: Option<Size>,
+    ///Trading stops for the rest of the UTC day after losing this much
+    pub max_daily_loss: Option<Decimal>,
+    ///Books without updates for longer are not traded
+    pub max_book_age_ms: u64,
+    ///Books whose last updates were received further apart are not compared
+    pub max_book_skew_ms: Option<u64>,
+    pub min_edge_bps: Decimal,
+    ///Trading halts once this file exists
+    pub kill_switch_file: Option<PathBuf>,
+    ///Close exposure opened by the bot when kill switch engages, deposited inventory is kept
+    pub flatten_on_kill: bool,
+}
+
+impl RiskConfig {
+    ///Limits applied when books are compared
+    pub fn freshness(&self) -> BookFreshness {
+        BookFreshness {
+            max_age: Some(Duration::from_millis(self.max_book_age_ms)),
+            max_skew: self.max_book_skew_ms.map(Duration::from_millis),
+        }
+    }
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            max_trade_notional: None,
+            max_position: None,
+            max_daily_loss: None,
+            max_book_age_ms: 10_000,
+            max_book_skew_ms: None,
+            min_edge_bps: Decimal::ZERO,
+            kill_switch_file: None,
+            flatten_on_kill: false,
+        }
+    }
+}
+
+///Reason a trade was blocked
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RiskViolation {
+    #[error("kill switch is engaged")]
+    KillSwitch,
+    #[error("daily loss {loss} reached limit {limit}")]
+    DailyLoss { loss: Decimal, limit: Decimal },
+    #[error("{venue} book is {integrity:?}")]
+    BrokenBook {
+        venue: Venue,
+        integrity: BookIntegrity,
+    },
+    #[error("{venue} book has not been updated for {age:?}, limit {limit:?}")]
+    StaleBook {
+        venue: Venue,
+        age: Option<Duration>,
+        limit: Duration,
+    },
+    #[error("edge {edge} bps is below minimum {min} bps")]
+    EdgeTooLow { edge: Decimal, min: Decimal },
+    #[error("{venue} notional {notional} exceeds limit {limit}")]
+    NotionalLimit {
+        venue: Venue,
+        notional: Decimal,
+        limit: Decimal,
+    },
+    #[error("{venue} position would reach {position}, limit {limit}")]
+    PositionLimit {
+        venue: Venue,
+        position: Size,
+        limit: Size,
+    },
+}
+
+///Health of a book at the moment of a risk check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookState {
+    pub venue: Venue,
+    ///Time since last applied update, `None` before the first one
+    pub age: Option<Duration>,
+    pub integrity: BookIntegrity,
+}
+
+impl BookState {
+    pub fn read<B: Orderbook>(book: &B) -> Self {
+        Self {
+            venue: book.venue(),
+            age: book.timestamps().age(),
+            integrity: book.check_integrity(),
+        }
+    }
+
+    ///State with age measured from receive time at simulated `now`
+    pub fn read_at<B: Orderbook>(book: &B, now: DateTime<Utc>) -> Self {
+        Self {
+            age: book.timestamps().age_at(now),
+            ..Self::read(book)
+        }
+    }
+}
+
+/// Halts trading when engaged, engagement can not be undone
+///
+/// Engaged by `engage`, by Ctrl-C once `listen_for_signal` runs,
+/// or by creating the configured file
+#[derive(Debug, Clone, Default)]
+pub struct KillSwitch {
+    engaged: Arc<AtomicBool>,
+    file: Option<PathBuf>,
+}
+
+impl KillSwitch {
+    pub fn new(file: Option<PathBuf>) -> Self {
+        Self {
+            engaged: Arc::new(AtomicBool::new(false)),
+            file,
+        }
+    }
+
+    pub fn engage(&self) {
+        if !self.engaged.swap(true, Ordering::SeqCst) {
+            error!("Kill switch engaged");
+        }
+    }
+
+    pub fn is_engaged(&self) -> bool {
+        if self.engaged.load(Ordering::SeqCst) {
+            return true;
+        }
+        if self.file.as_ref().is_some_and(|file| file.exists()) {
+            self.engage();
+            return true;
+        }
+        false
+    }
+
+    ///Engages kill switch on Ctrl-C instead of terminating the process
+    pub fn listen_for_signal(&self) -> JoinHandle<()> {
+        let kill_switch = self.clone();
+        tokio::spawn(async move {
+            match tokio::signal::ctrl_c().await {
+                Ok(()) => kill_switch.engage(),
+                Err(e) => warn!("Failed to listen for Ctrl-C : {e}"),
+            }
+        })
+    }
+}
+
+/// Checks opportunities against limits before anything is executed
+///
+/// Daily loss is measured from realized and unrealized profit and loss
+/// at the first check of each UTC day
+#[derive(Debug)]
+pub struct RiskManager {
+    config: RiskConfig,
+    kill_switch: KillSwitch,
+    day: Option<(NaiveDate, Decimal)>,
+}
+
+impl RiskManager {
+    pub fn new(config: RiskConfig, kill_switch: KillSwitch) -> Self {
+        Self {
+            config,
+            kill_switch,
+            day: None,
+        }
+    }
+
+    pub fn kill_switch(&self) -> &KillSwitch {
+        &self.kill_switch
+    }
+
+    pub fn check(
+        &mut self,
+        opportunity: &ArbitrageOpportunity,
+        portfolio: &Portfolio,
+        books: &[BookState],
+    ) -> Result<(), RiskViolation> {
+        self.check_at(opportunity, portfolio, books, Utc::now())
+    }
+
+    ///Checks opportunity with trading days taken from `now`, used when time is simulated
+    pub fn check_at(
+        &mut self,
+        opportunity: &ArbitrageOpportunity,
+        portfolio: &Portfolio,
+        books: &[BookState],
+        now: DateTime<Utc>,
+    ) -> Result<(), RiskViolation> {
+        if self.kill_switch.is_engaged() {
+            return Err(RiskViolation::KillSwitch);
+        }
+
+        if let Some(limit) = self.config.max_daily_loss {
+            let loss = -self.daily_p_l(portfolio, now);
+            if loss >= limit {
+                return Err(RiskViolation::DailyLoss { loss, limit });
+            }
+        }
+
+        let max_age = Duration::from_millis(self.config.max_book_age_ms);
+        for book in books {
+            if !book.integrity.is_healthy() {
+                return Err(RiskViolation::BrokenBook {
+                    venue: book.venue,
+                    integrity: book.integrity,
+                });
+            }
+            if book.age.is_none_or(|age| age > max_age) {
+                return Err(RiskViolation::StaleBook {
+                    venue: book.venue,
+                    age: book.age,
+                    limit: max_age,
+                });
+            }
+        }
+
+        if opportunity.net_edge_bps < self.config.min_edge_bps {
+            return Err(RiskViolation::EdgeTooLow {
+                edge: opportunity.net_edge_bps,
+                min: self.config.min_edge_bps,
+            });
+        }
+
+        for leg in [&opportunity.buy, &opportunity.sell] {
+            self.check_leg(leg, portfolio)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_leg(&self, leg: &ArbitrageLeg, portfolio: &Portfolio) -> Result<(), RiskViolation> {
+        if let Some(limit) = self.config.max_trade_notional {
+            if leg.notional > limit {
+                return Err(RiskViolation::NotionalLimit {
+                    venue: leg.venue,
+                    notional: leg.notional,
+                    limit,
+                });
+            }
+        }
+
+        if let Some(limit) = self.config.max_position {
+            let position = portfolio.position(leg.venue).position
+                + match leg.side {
+                    Side::Buy => leg.quantity,
+                    Side::Sell => -leg.quantity,
+                };
+            if position.abs() > limit {
+                return Err(RiskViolation::PositionLimit {
+                    venue: leg.venue,
+                    position,
+                    limit,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Profit and loss since the first check of current UTC day
+    fn daily_p_l(&mut self, portfolio: &Portfolio, now: DateTime<Utc>) -> Decimal {
+        let p_l = portfolio.realized_p_l() + portfolio.unrealized_p_l();
+        let today = now.date_naive();
+        match self.day {
+            Some((day, start)) if day == today => p_l - start,
+            _ => {
+                self.day = Some((today, p_l));
+                Decimal::ZERO
+            }
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::cmp;
+
+use rust_decimal::Decimal;
+
+use crate::{
+    fees::FeeSchedule,
+    instrument::{Price, Size},
+    orderbook::Orderbook,
+    portfolio::VenueBalance,
+};
+
+///Per-venue trading terms used when sizing trades
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VenueTerms {
+    pub fees: FeeSchedule,
+    ///Largest quantity a single order may trade on the venue
+    pub max_order_qty: Option<Size>,
+    ///Assets held on the venue, unlimited when absent
+    pub inventory: Option<VenueBalance>,
+}
+
+impl VenueTerms {
+    pub fn new(fees: FeeSchedule, max_order_qty: Option<Size>) -> Self {
+        Self {
+            fees,
+            max_order_qty,
+            inventory: None,
+        }
+    }
+
+    pub fn with_inventory(mut self, inventory: VenueBalance) -> Self {
+        self.inventory = Some(inventory);
+        self
+    }
+
+    ///Capital limited by USDC held when this venue is bought on
+    pub fn buy_capital(&self, capital: Decimal) -> Decimal {
+        match self.inventory {
+            Some(inventory) => capital.min(inventory.usdc),
+            None => capital,
+        }
+    }
+
+    ///Largest quantity that can be sold on this venue
+    pub fn max_sell_qty(&self) -> Option<Size> {
+        [
+            self.max_order_qty,
+            self.inventory.map(|inventory| inventory.base),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+}
+
+///Cumulative result of trading `quantity` on both books
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProfitPoint {
+    pub quantity: Size,
+    ///Paid for buy leg including taker fee
+    pub buy_cost: Decimal,
+    ///Received for sell leg after taker fee
+    pub sell_proceeds: Decimal,
+    pub profit: Decimal,
+    ///Prices of the last unit bought and sold
+    pub marginal_buy_price: Price,
+    pub marginal_sell_price: Price,
+}
+
+/// Profit maximising trade size
+///
+/// Curve contains one point per consumed pair of levels, so it shows how profit
+/// grows with size until marginal buy price crosses marginal sell price
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SizingResult {
+    pub quantity: Size,
+    pub profit: Decimal,
+    pub curve: Vec<ProfitPoint>,
+}
+
+impl SizingResult {
+    pub fn is_empty(&self) -> bool {
+        self.quantity.is_zero()
+    }
+}
+
+/// Walks asks of buy book and bids of sell book jointly
+///
+/// Every unit is added while it is profitable after fees, so the walk stops exactly
+/// where marginal buy price crosses marginal sell price,
+/// or earlier when capital, inventory or per-venue order limits are exhausted.
+/// Chunks are rounded down to step sizes of both books, rounding alone never ends the walk.
+pub fn optimal_size<B: Orderbook, S: Orderbook>(
+    buy_book: &B,
+    buy_terms: &VenueTerms,
+    sell_book: &S,
+    sell_terms: &VenueTerms,
+    capital: Decimal,
+) -> SizingResult {
+    let buy_rate = Decimal::ONE + buy_terms.fees.taker_rate();
+    let sell_rate = Decimal::ONE - sell_terms.fees.taker_rate();
+    let capital = buy_terms.buy_capital(capital);
+
+    let max_qty = [buy_terms.max_order_qty, sell_terms.max_sell_qty()]
+        .into_iter()
+        .flatten()
+        .min();
+
+    let mut asks = buy_book.ask_levels();
+    let mut bids = sell_book.bid_levels().rev();
+    let mut ask = asks.next();
+    let mut bid = bids.next();
+
+    let mut result = SizingResult::default();
+    let mut buy_cost = Decimal::ZERO;
+    let mut sell_proceeds = Decimal::ZERO;
+
+    while let (Some((ask_price, ask_size)), Some((bid_price, bid_size))) = (ask, bid) {
+        //Units are only profitable while fee adjusted bid is above fee adjusted ask
+        let unit_cost = ask_price * buy_rate;
+        let unit_proceeds = bid_price * sell_rate;
+        if unit_proceeds <= unit_cost {
+            break;
+        }
+
+        let level_chunk = cmp::min(ask_size, bid_size);
+        let mut chunk = cmp::min(level_chunk, (capital - buy_cost) / unit_cost);
+        if let Some(max_qty) = max_qty {
+            chunk = cmp::min(chunk, max_qty - result.quantity);
+        }
+        //Capital or order limit cut the chunk, no further unit fits
+        let limited = chunk < level_chunk;
+        chunk = sell_book
+            .spec()
+            .floor_size(buy_book.spec().floor_size(chunk));
+
+        if chunk <= Decimal::ZERO {
+            if limited {
+                break;
+            }
+            //Remainder of a level below step size is traded together with the next level,
+            //priced at the worse price of the next one
+            if ask_size <= bid_size {
+                ask = asks.next().map(|(price, size)| (price, size + ask_size));
+            } else {
+                bid = bids.next().map(|(price, size)| (price, size + bid_size));
+            }
+            continue;
+        }
+
+        result.quantity += chunk;
+        buy_cost += chunk * unit_cost;
+        sell_proceeds += chunk * unit_proceeds;
+        result.curve.push(ProfitPoint {
+            quantity: result.quantity,
+            buy_cost,
+            sell_proceeds,
+            profit: sell_proceeds - buy_cost,
+            marginal_buy_price: ask_price,
+            marginal_sell_price: bid_price,
+        });
+
+        //Move to next level on the side which was fully consumed
+        ask = if chunk == ask_size {
+            asks.next()
+        } else {
+            Some((ask_price, ask_size - chunk))
+        };
+        bid = if chunk == bid_size {
+            bids.next()
+        } else {
+            Some((bid_price, bid_size - chunk))
+        };
+
+        if limited {
+            break;
+        }
+    }
+
+    result.profit = sell_proceeds - buy_cost;
+    result
+}
@@ -0,0 +1,314 @@
+use arbitrage_bot::{
+    aevo::{
+        aevo_auth::AEVOCredentials,
+        aevo_order_client::{AEVOOrderClient, OrderTransportAEVO},
+        aevo_order_structs::{OrderPayloadAEVO, OrderResponseAEVO},
+        aevo_orderbook_feed::AEVOWSAuthenticator,
+        aevo_signer::{AEVOSigner, OrderSignatureDataAEVO, SigningDomainAEVO},
+    },
+    config::AEVOConfig,
+    order::{OrderClient, OrderReport, OrderRequest, OrderStatus, TimeInForce},
+    venue::{Side, Venue},
+};
+use futures::{SinkExt, StreamExt};
+use k256::ecdsa::{RecoveryId, Signature, SigningKey, VerifyingKey};
+use rust_decimal_macros::dec;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+use wiremock::{
+    matchers::{header_exists, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const SIGNING_KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+const ACCOUNT: &str = "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23";
+
+fn signer() -> AEVOSigner {
+    AEVOSigner::from_hex(SIGNING_KEY, SigningDomainAEVO::default()).unwrap()
+}
+
+fn credentials() -> AEVOCredentials {
+    AEVOCredentials::new("key".to_string(), "secret".to_string())
+}
+
+fn config(rest_url: String, order_transport: OrderTransportAEVO) -> AEVOConfig {
+    AEVOConfig {
+        rest_url,
+        account: Some(ACCOUNT.to_string()),
+        order_transport,
+        ..AEVOConfig::default()
+    }
+}
+
+fn filled_order() -> Value {
+    json!({
+        "order_id": "0xabc",
+        "side": "buy",
+        "amount": "0.5",
+        "filled": "0.5",
+        "avg_price": "2000.5",
+        "order_status": "filled"
+    })
+}
+
+async fn mock_rest() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/instrument/ETH-PERP"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "instrument_id": "1",
+            "instrument_name": "ETH-PERP"
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[test]
+fn order_signature_recovers_to_signing_key() {
+    let signer = signer();
+    assert_eq!(signer.address(), ACCOUNT);
+
+    let order = OrderSignatureDataAEVO {
+        maker: [0x11; 20],
+        is_buy: true,
+        limit_price: 2_000_000_000,
+        amount: 500_000,
+        salt: 7,
+        instrument: 1,
+        timestamp: 1_688_083_200,
+    };
+    let signature =
+        hex::decode(signer.sign_order(&order).unwrap().trim_start_matches("0x")).unwrap();
+    assert_eq!(signature.len(), 65);
+
+    let recovered = VerifyingKey::recover_from_prehash(
+        &signer.order_digest(&order),
+        &Signature::from_slice(&signature[..64]).unwrap(),
+        RecoveryId::from_byte(signature[64] - 27).unwrap(),
+    )
+    .unwrap();
+    let signing_key = SigningKey::from_slice(&hex::decode(SIGNING_KEY).unwrap()).unwrap();
+    assert_eq!(&recovered, signing_key.verifying_key());
+    assert!(!format!("{signer:?}").contains(SIGNING_KEY));
+}
+
+#[test]
+fn unknown_order_side_is_an_error() {
+    let mut order = filled_order();
+    order["side"] = json!("long");
+    let order: OrderResponseAEVO = serde_json::from_value(order).unwrap();
+
+    let error = order.to_report(1, TimeInForce::Ioc).unwrap_err();
+    assert!(error.to_string().contains("Unknown AEVO order side long"));
+}
+
+#[tokio::test]
+async fn places_signed_order_over_rest() {
+    let server = mock_rest().await;
+    Mock::given(method("POST"))
+        .and(path("/orders"))
+        .and(header_exists("AEVO-SIGNATURE"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(filled_order()))
+        .mount(&server)
+        .await;
+
+    let auth = AEVOWSAuthenticator::new("ws://127.0.0.1:1", credentials());
+    let client = AEVOOrderClient::connect(
+        &auth,
+        &config(server.uri(), OrderTransportAEVO::Rest),
+        signer(),
+    )
+    .await
+    .unwrap();
+
+    let request = OrderRequest::ioc(1, Side::Buy, dec!(0.5), dec!(2001.25));
+    let report = client.place_order(&request).await.unwrap();
+    assert_eq!(report.order_id, "0xabc");
+    assert_eq!(report.status, OrderStatus::Filled);
+    assert_eq!(report.filled_qty, dec!(0.5));
+    assert_eq!(report.avg_price, Some(dec!(2000.5)));
+
+    let requests = server.received_requests().await.unwrap();
+    let order_request = requests
+        .iter()
+        .find(|request| request.url.path() == "/orders")
+        .unwrap();
+    let payload: OrderPayloadAEVO = serde_json::from_slice(&order_request.body).unwrap();
+    assert_eq!(payload.instrument, "1");
+    assert_eq!(payload.maker, ACCOUNT);
+    assert!(payload.is_buy);
+    assert_eq!(payload.amount, "500000");
+    assert_eq!(payload.limit_price, "2001250000");
+    assert_eq!(payload.time_in_force, "IOC");
+
+    //Request is authenticated with HMAC of its exact body
+    let header = |name: &str| {
+        order_request
+            .headers
+            .iter()
+            .find(|(header, _)| header.as_str().eq_ignore_ascii_case(name))
+            .map(|(_, values)| values.last().as_str().to_string())
+            .unwrap()
+    };
+    assert_eq!(header("AEVO-KEY"), "key");
+    let timestamp: u128 = header("AEVO-TIMESTAMP").parse().unwrap();
+    let body = String::from_utf8(order_request.body.clone()).unwrap();
+    assert_eq!(
+        header("AEVO-SIGNATURE"),
+        credentials().sign(timestamp, "POST", "/orders", &body)
+    );
+}
+
+#[tokio::test]
+async fn off_tick_orders_are_rounded_to_tick_and_step() {
+    let server = mock_rest().await;
+    let auth = AEVOWSAuthenticator::new("ws://127.0.0.1:1", credentials());
+    let client = AEVOOrderClient::connect(
+        &auth,
+        &config(server.uri(), OrderTransportAEVO::Rest),
+        signer(),
+    )
+    .await
+    .unwrap();
+
+    //Buys never pay less than requested, sells never receive more
+    let buy = client
+        .build_order(&OrderRequest::ioc(
+            1,
+            Side::Buy,
+            dec!(0.505),
+            dec!(2001.253),
+        ))
+        .unwrap();
+    assert_eq!(buy.amount, "500000");
+    assert_eq!(buy.limit_price, "2001260000");
+
+    let sell = client
+        .build_order(&OrderRequest::ioc(
+            2,
+            Side::Sell,
+            dec!(0.505),
+            dec!(2001.257),
+        ))
+        .unwrap();
+    assert_eq!(sell.amount, "500000");
+    assert_eq!(sell.limit_price, "2001250000");
+
+    //Nothing is left to trade below one step
+    assert!(client
+        .build_order(&OrderRequest::ioc(3, Side::Buy, dec!(0.005), dec!(2001)))
+        .is_err());
+}
+
+#[tokio::test]
+async fn rest_rejection_and_cancellation() {
+    let server = mock_rest().await;
+    Mock::given(method("POST"))
+        .and(path("/orders"))
+        .respond_with(
+            ResponseTemplate::new(400).set_body_json(json!({ "error": "INSUFFICIENT_BALANCE" })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/orders/0xabc"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "order_id": "0xabc",
+            "side": "sell",
+            "amount": "1",
+            "filled": "0.4",
+            "avg_price": "2010",
+            "order_status": "opened"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/orders/0xabc"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "order_id": "0xabc" })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let auth = AEVOWSAuthenticator::new("ws://127.0.0.1:1", credentials());
+    let client = AEVOOrderClient::connect(
+        &auth,
+        &config(server.uri(), OrderTransportAEVO::Rest),
+        signer(),
+    )
+    .await
+    .unwrap();
+
+    let request = OrderRequest::limit(2, Side::Sell, dec!(1), dec!(2010));
+    let error = client.place_order(&request).await.unwrap_err();
+    assert!(format!("{error:#}").contains("INSUFFICIENT_BALANCE"));
+
+    let placed = OrderReport::pending(Venue::Aevo, "0xabc".to_string(), &request);
+    let placed = client.query_order(&placed).await.unwrap();
+    assert_eq!(placed.status, OrderStatus::PartiallyFilled);
+    assert_eq!(placed.filled_qty, dec!(0.4));
+
+    client.cancel_order(&placed).await.unwrap();
+}
+
+#[tokio::test]
+async fn places_order_over_websocket() {
+    let server = mock_rest().await;
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+
+    let venue = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut websocket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+        let auth: Value = match websocket.next().await.unwrap().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            message => panic!("unexpected {message:?}"),
+        };
+        assert_eq!(auth["op"], "auth");
+        websocket
+            .send(Message::Text(
+                json!({ "data": { "success": true } }).to_string(),
+            ))
+            .await
+            .unwrap();
+
+        let request: Value = match websocket.next().await.unwrap().unwrap() {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            message => panic!("unexpected {message:?}"),
+        };
+        assert_eq!(request["op"], "create_order");
+        assert_eq!(request["data"]["is_buy"], false);
+
+        //Unrelated channel message arrives before acknowledgement
+        websocket
+            .send(Message::Text(
+                json!({ "channel": "fills", "data": {} }).to_string(),
+            ))
+            .await
+            .unwrap();
+        websocket
+            .send(Message::Text(
+                json!({ "id": request["id"], "data": filled_order() }).to_string(),
+            ))
+            .await
+            .unwrap();
+    });
+
+    let auth = AEVOWSAuthenticator::new(&ws_url, credentials());
+    let client = AEVOOrderClient::connect(
+        &auth,
+        &config(server.uri(), OrderTransportAEVO::Websocket),
+        signer(),
+    )
+    .await
+    .unwrap();
+
+    let request = OrderRequest::ioc(3, Side::Sell, dec!(0.5), dec!(1999));
+    let report = client.place_order(&request).await.unwrap();
+
+    assert_eq!(report.order_id, "0xabc");
+    assert_eq!(report.status, OrderStatus::Filled);
+    venue.await.unwrap();
+}
@@ -0,0 +1,208 @@
+use anyhow::Result;
+use arbitrage_bot::{
+    backtest::{max_drawdown, run_backtest, sharpe_ratio, EquityPoint},
+    config::Config,
+    fees::FeeSchedule,
+    recording::RecordedFrame,
+    venue::Venue,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+const AEVO_SNAPSHOT: &str = r#"{"channel":"orderbook:ETH-PERP","data":{"type":"snapshot","instrument_type":"PERPETUAL","bids":[["1999","1","0"]],"asks":[["2000","2","0"]],"last_updated":"1700000000000000000"}}"#;
+const DXDY_SUBSCRIBED: &str = r#"{"type":"subscribed","connection_id":"c","message_id":1,"id":"ETH-USD","contents":{"bids":[{"price":"2010","size":"1"}],"asks":[{"price":"2011","size":"3"}]}}"#;
+const AEVO_BTC_SNAPSHOT: &str = r#"{"channel":"orderbook:BTC-PERP","data":{"type":"snapshot","instrument_type":"PERPETUAL","bids":[["59990","1","0"]],"asks":[["60000","1","0"]],"last_updated":"1700000000000000000"}}"#;
+const DXDY_BTC_SUBSCRIBED: &str = r#"{"type":"subscribed","connection_id":"c","message_id":2,"id":"BTC-USD","contents":{"bids":[{"price":"60300","size":"1"}],"asks":[{"price":"60301","size":"1"}]}}"#;
+const DXDY_SOL_SUBSCRIBED: &str = r#"{"type":"subscribed","connection_id":"c","message_id":3,"id":"SOL-USD","contents":{"bids":[{"price":"150","size":"1"}],"asks":[{"price":"151","size":"1"}]}}"#;
+const DXDY_BID_REMOVED: &str = r#"{"type":"channel_data","connection_id":"c","message_id":2,"id":"ETH-USD","version":"1","contents":{"bids":[{"price":"2010","size":"0"},{"price":"1990","size":"1"}]}}"#;
+
+fn start() -> DateTime<Utc> {
+    DateTime::from_timestamp_nanos(1_700_000_000_010_000_000)
+}
+
+fn frame(venue: Venue, offset_ms: i64, text: &str) -> Result<RecordedFrame> {
+    Ok(RecordedFrame {
+        venue,
+        received_time: start() + TimeDelta::milliseconds(offset_ms),
+        text: text.to_string(),
+    })
+}
+
+fn config(latency_ms: u64) -> Config {
+    let mut config = Config::default();
+    config.aevo.fees = FeeSchedule::zero();
+    config.dxdy.fees = FeeSchedule::zero();
+    config.paper.latency_ms = latency_ms;
+    config.paper.slippage_bps = Decimal::ZERO;
+    config
+}
+
+#[test]
+fn opportunity_is_traded_at_recorded_time() {
+    let report = run_backtest(
+        &config(0),
+        [
+            frame(Venue::Aevo, 0, AEVO_SNAPSHOT),
+            frame(Venue::Dxdy, 20, DXDY_SUBSCRIBED),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(report.frames, 2);
+    assert_eq!(report.opportunities, 1);
+    //Paper balances allow 0.5 ETH bought on AEVO and sold on dXdY
+    assert_eq!(report.trades.len(), 1);
+    let trade = &report.trades[0];
+    assert_eq!(trade.buy.venue, Venue::Aevo);
+    assert_eq!(trade.buy.quantity, dec!(0.5));
+    assert_eq!(trade.realized_profit, dec!(5));
+    assert_eq!(trade.executed_at, start() + TimeDelta::milliseconds(20));
+    assert_eq!(report.realized_p_l, dec!(5));
+    assert_eq!(report.hit_rate, Some(Decimal::ONE));
+    assert_eq!(report.end, Some(start() + TimeDelta::milliseconds(20)));
+}
+
+#[test]
+fn fills_use_books_after_latency() {
+    let frames = || {
+        [
+            frame(Venue::Aevo, 0, AEVO_SNAPSHOT),
+            frame(Venue::Dxdy, 20, DXDY_SUBSCRIBED),
+            frame(Venue::Dxdy, 100, DXDY_BID_REMOVED),
+        ]
+    };
+
+    //Bid disappears before latency elapses, sell leg fills at a loss
+    let report = run_backtest(&config(200), frames()).unwrap();
+    assert_eq!(report.opportunities, 1);
+    assert_eq!(report.trades.len(), 1);
+    assert_eq!(report.trades[0].realized_profit, dec!(-5));
+    assert_eq!(
+        report.trades[0].executed_at,
+        start() + TimeDelta::milliseconds(220)
+    );
+    assert_eq!(report.expected_p_l, dec!(5));
+    assert_eq!(report.hit_rate, Some(Decimal::ZERO));
+
+    //Bid is still there once shorter latency elapses
+    let report = run_backtest(&config(50), frames()).unwrap();
+    assert_eq!(report.trades[0].realized_profit, dec!(5));
+}
+
+#[test]
+fn equity_is_sampled_on_interval_grid() {
+    let report = run_backtest(
+        &config(0),
+        [
+            frame(Venue::Aevo, 0, AEVO_SNAPSHOT),
+            frame(Venue::Dxdy, 20, DXDY_SUBSCRIBED),
+            frame(Venue::Dxdy, 150_000, DXDY_BID_REMOVED),
+        ],
+    )
+    .unwrap();
+
+    //Quiet minutes carry equity forward, the run closes on the next grid point
+    let times: Vec<_> = report.equity.iter().map(|point| point.time).collect();
+    let minute = |minutes| start() + TimeDelta::minutes(minutes);
+    assert_eq!(times, [minute(0), minute(1), minute(2), minute(3)]);
+    assert_eq!(report.equity[0].p_l(), Decimal::ZERO);
+    assert_eq!(
+        report.equity[1],
+        EquityPoint {
+            time: minute(1),
+            ..report.equity[2]
+        }
+    );
+    //0.5 bought at 2000 and sold at 2010 are marked at mids 1999.5 and 2010.5
+    assert_eq!(report.equity[1].p_l(), dec!(-0.5));
+    assert_eq!(report.equity[3].p_l(), report.final_p_l);
+}
+
+#[test]
+fn drawdown_and_sharpe_follow_equity_curve() {
+    let equity: Vec<_> = [dec!(0), dec!(10), dec!(4), dec!(12), dec!(9)]
+        .into_iter()
+        .enumerate()
+        .map(|(minute, p_l)| EquityPoint {
+            time: start() + TimeDelta::minutes(minute as i64),
+            realized_p_l: p_l,
+            unrealized_p_l: Decimal::ZERO,
+        })
+        .collect();
+
+    assert_eq!(max_drawdown(&equity), dec!(6));
+
+    let sharpe = sharpe_ratio(&equity, dec!(1000), TimeDelta::minutes(1)).unwrap();
+    //Returns 1%, -0.6%, 0.8%, -0.3% annualized over minutes of a year
+    let returns = [0.01, -0.006, 0.008, -0.003];
+    let mean = returns.iter().sum::<f64>() / 4.0;
+    let deviation = (returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / 3.0).sqrt();
+    let expected = mean / deviation * (365.0f64 * 24.0 * 60.0).sqrt();
+    assert!((sharpe - expected).abs() < 1e-9);
+
+    //Flat curve has no risk to measure
+    assert_eq!(
+        sharpe_ratio(&equity[..1], dec!(1000), TimeDelta::minutes(1)),
+        None
+    );
+}
+
+#[test]
+fn every_market_pair_is_traded_on_its_own_books() {
+    let markets = Config::from_toml(
+        r#"
+        [[markets]]
+        aevo = { symbol = "ETH-PERP", tick_size = "0.01", step_size = "0.01" }
+        dxdy = { symbol = "ETH-USD", tick_size = "0.1", step_size = "0.001" }
+        capital = 1000
+        paper = { aevo = { usdc = 1000, base = "0.5" }, dxdy = { usdc = 1000, base = "0.5" } }
+
+        [[markets]]
+        aevo = { symbol = "BTC-PERP", tick_size = "0.5", step_size = "0.001" }
+        dxdy = { symbol = "BTC-USD", tick_size = "1", step_size = "0.0001" }
+        capital = 6000
+        paper = { aevo = { usdc = 10000, base = "0.1" }, dxdy = { usdc = 10000, base = "0.1" } }
+        "#,
+    )
+    .unwrap();
+    markets.validate().unwrap();
+    let mut config = config(0);
+    config.markets = markets.markets;
+
+    let report = run_backtest(
+        &config,
+        [
+            frame(Venue::Aevo, 0, AEVO_SNAPSHOT),
+            frame(Venue::Aevo, 10, AEVO_BTC_SNAPSHOT),
+            frame(Venue::Dxdy, 20, DXDY_SUBSCRIBED),
+            frame(Venue::Dxdy, 30, DXDY_BTC_SUBSCRIBED),
+            //Markets without a pair are skipped
+            frame(Venue::Dxdy, 40, DXDY_SOL_SUBSCRIBED),
+        ],
+    )
+    .unwrap();
+
+    assert_eq!(report.frames, 5);
+    assert_eq!(report.rejected_frames, 0);
+    assert_eq!(report.trades.len(), 2);
+    let markets: Vec<_> = report
+        .trades
+        .iter()
+        .map(|trade| trade.market.as_deref().unwrap())
+        .collect();
+    assert_eq!(markets, ["ETH-PERP/ETH-USD", "BTC-PERP/BTC-USD"]);
+    //BTC pair buys 0.1 BTC within its own capital and balances
+    assert_eq!(report.trades[1].buy.quantity, dec!(0.1));
+    assert_eq!(report.trades[1].realized_profit, dec!(30));
+    assert_eq!(report.markets.len(), 2);
+    //Pair reports come from portfolios marked at each venue's own mid, inventory sold
+    //on dXdY was valued at its mid, so the spread the trade locked in is not realized yet
+    assert_eq!(report.markets[1].realized_p_l, dec!(-0.05));
+    assert_eq!(report.markets[1].final_p_l, dec!(-0.55));
+    assert_eq!(
+        report.final_p_l,
+        report.markets.iter().map(|market| market.final_p_l).sum()
+    );
+    assert_eq!(report.realized_p_l, dec!(35));
+}
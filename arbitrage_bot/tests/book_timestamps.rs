@@ -0,0 +1,99 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use arbitrage_bot::{
+    aevo::aevo_structs::{OrderbookAEVO, OrderbookAEVOResponse},
+    calculations::check_orderbooks,
+    fees::FeeSchedule,
+    orderbook::{BookFreshness, Orderbook},
+    sizing::VenueTerms,
+};
+use chrono::{DateTime, TimeDelta};
+use common::books::{aevo_spec, dxdy_book, shared};
+use rust_decimal_macros::dec;
+
+fn aevo_snapshot(last_updated: &str) -> OrderbookAEVOResponse {
+    serde_json::from_str(&format!(
+        r#"{{
+            "channel": "orderbook:ETH-PERP",
+            "data": {{
+                "type": "snapshot",
+                "instrument_type": "PERPETUAL",
+                "bids": [["1999", "1", "0"]],
+                "asks": [["2000", "1", "0"]],
+                "last_updated": "{last_updated}"
+            }}
+        }}"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn aevo_book_records_exchange_and_receive_time() {
+    let mut book = OrderbookAEVO::new(aevo_spec());
+    book.apply_changes(aevo_snapshot("1700000000000000000"))
+        .unwrap();
+
+    let exchange_time = DateTime::from_timestamp_nanos(1_700_000_000_000_000_000);
+    assert_eq!(book.timestamps().exchange_time, Some(exchange_time));
+    assert_eq!(book.timestamps().feed_latency(), None);
+
+    book.record_receipt(Instant::now(), exchange_time + TimeDelta::milliseconds(35));
+    assert_eq!(
+        book.timestamps().feed_latency(),
+        Some(Duration::from_millis(35))
+    );
+    assert!(book.timestamps().age().is_some());
+
+    book.reset();
+    assert_eq!(book.timestamps().received_at, None);
+    assert_eq!(book.timestamps().exchange_time, None);
+}
+
+#[tokio::test]
+async fn books_updated_far_apart_are_not_compared() {
+    let mut aevo = OrderbookAEVO::new(aevo_spec());
+    aevo.apply_changes(aevo_snapshot("1700000000000000000"))
+        .unwrap();
+    let mut dxdy = dxdy_book(&[(dec!(2010), dec!(1))], &[(dec!(2011), dec!(1))]);
+
+    let now = Instant::now();
+    let received_time = chrono::Utc::now();
+    aevo.record_receipt(now - Duration::from_millis(800), received_time);
+    dxdy.record_receipt(now, received_time);
+
+    let aevo = shared(aevo);
+    let dxdy = shared(dxdy);
+    let terms = VenueTerms::new(FeeSchedule::zero(), None);
+    let check = |freshness| {
+        check_orderbooks(
+            aevo.clone(),
+            dxdy.clone(),
+            &terms,
+            &terms,
+            dec!(10000),
+            freshness,
+        )
+    };
+
+    assert!(check(BookFreshness::default()).await.is_some());
+    assert!(check(BookFreshness {
+        max_age: Some(Duration::from_secs(5)),
+        max_skew: Some(Duration::from_secs(1)),
+    })
+    .await
+    .is_some());
+    assert!(check(BookFreshness {
+        max_age: None,
+        max_skew: Some(Duration::from_millis(500)),
+    })
+    .await
+    .is_none());
+    assert!(check(BookFreshness {
+        max_age: Some(Duration::from_millis(500)),
+        max_skew: None,
+    })
+    .await
+    .is_none());
+}
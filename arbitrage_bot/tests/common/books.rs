@@ -0,0 +1,47 @@
+//! Orderbook builders shared by the integration tests
+
+use std::sync::Arc;
+
+use arbitrage_bot::{
+    aevo::aevo_structs::OrderbookAEVO, dxdy::dxdy_structs::OrderbookDXDY,
+    instrument::InstrumentSpec,
+};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use tokio::sync::Mutex;
+
+///Book levels as (price, size)
+pub type BookLevels<'a> = &'a [(Decimal, Decimal)];
+
+///Tick and step of the AEVO ETH perpetual
+pub fn aevo_spec() -> InstrumentSpec {
+    InstrumentSpec::new(dec!(0.01), dec!(0.01))
+}
+
+///Tick and step of the dXdY ETH-USD market
+pub fn dxdy_spec() -> InstrumentSpec {
+    InstrumentSpec::new(dec!(0.1), dec!(0.001))
+}
+
+///Live AEVO book holding the given levels
+pub fn aevo_book(bids: BookLevels, asks: BookLevels) -> OrderbookAEVO {
+    let mut book = OrderbookAEVO::new(aevo_spec());
+    book.bids = bids.iter().map(|&(p, s)| (p, (p, s, 0.0))).collect();
+    book.asks = asks.iter().map(|&(p, s)| (p, (p, s, 0.0))).collect();
+    book.stale = false;
+    book
+}
+
+///Live dXdY book holding the given levels
+pub fn dxdy_book(bids: BookLevels, asks: BookLevels) -> OrderbookDXDY {
+    let mut book = OrderbookDXDY::new(dxdy_spec());
+    book.bids = bids.iter().map(|&(p, s)| (p, (p, s))).collect();
+    book.asks = asks.iter().map(|&(p, s)| (p, (p, s))).collect();
+    book.stale = false;
+    book
+}
+
+///Book behind the lock feeds and the main loop share
+pub fn shared<B>(book: B) -> Arc<Mutex<B>> {
+    Arc::new(Mutex::new(book))
+}
@@ -0,0 +1,358 @@
+//! Orderbook builders and mock AEVO and dXdY websocket servers for integration tests
+//!
+//! Servers speak enough of each venue protocol for the feeds: AEVO `auth`, `channels`
+//! and `orderbook` requests, dXdY `subscribe` / `unsubscribe` with `subscribed`
+//! and `channel_data` answers. Every accepted connection plays the next scripted session.
+//! Book frames belong to the first subscribed market until `Script::Market` switches it.
+#![allow(dead_code)]
+
+pub mod books;
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex as StdMutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use futures::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+///Price levels as (price, size)
+pub type Levels = &'static [(&'static str, &'static str)];
+
+pub const AEVO_CHANNELS: &[&str] = &[
+    "ticker:ETH:PERPETUAL",
+    "orderbook:ETH-PERP",
+    "orderbook:BTC-PERP",
+];
+
+///Step of a scripted session, played once the feed subscribes
+#[derive(Debug, Clone)]
+pub enum Script {
+    ///Full book, resent whenever the feed asks for a snapshot again
+    Snapshot {
+        bids: Levels,
+        asks: Levels,
+    },
+    ///Changed levels, zero size removes a level
+    Delta {
+        bids: Levels,
+        asks: Levels,
+    },
+    ///Text sent as is, e.g. malformed JSON
+    Raw(&'static str),
+    Pause(Duration),
+    ///Drops TCP connection without closing handshake
+    Disconnect,
+    ///Skips a dXdY message id, so that the feed sees a sequence gap
+    SkipSequence,
+    ///Following book frames belong to this market
+    Market(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Protocol {
+    Aevo,
+    Dxdy,
+}
+
+/// Venue websocket server on a local port
+///
+/// Connections beyond scripted sessions get no book data. Server stops when dropped.
+pub struct MockVenue {
+    pub url: String,
+    requests: Arc<StdMutex<Vec<Value>>>,
+    connections: Arc<AtomicUsize>,
+    handle: JoinHandle<()>,
+}
+
+impl Drop for MockVenue {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl MockVenue {
+    pub async fn aevo(sessions: Vec<Vec<Script>>) -> Self {
+        Self::start(Protocol::Aevo, sessions).await
+    }
+
+    pub async fn dxdy(sessions: Vec<Vec<Script>>) -> Self {
+        Self::start(Protocol::Dxdy, sessions).await
+    }
+
+    async fn start(protocol: Protocol, sessions: Vec<Vec<Script>>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(StdMutex::new(Vec::new()));
+        let connections = Arc::new(AtomicUsize::new(0));
+
+        let handle = tokio::spawn({
+            let requests = requests.clone();
+            let connections = connections.clone();
+            async move {
+                let mut sessions = sessions.into_iter();
+                while let Ok((stream, _)) = listener.accept().await {
+                    connections.fetch_add(1, Ordering::SeqCst);
+                    let script = sessions.next().unwrap_or_default();
+                    tokio::spawn(serve(protocol, stream, script, requests.clone()));
+                }
+            }
+        });
+
+        Self {
+            url,
+            requests,
+            connections,
+            handle,
+        }
+    }
+
+    ///JSON requests received over all connections
+    pub fn requests(&self) -> Vec<Value> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    ///Number of requests with `field` equal to `value`
+    pub fn count_requests(&self, field: &str, value: &str) -> usize {
+        self.requests()
+            .iter()
+            .filter(|request| request[field] == value)
+            .count()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+///State of one connection
+struct Session {
+    protocol: Protocol,
+    websocket: WebSocketStream<TcpStream>,
+    script: Option<Vec<Script>>,
+    ///Last snapshot of every market
+    snapshots: HashMap<String, (Levels, Levels)>,
+    market: String,
+    message_id: u64,
+}
+
+async fn serve(
+    protocol: Protocol,
+    stream: TcpStream,
+    script: Vec<Script>,
+    requests: Arc<StdMutex<Vec<Value>>>,
+) {
+    let Ok(websocket) = tokio_tungstenite::accept_async(stream).await else {
+        return;
+    };
+    let mut session = Session {
+        protocol,
+        websocket,
+        script: Some(script),
+        snapshots: HashMap::new(),
+        market: String::new(),
+        message_id: 0,
+    };
+
+    if protocol == Protocol::Dxdy {
+        let connected = json!({ "type": "connected", "connection_id": "mock", "message_id": 0 });
+        if session.send(connected).await.is_err() {
+            return;
+        }
+    }
+
+    while let Some(Ok(message)) = session.websocket.next().await {
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let Ok(request) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        requests.lock().unwrap().push(request.clone());
+
+        if session.answer(&request).await.is_err() {
+            return;
+        }
+    }
+}
+
+///Connection ends, either on failure or by scripted disconnect
+struct Closed;
+
+impl Session {
+    async fn answer(&mut self, request: &Value) -> Result<(), Closed> {
+        match (
+            self.protocol,
+            request["op"].as_str(),
+            request["type"].as_str(),
+        ) {
+            (Protocol::Aevo, Some("auth"), _) => {
+                self.send(json!({ "id": request["id"], "data": { "success": true } }))
+                    .await
+            }
+            (Protocol::Aevo, Some("channels"), _) => {
+                self.send(json!({ "data": AEVO_CHANNELS })).await
+            }
+            (Protocol::Aevo, Some("orderbook"), _) => {
+                let markets = request["data"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|channel| channel.as_str()?.strip_prefix("orderbook:"))
+                    .map(str::to_string)
+                    .collect();
+                self.subscribed(markets).await
+            }
+            (Protocol::Dxdy, _, Some("subscribe")) => {
+                let market = request["id"].as_str().unwrap_or_default().to_string();
+                self.subscribed(vec![market]).await
+            }
+            (Protocol::Dxdy, _, Some("unsubscribe")) => {
+                let id = self.next_message_id();
+                self.send(json!({
+                    "type": "unsubscribed",
+                    "connection_id": "mock",
+                    "message_id": id,
+                    "channel": "v4_orderbook",
+                    "id": request["id"],
+                }))
+                .await
+            }
+            _ => Ok(()),
+        }
+    }
+
+    ///Plays the script on first subscription, later ones get last snapshots of their markets again
+    async fn subscribed(&mut self, markets: Vec<String>) -> Result<(), Closed> {
+        match self.script.take() {
+            Some(script) => {
+                self.market = markets.into_iter().next().unwrap_or_default();
+                for step in script {
+                    self.play(step).await?;
+                }
+                Ok(())
+            }
+            None => {
+                for market in markets {
+                    if let Some(&(bids, asks)) = self.snapshots.get(&market) {
+                        self.market = market;
+                        self.play(Script::Snapshot { bids, asks }).await?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn play(&mut self, step: Script) -> Result<(), Closed> {
+        match step {
+            Script::Snapshot { bids, asks } => {
+                self.snapshots.insert(self.market.clone(), (bids, asks));
+                let frame = self.book_frame(true, bids, asks);
+                self.send(frame).await
+            }
+            Script::Delta { bids, asks } => {
+                let frame = self.book_frame(false, bids, asks);
+                self.send(frame).await
+            }
+            Script::Raw(text) => self.send_text(text.to_string()).await,
+            Script::Pause(delay) => {
+                tokio::time::sleep(delay).await;
+                Ok(())
+            }
+            Script::Disconnect => Err(Closed),
+            Script::SkipSequence => {
+                self.next_message_id();
+                Ok(())
+            }
+            Script::Market(market) => {
+                self.market = market.to_string();
+                Ok(())
+            }
+        }
+    }
+
+    fn book_frame(&mut self, snapshot: bool, bids: Levels, asks: Levels) -> Value {
+        match self.protocol {
+            Protocol::Aevo => {
+                let levels = |levels: Levels| -> Vec<[&str; 3]> {
+                    levels
+                        .iter()
+                        .map(|&(price, size)| [price, size, "0"])
+                        .collect()
+                };
+                json!({
+                    "channel": format!("orderbook:{}", self.market),
+                    "data": {
+                        "type": if snapshot { "snapshot" } else { "update" },
+                        "instrument_type": "PERPETUAL",
+                        "bids": levels(bids),
+                        "asks": levels(asks),
+                        "last_updated": Utc::now().timestamp_nanos_opt().unwrap().to_string(),
+                    }
+                })
+            }
+            Protocol::Dxdy => {
+                let levels = |levels: Levels| -> Vec<Value> {
+                    levels
+                        .iter()
+                        .map(|&(price, size)| json!({ "price": price, "size": size }))
+                        .collect()
+                };
+                let id = self.next_message_id();
+                let mut frame = json!({
+                    "type": if snapshot { "subscribed" } else { "channel_data" },
+                    "connection_id": "mock",
+                    "message_id": id,
+                    "channel": "v4_orderbook",
+                    "id": self.market,
+                    "contents": { "bids": levels(bids), "asks": levels(asks) },
+                });
+                if !snapshot {
+                    frame["version"] = json!("1");
+                }
+                frame
+            }
+        }
+    }
+
+    fn next_message_id(&mut self) -> u64 {
+        self.message_id += 1;
+        self.message_id
+    }
+
+    async fn send(&mut self, frame: Value) -> Result<(), Closed> {
+        self.send_text(frame.to_string()).await
+    }
+
+    async fn send_text(&mut self, text: String) -> Result<(), Closed> {
+        self.websocket
+            .send(Message::Text(text))
+            .await
+            .map_err(|_| Closed)
+    }
+}
+
+///Polls `condition` until it holds, failing the test after 10 seconds
+pub async fn wait_for<F, Fut>(what: &str, mut condition: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !condition().await {
+        assert!(Instant::now() < deadline, "Timed out waiting for {what}");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
@@ -0,0 +1,142 @@
+use arbitrage_bot::config::{Cli, Config};
+use rust_decimal_macros::dec;
+
+#[test]
+fn default_markets_are_venue_tickers() {
+    let config = Config::default();
+
+    assert_eq!(config.aevo.instrument, "ETH-PERP");
+    //dYdX v4 perpetuals are quoted in USD
+    assert_eq!(config.dxdy.market, "ETH-USD");
+    config.validate().unwrap();
+}
+
+#[test]
+fn example_config_matches_defaults() {
+    let example = Config::from_toml(include_str!("../config.example.toml")).unwrap();
+
+    assert_eq!(example.dxdy.market, Config::default().dxdy.market);
+    assert_eq!(example.aevo.instrument, Config::default().aevo.instrument);
+    example.validate().unwrap();
+}
+
+#[test]
+fn several_markets_need_their_own_collateral() {
+    let markets = r#"
+        [[markets]]
+        aevo = { symbol = "ETH-PERP", tick_size = "0.01", step_size = "0.01" }
+        dxdy = { symbol = "ETH-USD", tick_size = "0.1", step_size = "0.001" }
+        capital = 1000
+        paper = { aevo = { usdc = 1000, base = "0.5" }, dxdy = { usdc = 1000, base = "0.5" } }
+
+        [[markets]]
+        aevo = { symbol = "BTC-PERP", tick_size = "0.5", step_size = "0.001" }
+        dxdy = { symbol = "BTC-USD", tick_size = "1", step_size = "0.0001" }
+        capital = 2000
+        paper = { aevo = { usdc = 2000, base = "0.05" }, dxdy = { usdc = 2000, base = "0.05" } }
+    "#;
+    let mut config = Config::from_toml(markets).unwrap();
+    config.validate().unwrap();
+
+    //Live trading spends portfolio inventory, paper balances do not cover it
+    config.live = true;
+    let error = config.validate().unwrap_err();
+    assert!(format!("{error:#}").contains("markets[0] must set capital and portfolio"));
+
+    //Single pair falls back to top level values
+    config.markets.truncate(1);
+    config.markets[0].capital = None;
+    config.validate().unwrap();
+}
+
+#[test]
+fn eth_balances_are_read_as_base_asset() {
+    let config = Config::from_toml(
+        r#"
+        [paper]
+        aevo = { usdc = 1000, eth = "0.5" }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.paper.aevo.base, dec!(0.5));
+}
+
+#[test]
+fn several_markets_limit_positions_in_their_base_asset() {
+    let markets = r#"
+        [risk]
+        max_position = "2"
+
+        [[markets]]
+        aevo = { symbol = "ETH-PERP", tick_size = "0.01", step_size = "0.01" }
+        dxdy = { symbol = "ETH-USD", tick_size = "0.1", step_size = "0.001" }
+        capital = 1000
+        paper = { aevo = { usdc = 1000, base = "0.5" }, dxdy = { usdc = 1000, base = "0.5" } }
+        max_position = "2"
+
+        [[markets]]
+        aevo = { symbol = "BTC-PERP", tick_size = "0.5", step_size = "0.001" }
+        dxdy = { symbol = "BTC-USD", tick_size = "1", step_size = "0.0001" }
+        capital = 2000
+        paper = { aevo = { usdc = 2000, base = "0.05" }, dxdy = { usdc = 2000, base = "0.05" } }
+    "#;
+    let mut config = Config::from_toml(markets).unwrap();
+    let error = config.validate().unwrap_err();
+    assert!(format!("{error:#}").contains("markets[1] must set max_position"));
+
+    config.markets[1].max_position = Some(dec!(0.1));
+    config.validate().unwrap();
+    let limits: Vec<_> = config
+        .markets()
+        .iter()
+        .map(|market| config.for_market(market).risk.max_position)
+        .collect();
+    assert_eq!(limits, [Some(dec!(2)), Some(dec!(0.1))]);
+}
+
+#[test]
+fn market_flags_conflict_with_configured_markets() {
+    let path = std::env::temp_dir().join(format!("config_markets_{}.toml", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"
+        [[markets]]
+        aevo = { symbol = "BTC-PERP", tick_size = "0.5", step_size = "0.001" }
+        dxdy = { symbol = "BTC-USD", tick_size = "1", step_size = "0.0001" }
+        "#,
+    )
+    .unwrap();
+
+    let error = Config::load(&Cli {
+        config: Some(path.clone()),
+        aevo_instrument: Some("ETH-PERP".to_string()),
+        ..Cli::default()
+    })
+    .unwrap_err();
+    assert!(format!("{error:#}").contains("--aevo-instrument can not be combined with [[markets]]"));
+
+    //Other flags still apply to every pair
+    let config = Config::load(&Cli {
+        config: Some(path.clone()),
+        min_profit: Some(dec!(1)),
+        ..Cli::default()
+    })
+    .unwrap();
+    assert_eq!(config.min_profit, dec!(1));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn log_level_accepts_env_logger_directives() {
+    let mut config = Config::default();
+    for log_level in ["warn", "arbitrage_bot", "info,arbitrage_bot::feed=debug"] {
+        config.log_level = log_level.to_string();
+        config.validate().unwrap();
+    }
+
+    for log_level in ["arbitrage_bot=loud", "info,=debug", "arbitrage bot"] {
+        config.log_level = log_level.to_string();
+        assert!(config.validate().is_err(), "{log_level} was accepted");
+    }
+}
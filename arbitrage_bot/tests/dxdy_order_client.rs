@@ -0,0 +1,215 @@
+use arbitrage_bot::{
+    config::DXDYConfig,
+    dxdy::{
+        dxdy_order_client::DXDYOrderClient,
+        dxdy_order_structs::BroadcastRequestDXDY,
+        dxdy_proto::{
+            AuthInfo, GoodTil, MsgPlaceOrder, PubKey, SignDoc, TimeInForceProto, TxBody, TxRaw,
+            MSG_PLACE_ORDER_TYPE_URL,
+        },
+        dxdy_wallet::DXDYWallet,
+    },
+    order::{OrderClient, OrderRequest, OrderStatus},
+    venue::Side,
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use k256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use prost::Message;
+use rust_decimal_macros::dec;
+use serde_json::json;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const PRIVATE_KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+async fn mock_chain(wallet: &DXDYWallet, broadcast_code: u32) -> MockServer {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/v4/perpetualMarkets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "markets": {
+                "ETH-USD": {
+                    "clobPairId": "1",
+                    "atomicResolution": -9,
+                    "quantumConversionExponent": -9,
+                    "stepBaseQuantums": 1000000,
+                    "subticksPerTick": 100000
+                }
+            }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!(
+            "/cosmos/auth/v1beta1/accounts/{}",
+            wallet.address()
+        )))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "account": {
+                "@type": "/cosmos.auth.v1beta1.BaseAccount",
+                "address": wallet.address(),
+                "account_number": "7",
+                "sequence": "3"
+            }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v4/height"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "height": "100",
+            "time": "2023-06-30T00:00:00.000Z"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/cosmos/tx/v1beta1/txs"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tx_response": {
+                "code": broadcast_code,
+                "txhash": "5F4A",
+                "raw_log": if broadcast_code == 0 { "" } else { "insufficient collateral" }
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    server
+}
+
+fn config(server: &MockServer) -> DXDYConfig {
+    DXDYConfig {
+        market: "ETH-USD".to_string(),
+        node_url: server.uri(),
+        indexer_url: server.uri(),
+        ..DXDYConfig::default()
+    }
+}
+
+async fn broadcast_tx(server: &MockServer) -> TxRaw {
+    let request = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|request| request.url.path() == "/cosmos/tx/v1beta1/txs")
+        .unwrap();
+    let body: BroadcastRequestDXDY = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(body.mode, "BROADCAST_MODE_SYNC");
+
+    TxRaw::decode(BASE64.decode(body.tx_bytes).unwrap().as_slice()).unwrap()
+}
+
+#[test]
+fn wallet_derives_dydx_address() {
+    let wallet = DXDYWallet::from_hex(PRIVATE_KEY).unwrap();
+
+    assert!(wallet.address().starts_with("dydx1"));
+    assert_eq!(wallet.public_key().len(), 33);
+    assert!(!format!("{wallet:?}").contains(PRIVATE_KEY));
+}
+
+#[tokio::test]
+async fn places_signed_short_term_ioc_order() {
+    let wallet = DXDYWallet::from_hex(PRIVATE_KEY).unwrap();
+    let server = mock_chain(&wallet, 0).await;
+    let client = DXDYOrderClient::connect(&config(&server), wallet.clone())
+        .await
+        .unwrap();
+
+    let request = OrderRequest::ioc(42, Side::Buy, dec!(0.5), dec!(2000.05));
+    let report = client.place_order(&request).await.unwrap();
+    assert_eq!(report.order_id, "5F4A");
+    assert_eq!(report.status, OrderStatus::Pending);
+
+    let tx = broadcast_tx(&server).await;
+    let body = TxBody::decode(tx.body_bytes.as_slice()).unwrap();
+    assert_eq!(body.messages[0].type_url, MSG_PLACE_ORDER_TYPE_URL);
+    let order = MsgPlaceOrder::decode(body.messages[0].value.as_slice())
+        .unwrap()
+        .order
+        .unwrap();
+    let order_id = order.order_id.unwrap();
+    assert_eq!(order_id.client_id, 42);
+    assert_eq!(order_id.clob_pair_id, 1);
+    assert_eq!(order_id.subaccount_id.unwrap().owner, wallet.address());
+    assert_eq!(order.quantums, 500_000_000);
+    //Buy price is rounded up to 0.1 tick
+    assert_eq!(order.subticks, 2_000_100_000);
+    assert_eq!(order.good_til, Some(GoodTil::Block(120)));
+    assert_eq!(order.time_in_force, TimeInForceProto::Ioc as i32);
+
+    //Signature covers body and auth info for account 7 at sequence 3
+    let auth_info = AuthInfo::decode(tx.auth_info_bytes.as_slice()).unwrap();
+    let signer = &auth_info.signer_infos[0];
+    assert_eq!(signer.sequence, 3);
+    let public_key = PubKey::decode(signer.public_key.as_ref().unwrap().value.as_slice()).unwrap();
+    let sign_doc = SignDoc {
+        body_bytes: tx.body_bytes.clone(),
+        auth_info_bytes: tx.auth_info_bytes.clone(),
+        chain_id: "dydx-mainnet-1".to_string(),
+        account_number: 7,
+    };
+    let verifying_key = VerifyingKey::from_sec1_bytes(&public_key.key).unwrap();
+    let signature = Signature::from_slice(&tx.signatures[0]).unwrap();
+    verifying_key
+        .verify(&sign_doc.encode_to_vec(), &signature)
+        .unwrap();
+}
+
+#[tokio::test]
+async fn rejected_transaction_is_an_error() {
+    let wallet = DXDYWallet::from_hex(PRIVATE_KEY).unwrap();
+    let server = mock_chain(&wallet, 5).await;
+    let client = DXDYOrderClient::connect(&config(&server), wallet)
+        .await
+        .unwrap();
+
+    let request = OrderRequest::limit(43, Side::Sell, dec!(1), dec!(2100));
+    let error = client.place_order(&request).await.unwrap_err();
+
+    assert!(format!("{error:#}").contains("insufficient collateral"));
+}
+
+#[tokio::test]
+async fn query_reports_fills_from_indexer() {
+    let wallet = DXDYWallet::from_hex(PRIVATE_KEY).unwrap();
+    let server = mock_chain(&wallet, 0).await;
+    Mock::given(method("GET"))
+        .and(path("/v4/orders"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "id": "order-1",
+            "clientId": "44",
+            "size": "0.5",
+            "totalFilled": "0.5",
+            "status": "FILLED"
+        }])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v4/fills"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "fills": [
+                { "orderId": "order-1", "price": "2000", "size": "0.2" },
+                { "orderId": "order-1", "price": "2001", "size": "0.3" },
+                { "orderId": "order-2", "price": "1900", "size": "1" }
+            ]
+        })))
+        .mount(&server)
+        .await;
+    let client = DXDYOrderClient::connect(&config(&server), wallet)
+        .await
+        .unwrap();
+
+    let request = OrderRequest::ioc(44, Side::Buy, dec!(0.5), dec!(2001));
+    let placed = client.place_order(&request).await.unwrap();
+    let report = client.query_order(&placed).await.unwrap();
+
+    assert_eq!(report.order_id, "order-1");
+    assert_eq!(report.status, OrderStatus::Filled);
+    assert_eq!(report.filled_qty, dec!(0.5));
+    assert_eq!(report.avg_price, Some(dec!(2000.6)));
+}
@@ -0,0 +1,62 @@
+use arbitrage_bot::dxdy::dxdy_sequence::{DXDYSequenceTracker, SequenceCheck};
+
+#[test]
+fn consecutive_ids_are_in_order() {
+    let mut tracker = DXDYSequenceTracker::default();
+
+    //Connection may start at any id
+    for message_id in 7..10 {
+        assert_eq!(tracker.observe(message_id), SequenceCheck::InOrder);
+    }
+}
+
+#[test]
+fn skipped_id_is_a_gap() {
+    let mut tracker = DXDYSequenceTracker::default();
+    tracker.observe(1);
+
+    assert_eq!(
+        tracker.observe(4),
+        SequenceCheck::Gap {
+            expected: 2,
+            received: 4
+        }
+    );
+    //Sequence continues from the received id, so messages after resubscription are in order
+    assert_eq!(tracker.observe(5), SequenceCheck::InOrder);
+}
+
+#[test]
+fn duplicate_and_late_ids_are_out_of_order() {
+    let mut tracker = DXDYSequenceTracker::default();
+    tracker.observe(1);
+    tracker.observe(2);
+
+    assert_eq!(
+        tracker.observe(2),
+        SequenceCheck::OutOfOrder {
+            last: 2,
+            received: 2
+        }
+    );
+    assert_eq!(
+        tracker.observe(1),
+        SequenceCheck::OutOfOrder {
+            last: 2,
+            received: 1
+        }
+    );
+    //Late message does not move the sequence back
+    assert_eq!(tracker.observe(3), SequenceCheck::InOrder);
+}
+
+#[test]
+fn new_connection_starts_new_sequence() {
+    let mut tracker = DXDYSequenceTracker::default();
+    tracker.observe(40);
+    assert!(!tracker.observe(1).is_in_order());
+
+    //Indexer restarts ids on every connection, feed session starts a fresh tracker
+    let mut tracker = DXDYSequenceTracker::default();
+    assert_eq!(tracker.observe(1), SequenceCheck::InOrder);
+}
@@ -0,0 +1,335 @@
+mod common;
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex as StdMutex},
+};
+
+use anyhow::{bail, Result};
+use arbitrage_bot::{
+    aevo::aevo_structs::OrderbookAEVO,
+    calculations::{evaluate_direction, ArbitrageOpportunity},
+    dxdy::dxdy_structs::OrderbookDXDY,
+    execution::{
+        AuditEvent, Correction, ExecutionConfig, ExecutionCoordinator, ExecutionOutcome,
+        LegRiskPolicy,
+    },
+    fees::FeeSchedule,
+    instrument::Size,
+    order::{OrderClient, OrderReport, OrderRequest, OrderStatus},
+    portfolio::{Fill, Portfolio, VenueBalance},
+    sizing::VenueTerms,
+    venue::{Side, Venue},
+};
+use async_trait::async_trait;
+use common::books::{aevo_book, dxdy_book, shared};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use tokio::sync::Mutex;
+
+///Fills orders with scripted quantities, `None` rejects the order
+struct ScriptedClient {
+    venue: Venue,
+    fills: StdMutex<VecDeque<Option<Size>>>,
+    placed: Arc<StdMutex<Vec<OrderRequest>>>,
+}
+
+impl ScriptedClient {
+    ///Boxed client and handle to the orders it receives
+    fn boxed(
+        venue: Venue,
+        fills: &[Option<Size>],
+    ) -> (Box<dyn OrderClient>, Arc<StdMutex<Vec<OrderRequest>>>) {
+        let placed = Arc::new(StdMutex::new(Vec::new()));
+        let client = Self {
+            venue,
+            fills: StdMutex::new(fills.iter().copied().collect()),
+            placed: placed.clone(),
+        };
+        (Box::new(client), placed)
+    }
+}
+
+#[async_trait]
+impl OrderClient for ScriptedClient {
+    fn venue(&self) -> Venue {
+        self.venue
+    }
+
+    async fn place_order(&self, request: &OrderRequest) -> Result<OrderReport> {
+        self.placed.lock().unwrap().push(*request);
+        let Some(fill) = self
+            .fills
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or(Some(Decimal::ZERO))
+        else {
+            bail!("rejected");
+        };
+
+        let filled_qty = fill.min(request.quantity);
+        Ok(OrderReport {
+            status: if filled_qty == request.quantity {
+                OrderStatus::Filled
+            } else {
+                OrderStatus::Canceled
+            },
+            filled_qty,
+            avg_price: (filled_qty > Decimal::ZERO).then_some(request.price),
+            ..OrderReport::pending(self.venue, format!("{}", request.client_id), request)
+        })
+    }
+
+    async fn cancel_order(&self, _order: &OrderReport) -> Result<()> {
+        Ok(())
+    }
+
+    async fn query_order(&self, order: &OrderReport) -> Result<OrderReport> {
+        Ok(order.clone())
+    }
+}
+
+type Books = (Arc<Mutex<OrderbookAEVO>>, Arc<Mutex<OrderbookDXDY>>);
+
+///AEVO asks at 2000, dXdY bids at 2010, so we buy on AEVO and sell on dXdY
+fn books() -> Books {
+    (
+        shared(aevo_book(
+            &[(dec!(1995), dec!(1))],
+            &[(dec!(2000), dec!(1))],
+        )),
+        shared(dxdy_book(
+            &[(dec!(2010), dec!(1))],
+            &[(dec!(2015), dec!(1))],
+        )),
+    )
+}
+
+async fn opportunity(books: &Books) -> ArbitrageOpportunity {
+    let terms = VenueTerms::new(FeeSchedule::zero(), None);
+    let aevo = books.0.lock().await;
+    let dxdy = books.1.lock().await;
+    evaluate_direction(&*aevo, &terms, &*dxdy, &terms, dec!(100000)).unwrap()
+}
+
+fn config(policy: LegRiskPolicy) -> ExecutionConfig {
+    ExecutionConfig {
+        policy,
+        poll_interval_ms: 1,
+        ..ExecutionConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn matching_fills_complete_without_corrections() {
+    let books = books();
+    let (aevo, _) = ScriptedClient::boxed(Venue::Aevo, &[Some(dec!(1))]);
+    let (dxdy, _) = ScriptedClient::boxed(Venue::Dxdy, &[Some(dec!(1))]);
+    let mut coordinator =
+        ExecutionCoordinator::new(vec![aevo, dxdy], config(LegRiskPolicy::Retry)).unwrap();
+
+    let report = coordinator
+        .execute(&opportunity(&books).await, books.0.clone(), books.1.clone())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(report.outcome, ExecutionOutcome::Complete);
+    assert_eq!(report.residual, Decimal::ZERO);
+    assert!(report.corrections.is_empty());
+    assert_eq!(report.buy.unwrap().venue, Venue::Aevo);
+    assert_eq!(report.sell.unwrap().venue, Venue::Dxdy);
+}
+
+#[tokio::test]
+async fn partial_leg_is_retried_on_its_venue() {
+    let books = books();
+    let (aevo, _) = ScriptedClient::boxed(Venue::Aevo, &[Some(dec!(1))]);
+    let (dxdy, dxdy_orders) =
+        ScriptedClient::boxed(Venue::Dxdy, &[Some(dec!(0.6)), Some(dec!(0.4))]);
+    let mut coordinator =
+        ExecutionCoordinator::new(vec![aevo, dxdy], config(LegRiskPolicy::Retry)).unwrap();
+
+    let report = coordinator
+        .execute(&opportunity(&books).await, books.0.clone(), books.1.clone())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(report.outcome, ExecutionOutcome::Corrected);
+    assert_eq!(report.residual, Decimal::ZERO);
+    let retry = dxdy_orders.lock().unwrap()[1];
+    assert_eq!(retry.side, Side::Sell);
+    assert_eq!(retry.quantity, dec!(0.4));
+    //Original limit of 2010 lowered by 10 bps
+    //2010 less 10 bps is rounded down to dXdY tick
+    assert_eq!(retry.price, dec!(2007.9));
+    assert!(report.events.iter().any(|event| matches!(
+        event,
+        AuditEvent::Submitted {
+            correction: Some(Correction::Retry),
+            ..
+        }
+    )));
+}
+
+#[tokio::test]
+async fn leg_without_price_is_not_sent() {
+    let books = books();
+    let (aevo, aevo_orders) = ScriptedClient::boxed(Venue::Aevo, &[Some(dec!(1))]);
+    let (dxdy, dxdy_orders) = ScriptedClient::boxed(Venue::Dxdy, &[Some(dec!(1))]);
+    let mut coordinator =
+        ExecutionCoordinator::new(vec![aevo, dxdy], config(LegRiskPolicy::Unwind)).unwrap();
+
+    let mut opportunity = opportunity(&books).await;
+    opportunity.sell.avg_price = None;
+    opportunity.sell.worst_price = None;
+    let report = coordinator
+        .execute(&opportunity, books.0.clone(), books.1.clone())
+        .await
+        .unwrap();
+
+    assert!(report.is_none());
+    assert!(aevo_orders.lock().unwrap().is_empty());
+    assert!(dxdy_orders.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn failed_leg_is_unwound_on_filled_venue() {
+    let books = books();
+    let (aevo, aevo_orders) = ScriptedClient::boxed(Venue::Aevo, &[None]);
+    let (dxdy, dxdy_orders) = ScriptedClient::boxed(Venue::Dxdy, &[Some(dec!(1)), Some(dec!(1))]);
+    let mut coordinator =
+        ExecutionCoordinator::new(vec![aevo, dxdy], config(LegRiskPolicy::Unwind)).unwrap();
+
+    let report = coordinator
+        .execute(&opportunity(&books).await, books.0.clone(), books.1.clone())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(report.outcome, ExecutionOutcome::Corrected);
+    assert!(report.buy.is_none());
+    assert_eq!(aevo_orders.lock().unwrap().len(), 1);
+    //Sold on dXdY, so it is bought back there at best ask raised by 10 bps, rounded up to a tick
+    let unwind = dxdy_orders.lock().unwrap()[1];
+    assert_eq!(unwind.side, Side::Buy);
+    assert_eq!(unwind.quantity, dec!(1));
+    assert_eq!(unwind.price, dec!(2017.1));
+    assert!(report.events.iter().any(|event| matches!(
+        event,
+        AuditEvent::Rejected {
+            venue: Venue::Aevo,
+            ..
+        }
+    )));
+}
+
+#[tokio::test]
+async fn residual_is_hedged_on_best_venue() {
+    let books = books();
+    let (aevo, _) = ScriptedClient::boxed(Venue::Aevo, &[Some(dec!(1))]);
+    let (dxdy, dxdy_orders) =
+        ScriptedClient::boxed(Venue::Dxdy, &[Some(dec!(0.25)), Some(dec!(0.75))]);
+    let mut coordinator =
+        ExecutionCoordinator::new(vec![aevo, dxdy], config(LegRiskPolicy::Hedge)).unwrap();
+
+    let report = coordinator
+        .execute(&opportunity(&books).await, books.0.clone(), books.1.clone())
+        .await
+        .unwrap()
+        .unwrap();
+
+    //dXdY bid of 2010 beats AEVO bid of 1995
+    assert_eq!(report.outcome, ExecutionOutcome::Corrected);
+    let hedge = dxdy_orders.lock().unwrap()[1];
+    assert_eq!(hedge.side, Side::Sell);
+    assert_eq!(hedge.quantity, dec!(0.75));
+}
+
+#[tokio::test]
+async fn unfilled_corrections_leave_residual_and_audit_log() {
+    let books = books();
+    let audit_log =
+        std::env::temp_dir().join(format!("execution_audit_{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&audit_log);
+    let (aevo, aevo_orders) = ScriptedClient::boxed(Venue::Aevo, &[Some(dec!(1))]);
+    let (dxdy, _) = ScriptedClient::boxed(Venue::Dxdy, &[Some(Decimal::ZERO)]);
+    let mut coordinator = ExecutionCoordinator::new(
+        vec![aevo, dxdy],
+        ExecutionConfig {
+            audit_log: Some(audit_log.clone()),
+            ..config(LegRiskPolicy::Retry)
+        },
+    )
+    .unwrap();
+
+    let report = coordinator
+        .execute(&opportunity(&books).await, books.0.clone(), books.1.clone())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(report.outcome, ExecutionOutcome::Unresolved);
+    assert_eq!(report.residual, dec!(1));
+    //Two retries on dXdY, then unwind sells on AEVO
+    assert_eq!(report.corrections.len(), 3);
+    assert_eq!(aevo_orders.lock().unwrap()[1].side, Side::Sell);
+
+    let lines = std::fs::read_to_string(&audit_log).unwrap();
+    assert_eq!(lines.lines().count(), report.events.len());
+    assert!(lines
+        .lines()
+        .all(|line| line.contains("\"execution_id\":1")));
+    std::fs::remove_file(&audit_log).unwrap();
+}
+
+#[tokio::test]
+async fn flatten_closes_exposure_and_keeps_deposited_inventory() {
+    let books = books();
+    let (aevo, aevo_orders) = ScriptedClient::boxed(Venue::Aevo, &[Some(dec!(0.5))]);
+    let (dxdy, dxdy_orders) = ScriptedClient::boxed(Venue::Dxdy, &[Some(dec!(0.3))]);
+    let mut coordinator =
+        ExecutionCoordinator::new(vec![aevo, dxdy], config(LegRiskPolicy::Retry)).unwrap();
+    //Bot bought 0.5 on flat AEVO and sold 0.3 of 1 deposited on dXdY
+    let mut portfolio = Portfolio::new([
+        (Venue::Aevo, VenueBalance::new(dec!(10000), Decimal::ZERO)),
+        (Venue::Dxdy, VenueBalance::new(Decimal::ZERO, dec!(1))),
+    ]);
+    portfolio.apply(&Fill {
+        venue: Venue::Aevo,
+        side: Side::Buy,
+        quantity: dec!(0.5),
+        price: dec!(2000),
+        fee: Decimal::ZERO,
+    });
+    portfolio.apply(&Fill {
+        venue: Venue::Dxdy,
+        side: Side::Sell,
+        quantity: dec!(0.3),
+        price: dec!(2010),
+        fee: Decimal::ZERO,
+    });
+
+    let reports = coordinator
+        .flatten(&portfolio, books.0.clone(), books.1.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(reports.len(), 2);
+    //Long AEVO position is sold below best bid of 1995
+    let order = aevo_orders.lock().unwrap()[0];
+    assert_eq!(order.side, Side::Sell);
+    assert_eq!(order.quantity, dec!(0.5));
+    assert!(order.reduce_only);
+    //1995 less 10 bps is rounded down to AEVO tick
+    assert_eq!(order.price, dec!(1993));
+    //Sold inventory is bought back, that grows the held dXdY position
+    let order = dxdy_orders.lock().unwrap()[0];
+    assert_eq!(order.side, Side::Buy);
+    assert_eq!(order.quantity, dec!(0.3));
+    assert!(!order.reduce_only);
+    //2015 plus 10 bps is rounded up to dXdY tick
+    assert_eq!(order.price, dec!(2017.1));
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use std::sync::Arc;
+
+use arbitrage_bot::{
+    aevo::{aevo_orderbook_feed::AEVOWSOrderbookFeed, aevo_structs::OrderbookAEVO},
+    dxdy::{
+        dxdy_orderbook_feed::DXDYWSOrderbookFeed, dxdy_sequence::DXDYFeedStats,
+        dxdy_structs::OrderbookDXDY,
+    },
+    feed::{BookUpdateNotifier, VenueBooks},
+    feed_source::FeedSource,
+    orderbook::Orderbook,
+    recording::FeedRecorder,
+    venue::Venue,
+};
+use chrono::Utc;
+use common::books::{aevo_spec, dxdy_spec, shared};
+use rust_decimal_macros::dec;
+use tokio_tungstenite::tungstenite::Message;
+
+const AEVO_CHANNELS: &str =
+    r#"{"data":["ticker:ETH:PERPETUAL","orderbook:ETH-PERP","orderbook:BTC-PERP"]}"#;
+const AEVO_SNAPSHOT: &str = r#"{"channel":"orderbook:ETH-PERP","data":{"type":"snapshot","instrument_type":"PERPETUAL","bids":[["1999","1","0"]],"asks":[["2000","2","0"]],"last_updated":"1700000000000000000"}}"#;
+const DXDY_CONNECTED: &str = r#"{"type":"connected","connection_id":"c","message_id":0}"#;
+const DXDY_SUBSCRIBED: &str = r#"{"type":"subscribed","connection_id":"c","message_id":1,"id":"ETH-USD","contents":{"bids":[{"price":"2010","size":"1"}],"asks":[{"price":"2011","size":"3"}]}}"#;
+const DXDY_UPDATE: &str = r#"{"type":"channel_data","connection_id":"c","message_id":2,"id":"ETH-USD","version":"1","contents":{"asks":[{"price":"2011","size":"0"},{"price":"2012","size":"1"}]}}"#;
+const DXDY_GAP: &str = r#"{"type":"channel_data","connection_id":"c","message_id":5,"id":"ETH-USD","version":"1","contents":{"bids":[{"price":"2009","size":"1"}]}}"#;
+
+fn sent_text(message: Option<Message>) -> serde_json::Value {
+    match message {
+        Some(Message::Text(text)) => serde_json::from_str(&text).unwrap(),
+        other => panic!("Expected text request, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn aevo_feed_subscribes_over_channel() {
+    let (source, mut peer) = FeedSource::channel();
+    let book = shared(OrderbookAEVO::new(aevo_spec()));
+
+    let mut feed = AEVOWSOrderbookFeed::new(source, &["ETH-PERP"]);
+    peer.send_text(AEVO_CHANNELS).unwrap();
+    feed.subscribe_for_feed().await.unwrap();
+
+    assert_eq!(sent_text(peer.next_sent().await)["op"], "channels");
+    let subscribe = sent_text(peer.next_sent().await);
+    assert_eq!(subscribe["op"], "orderbook");
+    assert_eq!(subscribe["data"][0], "orderbook:ETH-PERP");
+
+    peer.send_text(AEVO_SNAPSHOT).unwrap();
+    //Feed ends cleanly once the peer is gone
+    peer.close();
+    feed.run(VenueBooks::single("ETH-PERP", book.clone()))
+        .await
+        .unwrap();
+
+    let book = book.lock().await;
+    assert!(!book.is_stale());
+    assert_eq!(book.best_bid(), Some((dec!(1999), dec!(1))));
+    assert_eq!(book.best_ask(), Some((dec!(2000), dec!(2))));
+}
+
+#[tokio::test]
+async fn dxdy_feed_notifies_only_applied_updates() {
+    let (source, peer) = FeedSource::channel();
+    let book = shared(OrderbookDXDY::new(dxdy_spec()));
+    let (notifier, updates) = BookUpdateNotifier::channel();
+
+    for frame in [DXDY_CONNECTED, DXDY_SUBSCRIBED] {
+        peer.send_text(frame).unwrap();
+    }
+    peer.close();
+    DXDYWSOrderbookFeed::new(source, &["ETH-USD"])
+        .with_notifier(notifier)
+        .run(VenueBooks::single("ETH-USD", book.clone()))
+        .await
+        .unwrap();
+
+    //Connection acknowledgement updates no book
+    assert_eq!(updates.borrow().unwrap().sequence, 1);
+}
+
+#[tokio::test]
+async fn dxdy_feed_resubscribes_on_sequence_gap() {
+    let (source, mut peer) = FeedSource::channel();
+    let book = shared(OrderbookDXDY::new(dxdy_spec()));
+    let stats = Arc::new(DXDYFeedStats::default());
+
+    for frame in [DXDY_SUBSCRIBED, DXDY_UPDATE, DXDY_GAP] {
+        peer.send_text(frame).unwrap();
+    }
+    peer.close();
+    DXDYWSOrderbookFeed::new(source, &["ETH-USD"])
+        .with_stats(stats.clone())
+        .run(VenueBooks::single("ETH-USD", book.clone()))
+        .await
+        .unwrap();
+
+    assert_eq!(stats.sequence_gaps(), 1);
+    assert_eq!(sent_text(peer.next_sent().await)["type"], "unsubscribe");
+    assert_eq!(sent_text(peer.next_sent().await)["type"], "subscribe");
+    //Book waits for the snapshot of the new subscription
+    assert!(book.lock().await.is_stale());
+}
+
+#[tokio::test]
+async fn dxdy_feed_runs_over_recording() {
+    let path = std::env::temp_dir().join(format!(
+        "feed_source_recording_{}.jsonl.gz",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let recorder = FeedRecorder::create(&path).unwrap();
+    recorder
+        .record(Venue::Aevo, Utc::now(), AEVO_SNAPSHOT)
+        .unwrap();
+    for frame in [DXDY_SUBSCRIBED, DXDY_UPDATE] {
+        recorder.record(Venue::Dxdy, Utc::now(), frame).unwrap();
+    }
+    drop(recorder);
+
+    let book = shared(OrderbookDXDY::new(dxdy_spec()));
+    //Frames of other venues are skipped
+    let source = FeedSource::recording(&path, Venue::Dxdy).unwrap();
+    DXDYWSOrderbookFeed::new(source, &["ETH-USD"])
+        .run(VenueBooks::single("ETH-USD", book.clone()))
+        .await
+        .unwrap();
+
+    let book = book.lock().await;
+    assert!(!book.is_stale());
+    assert_eq!(book.best_bid(), Some((dec!(2010), dec!(1))));
+    assert_eq!(book.best_ask(), Some((dec!(2012), dec!(1))));
+    std::fs::remove_file(&path).unwrap();
+}
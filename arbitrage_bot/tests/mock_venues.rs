@@ -0,0 +1,454 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use arbitrage_bot::{
+    aevo::{
+        aevo_auth::AEVOCredentials,
+        aevo_orderbook_feed::{AEVOWSAuthenticator, AEVOWSOrderbookFeed},
+        aevo_structs::OrderbookAEVO,
+    },
+    config::Config,
+    dxdy::{
+        dxdy_orderbook_feed::{DXDYWSAuthenticator, DXDYWSOrderbookFeed},
+        dxdy_sequence::DXDYFeedStats,
+        dxdy_structs::OrderbookDXDY,
+    },
+    feed::{FeedHandle, VenueBooks},
+    main_loop,
+    orderbook::Orderbook,
+};
+use common::books::{aevo_spec, dxdy_spec, shared};
+use common::{wait_for, MockVenue, Script};
+use rust_decimal_macros::dec;
+use tokio::sync::Mutex;
+
+fn credentials() -> AEVOCredentials {
+    AEVOCredentials::new("key".to_string(), "secret".to_string())
+}
+
+fn aevo_feed(venue: &MockVenue) -> (Arc<Mutex<OrderbookAEVO>>, FeedHandle) {
+    let book = shared(OrderbookAEVO::new(aevo_spec()));
+    let feed = AEVOWSOrderbookFeed::spawn_supervised(
+        AEVOWSAuthenticator::new(&venue.url, credentials()),
+        VenueBooks::single("ETH-PERP", book.clone()),
+        None,
+    );
+    (book, feed)
+}
+
+fn dxdy_feed(
+    venue: &MockVenue,
+    stats: Arc<DXDYFeedStats>,
+) -> (Arc<Mutex<OrderbookDXDY>>, FeedHandle) {
+    let book = shared(OrderbookDXDY::new(dxdy_spec()));
+    let feed = DXDYWSOrderbookFeed::spawn_supervised(
+        DXDYWSAuthenticator::new(&venue.url),
+        VenueBooks::single("ETH-USD", book.clone()),
+        stats,
+        None,
+    );
+    (book, feed)
+}
+
+#[tokio::test]
+async fn feeds_build_books_from_mock_venues() {
+    let aevo = MockVenue::aevo(vec![vec![
+        Script::Snapshot {
+            bids: &[("1999", "1"), ("1998", "2")],
+            asks: &[("2000", "2")],
+        },
+        Script::Delta {
+            bids: &[("1999", "0")],
+            asks: &[("2001", "1")],
+        },
+    ]])
+    .await;
+    let dxdy = MockVenue::dxdy(vec![vec![
+        Script::Snapshot {
+            bids: &[("2010", "1")],
+            asks: &[("2011", "3")],
+        },
+        Script::Delta {
+            bids: &[],
+            asks: &[("2011", "0"), ("2012", "1")],
+        },
+    ]])
+    .await;
+
+    let (aevo_book, aevo_feed) = aevo_feed(&aevo);
+    let (dxdy_book, dxdy_feed) = dxdy_feed(&dxdy, Arc::new(DXDYFeedStats::default()));
+
+    wait_for("AEVO delta", || {
+        let book = aevo_book.clone();
+        async move { book.lock().await.best_bid() == Some((dec!(1998), dec!(2))) }
+    })
+    .await;
+    wait_for("dXdY delta", || {
+        let book = dxdy_book.clone();
+        async move { book.lock().await.best_ask() == Some((dec!(2012), dec!(1))) }
+    })
+    .await;
+
+    assert!(aevo_feed.health.borrow().is_live());
+    assert!(dxdy_feed.health.borrow().is_live());
+    assert_eq!(
+        aevo_book.lock().await.best_ask(),
+        Some((dec!(2000), dec!(2)))
+    );
+    assert_eq!(
+        dxdy_book.lock().await.best_bid(),
+        Some((dec!(2010), dec!(1)))
+    );
+
+    //Feed authenticates, finds the channel and subscribes to it
+    let ops: Vec<_> = aevo
+        .requests()
+        .iter()
+        .map(|request| request["op"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(ops, ["auth", "channels", "orderbook"]);
+    assert_eq!(aevo.requests()[2]["data"][0], "orderbook:ETH-PERP");
+    assert_eq!(dxdy.count_requests("type", "subscribe"), 1);
+    assert_eq!(dxdy.requests()[0]["id"], "ETH-USD");
+    aevo_feed.handle.abort();
+    dxdy_feed.handle.abort();
+}
+
+#[tokio::test]
+async fn feed_reconnects_after_disconnect() {
+    let aevo = MockVenue::aevo(vec![
+        vec![
+            Script::Snapshot {
+                bids: &[("1999", "1")],
+                asks: &[("2000", "1")],
+            },
+            Script::Pause(Duration::from_millis(50)),
+            Script::Disconnect,
+        ],
+        vec![Script::Snapshot {
+            bids: &[("1990", "1")],
+            asks: &[("1995", "1")],
+        }],
+    ])
+    .await;
+
+    let (book, feed) = aevo_feed(&aevo);
+
+    wait_for("book of the second session", || {
+        let book = book.clone();
+        async move { book.lock().await.best_ask() == Some((dec!(1995), dec!(1))) }
+    })
+    .await;
+
+    assert_eq!(aevo.connections(), 2);
+    //Every session authenticates again
+    assert_eq!(aevo.count_requests("op", "auth"), 2);
+    assert!(feed.health.borrow().is_live());
+    feed.handle.abort();
+}
+
+#[tokio::test]
+async fn malformed_message_restarts_session() {
+    let dxdy = MockVenue::dxdy(vec![
+        vec![
+            Script::Snapshot {
+                bids: &[("2010", "1")],
+                asks: &[("2011", "1")],
+            },
+            Script::Raw("{not json"),
+        ],
+        vec![Script::Snapshot {
+            bids: &[("2020", "1")],
+            asks: &[("2021", "1")],
+        }],
+    ])
+    .await;
+
+    let (book, feed) = dxdy_feed(&dxdy, Arc::new(DXDYFeedStats::default()));
+
+    wait_for("book of the second session", || {
+        let book = book.clone();
+        async move { book.lock().await.best_bid() == Some((dec!(2020), dec!(1))) }
+    })
+    .await;
+
+    assert_eq!(dxdy.connections(), 2);
+    assert!(!book.lock().await.is_stale());
+    feed.handle.abort();
+}
+
+#[tokio::test]
+async fn sequence_gap_resubscribes_for_snapshot() {
+    let dxdy = MockVenue::dxdy(vec![vec![
+        Script::Snapshot {
+            bids: &[("2010", "1")],
+            asks: &[("2011", "1")],
+        },
+        Script::SkipSequence,
+        Script::Delta {
+            bids: &[("2009", "5")],
+            asks: &[],
+        },
+    ]])
+    .await;
+    let stats = Arc::new(DXDYFeedStats::default());
+
+    let (book, feed) = dxdy_feed(&dxdy, stats.clone());
+
+    wait_for("second subscription", || {
+        let requests = dxdy.count_requests("type", "subscribe");
+        async move { requests == 2 }
+    })
+    .await;
+    //Snapshot sent for the new subscription replaces the gapped delta
+    wait_for("fresh snapshot", || {
+        let book = book.clone();
+        async move {
+            let book = book.lock().await;
+            !book.is_stale() && book.bids.len() == 1
+        }
+    })
+    .await;
+
+    assert_eq!(stats.sequence_gaps(), 1);
+    assert_eq!(stats.resubscriptions(), 1);
+    assert_eq!(dxdy.count_requests("type", "unsubscribe"), 1);
+    //Resubscription happens on the same connection
+    assert_eq!(dxdy.connections(), 1);
+    feed.handle.abort();
+}
+
+#[tokio::test]
+async fn main_loop_paper_trades_against_mock_venues() {
+    //dXdY bid is 50 bps above AEVO ask, enough to pay taker fees on both venues
+    let aevo = MockVenue::aevo(vec![vec![Script::Snapshot {
+        bids: &[("1999", "1")],
+        asks: &[("2000", "2")],
+    }]])
+    .await;
+    let dxdy = MockVenue::dxdy(vec![vec![Script::Snapshot {
+        bids: &[("2010", "1")],
+        asks: &[("2011", "3")],
+    }]])
+    .await;
+
+    let dir = std::env::temp_dir();
+    let trade_log = dir.join(format!("mock_venues_trades_{}.jsonl", std::process::id()));
+    let kill_switch = dir.join(format!("mock_venues_stop_{}", std::process::id()));
+    let _ = std::fs::remove_file(&trade_log);
+    let _ = std::fs::remove_file(&kill_switch);
+
+    let mut config = Config::default();
+    config.aevo.ws_url = aevo.url.clone();
+    config.aevo.credentials = Some(credentials());
+    config.dxdy.ws_url = dxdy.url.clone();
+    config.dxdy.market = "ETH-USD".to_string();
+    config.check_interval_ms = 50;
+    config.log_level = "warn".to_string();
+    config.paper.latency_ms = 0;
+    config.paper.trade_log = Some(trade_log.clone());
+    config.risk.kill_switch_file = Some(kill_switch.clone());
+    config.validate().unwrap();
+
+    let bot = tokio::spawn(main_loop(config));
+
+    wait_for("paper trade", || {
+        let trades = std::fs::read_to_string(&trade_log).unwrap_or_default();
+        async move { !trades.is_empty() }
+    })
+    .await;
+    std::fs::write(&kill_switch, "").unwrap();
+    tokio::time::timeout(Duration::from_secs(5), bot)
+        .await
+        .expect("Kill switch did not stop main loop")
+        .unwrap()
+        .unwrap();
+
+    let trades = std::fs::read_to_string(&trade_log).unwrap();
+    let trade: serde_json::Value = serde_json::from_str(trades.lines().next().unwrap()).unwrap();
+    assert_eq!(trade["direction"], "BuyAevoSellDxdy");
+    assert_eq!(trade["buy"]["venue"], "Aevo");
+    assert_eq!(trade["sell"]["venue"], "Dxdy");
+    std::fs::remove_file(&trade_log).unwrap();
+    std::fs::remove_file(&kill_switch).unwrap();
+}
+
+///CPU time of the test thread in clock ticks, current thread runtime runs all tasks on it
+#[cfg(target_os = "linux")]
+fn thread_cpu_ticks() -> u64 {
+    let stat = std::fs::read_to_string("/proc/thread-self/stat").unwrap();
+    //Fields after the command name start with state, user and system times are 14th and 15th
+    let fields: Vec<&str> = stat
+        .rsplit_once(')')
+        .unwrap()
+        .1
+        .split_whitespace()
+        .collect();
+    fields[11].parse::<u64>().unwrap() + fields[12].parse::<u64>().unwrap()
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn main_loop_idles_while_paper_trade_is_due_during_outage() {
+    //AEVO drops before the paper trade is due and comes back after it, without arbitrage
+    let aevo = MockVenue::aevo(vec![
+        vec![
+            Script::Snapshot {
+                bids: &[("1999", "1")],
+                asks: &[("2000", "2")],
+            },
+            Script::Pause(Duration::from_millis(100)),
+            Script::Disconnect,
+        ],
+        vec![Script::Snapshot {
+            bids: &[("2009", "1")],
+            asks: &[("2012", "2")],
+        }],
+    ])
+    .await;
+    let dxdy = MockVenue::dxdy(vec![vec![Script::Snapshot {
+        bids: &[("2010", "1")],
+        asks: &[("2011", "3")],
+    }]])
+    .await;
+
+    let dir = std::env::temp_dir();
+    let trade_log = dir.join(format!("mock_venues_outage_{}.jsonl", std::process::id()));
+    let kill_switch = dir.join(format!("mock_venues_outage_stop_{}", std::process::id()));
+    let _ = std::fs::remove_file(&trade_log);
+    let _ = std::fs::remove_file(&kill_switch);
+
+    let mut config = Config::default();
+    config.aevo.ws_url = aevo.url.clone();
+    config.aevo.credentials = Some(credentials());
+    config.dxdy.ws_url = dxdy.url.clone();
+    config.dxdy.market = "ETH-USD".to_string();
+    config.check_interval_ms = 50;
+    config.log_level = "warn".to_string();
+    config.paper.latency_ms = 200;
+    config.paper.trade_log = Some(trade_log.clone());
+    config.risk.kill_switch_file = Some(kill_switch.clone());
+    config.validate().unwrap();
+
+    let cpu_before = thread_cpu_ticks();
+    let bot = tokio::spawn(main_loop(config));
+
+    wait_for("second AEVO session", || {
+        let connections = aevo.connections();
+        async move { connections == 2 }
+    })
+    .await;
+    let busy = thread_cpu_ticks() - cpu_before;
+    std::fs::write(&kill_switch, "").unwrap();
+    tokio::time::timeout(Duration::from_secs(5), bot)
+        .await
+        .expect("Kill switch did not stop main loop")
+        .unwrap()
+        .unwrap();
+
+    //Reconnect backoff lasts 500ms, loop must not spin on the due trade through it
+    assert!(busy < 15, "main loop was busy for {busy} ticks");
+    //Trade was due against a reset AEVO book, it is missed rather than filled after reconnect
+    let trades = std::fs::read_to_string(&trade_log).unwrap_or_default();
+    assert!(trades.is_empty(), "unexpected paper trades : {trades}");
+    let _ = std::fs::remove_file(&trade_log);
+    std::fs::remove_file(&kill_switch).unwrap();
+}
+
+#[tokio::test]
+async fn main_loop_trades_every_mapped_pair_over_one_connection() {
+    //Only BTC prices are apart, ETH books are the same on both venues
+    let aevo = MockVenue::aevo(vec![vec![
+        Script::Market("ETH-PERP"),
+        Script::Snapshot {
+            bids: &[("1999", "1")],
+            asks: &[("2000", "1")],
+        },
+        Script::Market("BTC-PERP"),
+        Script::Snapshot {
+            bids: &[("59990", "1")],
+            asks: &[("60000", "1")],
+        },
+    ]])
+    .await;
+    let dxdy = MockVenue::dxdy(vec![vec![
+        Script::Market("ETH-USD"),
+        Script::Snapshot {
+            bids: &[("1999.5", "1")],
+            asks: &[("2000.5", "1")],
+        },
+        Script::Market("BTC-USD"),
+        Script::Snapshot {
+            bids: &[("60400", "1")],
+            asks: &[("60401", "1")],
+        },
+    ]])
+    .await;
+
+    let dir = std::env::temp_dir();
+    let trade_log = dir.join(format!("mock_venues_markets_{}.jsonl", std::process::id()));
+    let kill_switch = dir.join(format!("mock_venues_markets_stop_{}", std::process::id()));
+    let _ = std::fs::remove_file(&trade_log);
+    let _ = std::fs::remove_file(&kill_switch);
+
+    let mut config = Config::from_toml(
+        r#"
+        [[markets]]
+        aevo = { symbol = "ETH-PERP", tick_size = "0.01", step_size = "0.01" }
+        dxdy = { symbol = "ETH-USD", tick_size = "0.1", step_size = "0.001" }
+        capital = 1000
+        paper = { aevo = { usdc = 1000, base = "0.5" }, dxdy = { usdc = 1000, base = "0.5" } }
+
+        [[markets]]
+        aevo = { symbol = "BTC-PERP", tick_size = "0.5", step_size = "0.001" }
+        dxdy = { symbol = "BTC-USD", tick_size = "1", step_size = "0.0001" }
+        capital = 6000
+        paper = { aevo = { usdc = 10000, base = "0.1" }, dxdy = { usdc = 10000, base = "0.1" } }
+        "#,
+    )
+    .unwrap();
+    config.aevo.ws_url = aevo.url.clone();
+    config.aevo.credentials = Some(credentials());
+    config.dxdy.ws_url = dxdy.url.clone();
+    config.check_interval_ms = 50;
+    config.log_level = "warn".to_string();
+    config.paper.latency_ms = 0;
+    config.paper.trade_log = Some(trade_log.clone());
+    config.risk.kill_switch_file = Some(kill_switch.clone());
+    config.validate().unwrap();
+
+    let bot = tokio::spawn(main_loop(config));
+
+    wait_for("paper trade", || {
+        let trades = std::fs::read_to_string(&trade_log).unwrap_or_default();
+        async move { !trades.is_empty() }
+    })
+    .await;
+    std::fs::write(&kill_switch, "").unwrap();
+    tokio::time::timeout(Duration::from_secs(5), bot)
+        .await
+        .expect("Kill switch did not stop main loop")
+        .unwrap()
+        .unwrap();
+
+    let trades = std::fs::read_to_string(&trade_log).unwrap();
+    for line in trades.lines() {
+        let trade: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(trade["market"], "BTC-PERP/BTC-USD");
+        assert_eq!(trade["direction"], "BuyAevoSellDxdy");
+    }
+    //Both instruments share one AEVO subscription, dXdY subscribes per market
+    assert_eq!(aevo.connections(), 1);
+    assert_eq!(aevo.count_requests("op", "orderbook"), 1);
+    let orderbook = aevo
+        .requests()
+        .into_iter()
+        .find(|request| request["op"] == "orderbook")
+        .unwrap();
+    assert_eq!(orderbook["data"].as_array().unwrap().len(), 2);
+    assert_eq!(dxdy.connections(), 1);
+    assert_eq!(dxdy.count_requests("type", "subscribe"), 2);
+    std::fs::remove_file(&trade_log).unwrap();
+    std::fs::remove_file(&kill_switch).unwrap();
+}
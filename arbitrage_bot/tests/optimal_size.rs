@@ -0,0 +1,99 @@
+mod common;
+
+use arbitrage_bot::{
+    calculations::evaluate_direction,
+    fees::FeeSchedule,
+    portfolio::VenueBalance,
+    sizing::{optimal_size, VenueTerms},
+};
+use common::books::{aevo_book, dxdy_book};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn no_fees() -> VenueTerms {
+    VenueTerms::new(FeeSchedule::zero(), None)
+}
+
+#[test]
+fn stops_where_marginal_prices_cross() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(1)), (dec!(2010), dec!(1))]);
+    let sell = dxdy_book(&[(dec!(2005), dec!(1.5)), (dec!(2008), dec!(0.5))], &[]);
+
+    let result = optimal_size(&buy, &no_fees(), &sell, &no_fees(), dec!(100000));
+
+    //0.5 @ 2000 -> 2008, 0.5 @ 2000 -> 2005, next ask 2010 is above every bid
+    assert_eq!(result.quantity, dec!(1));
+    assert_eq!(result.profit, dec!(6.5));
+    assert_eq!(result.curve.len(), 2);
+    assert_eq!(result.curve[1].marginal_sell_price, dec!(2005));
+}
+
+#[test]
+fn step_size_rounding_does_not_end_the_walk() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(2))]);
+    //First bid is not a multiple of AEVO step size
+    let sell = dxdy_book(&[(dec!(2010), dec!(0.125)), (dec!(2009), dec!(1))], &[]);
+
+    let result = optimal_size(&buy, &no_fees(), &sell, &no_fees(), dec!(100000));
+
+    //0.12 @ 2010, then 0.005 left at 2010 is traded with the next level at 2009
+    assert_eq!(result.quantity, dec!(1.12));
+    assert_eq!(result.profit, dec!(0.12) * dec!(10) + dec!(1) * dec!(9));
+    assert_eq!(result.curve.len(), 2);
+}
+
+#[test]
+fn quantity_is_bounded_by_capital_and_order_limit() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(10))]);
+    let sell = dxdy_book(&[(dec!(2010), dec!(10))], &[]);
+
+    let by_capital = optimal_size(&buy, &no_fees(), &sell, &no_fees(), dec!(3000));
+    assert_eq!(by_capital.quantity, dec!(1.5));
+
+    let limited = VenueTerms::new(FeeSchedule::zero(), Some(dec!(0.8)));
+    let by_limit = optimal_size(&buy, &limited, &sell, &no_fees(), dec!(100000));
+    assert_eq!(by_limit.quantity, dec!(0.8));
+}
+
+#[test]
+fn quantity_is_bounded_by_inventory() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(10))]);
+    let sell = dxdy_book(&[(dec!(2010), dec!(10))], &[]);
+
+    //USDC on buy venue caps capital
+    let buy_terms = no_fees().with_inventory(VenueBalance::new(dec!(1000), Decimal::ZERO));
+    let by_usdc = optimal_size(&buy, &buy_terms, &sell, &no_fees(), dec!(100000));
+    assert_eq!(by_usdc.quantity, dec!(0.5));
+
+    //ETH on sell venue caps quantity
+    let sell_terms = no_fees().with_inventory(VenueBalance::new(Decimal::ZERO, dec!(0.3)));
+    let by_eth = optimal_size(&buy, &no_fees(), &sell, &sell_terms, dec!(100000));
+    assert_eq!(by_eth.quantity, dec!(0.3));
+}
+
+#[test]
+fn fees_remove_thin_edge() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(1))]);
+    let sell = dxdy_book(&[(dec!(2001), dec!(1))], &[]);
+    let aevo = VenueTerms::new(FeeSchedule::aevo(), None);
+    let dxdy = VenueTerms::new(FeeSchedule::dxdy(), None);
+
+    let result = optimal_size(&buy, &aevo, &sell, &dxdy, dec!(100000));
+
+    assert!(result.is_empty());
+    assert!(result.curve.is_empty());
+}
+
+#[test]
+fn net_edge_is_measured_on_deployed_notional() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(1))]);
+    let sell = dxdy_book(&[(dec!(2010), dec!(1))], &[]);
+    let aevo = VenueTerms::new(FeeSchedule::zero().with_taker_bps(dec!(5)), None);
+
+    let opportunity = evaluate_direction(&buy, &aevo, &sell, &no_fees(), dec!(100000)).unwrap();
+
+    //Book depth sizes the trade far below capital, 9 profit on 2000 plus 1 fee spent
+    assert_eq!(opportunity.buy.quantity, dec!(1));
+    assert_eq!(opportunity.net_profit, dec!(9));
+    assert_eq!(opportunity.net_edge_bps, dec!(44.98));
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use arbitrage_bot::{
+    calculations::evaluate_direction,
+    fees::FeeSchedule,
+    paper_trading::{PaperConfig, PaperTrader},
+    portfolio::VenueBalance,
+    sizing::VenueTerms,
+    venue::Venue,
+};
+use common::books::{aevo_book, dxdy_book, shared};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn trader(slippage_bps: Decimal, aevo: VenueBalance, dxdy: VenueBalance) -> PaperTrader {
+    let config = PaperConfig {
+        latency_ms: 0,
+        slippage_bps,
+        aevo,
+        dxdy,
+        trade_log: None,
+    };
+    PaperTrader::new(&config, FeeSchedule::zero(), FeeSchedule::zero()).unwrap()
+}
+
+#[test]
+fn trade_moves_assets_between_venues() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(1))]);
+    let sell = dxdy_book(&[(dec!(2010), dec!(1))], &[]);
+    let terms = VenueTerms::new(FeeSchedule::zero(), None);
+    let opportunity = evaluate_direction(&buy, &terms, &sell, &terms, dec!(10000)).unwrap();
+
+    let mut trader = trader(
+        Decimal::ZERO,
+        VenueBalance::new(dec!(5000), Decimal::ZERO),
+        VenueBalance::new(Decimal::ZERO, dec!(2)),
+    );
+    let trade = trader.fill(&opportunity, &buy, &sell).unwrap();
+
+    assert_eq!(trade.buy.quantity, dec!(1));
+    assert_eq!(trade.realized_profit, dec!(10));
+    assert_eq!(
+        trader.balance(Venue::Aevo),
+        VenueBalance::new(dec!(3000), dec!(1))
+    );
+    assert_eq!(
+        trader.balance(Venue::Dxdy),
+        VenueBalance::new(dec!(2010), dec!(1))
+    );
+    assert_eq!(trader.realized_p_l(), dec!(10));
+    assert_eq!(trader.trades().len(), 1);
+}
+
+#[test]
+fn fills_are_limited_by_balances() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(1))]);
+    let sell = dxdy_book(&[(dec!(2010), dec!(1))], &[]);
+    let terms = VenueTerms::new(FeeSchedule::zero(), None);
+    let opportunity = evaluate_direction(&buy, &terms, &sell, &terms, dec!(10000)).unwrap();
+
+    let mut short_of_eth = trader(
+        Decimal::ZERO,
+        VenueBalance::new(dec!(5000), Decimal::ZERO),
+        VenueBalance::new(Decimal::ZERO, dec!(0.3)),
+    );
+    let trade = short_of_eth.fill(&opportunity, &buy, &sell).unwrap();
+    assert_eq!(trade.sell.quantity, dec!(0.3));
+
+    let mut short_of_usdc = trader(
+        Decimal::ZERO,
+        VenueBalance::new(dec!(500), Decimal::ZERO),
+        VenueBalance::new(Decimal::ZERO, dec!(2)),
+    );
+    let trade = short_of_usdc.fill(&opportunity, &buy, &sell).unwrap();
+    assert_eq!(trade.buy.quantity, dec!(0.25));
+}
+
+#[test]
+fn slippage_worsens_both_legs() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(1))]);
+    let sell = dxdy_book(&[(dec!(2010), dec!(1))], &[]);
+    let terms = VenueTerms::new(FeeSchedule::zero(), None);
+    let opportunity = evaluate_direction(&buy, &terms, &sell, &terms, dec!(10000)).unwrap();
+
+    let mut trader = trader(
+        dec!(10),
+        VenueBalance::new(dec!(5000), Decimal::ZERO),
+        VenueBalance::new(Decimal::ZERO, dec!(2)),
+    );
+    let trade = trader.fill(&opportunity, &buy, &sell).unwrap();
+
+    assert_eq!(trade.buy.avg_price, Some(dec!(2002)));
+    assert_eq!(trade.sell.avg_price, Some(dec!(2007.99)));
+    assert_eq!(trade.realized_profit, dec!(5.99));
+    assert!(trade.realized_profit < trade.expected_profit);
+}
+
+#[tokio::test]
+async fn scheduled_trade_fills_after_latency() {
+    let buy = aevo_book(&[], &[(dec!(2000), dec!(1))]);
+    let sell = dxdy_book(&[(dec!(2010), dec!(1))], &[]);
+    let terms = VenueTerms::new(FeeSchedule::zero(), None);
+    let opportunity = evaluate_direction(&buy, &terms, &sell, &terms, dec!(10000)).unwrap();
+
+    let config = PaperConfig {
+        latency_ms: 50,
+        slippage_bps: Decimal::ZERO,
+        aevo: VenueBalance::new(dec!(5000), Decimal::ZERO),
+        dxdy: VenueBalance::new(Decimal::ZERO, dec!(2)),
+        trade_log: None,
+    };
+    let mut trader = PaperTrader::new(&config, FeeSchedule::zero(), FeeSchedule::zero()).unwrap();
+    let (buy, sell) = (shared(buy), shared(sell));
+    trader.schedule(opportunity);
+
+    //Nothing is filled before latency elapsed
+    let trade = trader.execute_due(buy.clone(), sell.clone()).await.unwrap();
+    assert!(trade.is_none());
+    let execute_at = trader.pending().unwrap().execute_at;
+
+    //Books move while the trade waits
+    sell.lock()
+        .await
+        .bids
+        .insert(dec!(2020), (dec!(2020), dec!(1)));
+    tokio::time::sleep_until(execute_at.into()).await;
+    let trade = trader.execute_due(sell, buy).await.unwrap().unwrap();
+
+    assert_eq!(trade.sell.avg_price, Some(dec!(2020)));
+    assert_eq!(trade.realized_profit, dec!(20));
+    assert!(trader.pending().is_none());
+}
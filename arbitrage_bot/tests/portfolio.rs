@@ -0,0 +1,123 @@
+mod common;
+
+use arbitrage_bot::{
+    portfolio::{Fill, Portfolio, VenueBalance},
+    venue::{Side, Venue},
+};
+use common::books::dxdy_book;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+fn fill(venue: Venue, side: Side, quantity: Decimal, price: Decimal, fee: Decimal) -> Fill {
+    Fill {
+        venue,
+        side,
+        quantity,
+        price,
+        fee,
+    }
+}
+
+fn empty_portfolio() -> Portfolio {
+    Portfolio::new([
+        (Venue::Aevo, VenueBalance::new(dec!(10000), Decimal::ZERO)),
+        (Venue::Dxdy, VenueBalance::new(dec!(10000), Decimal::ZERO)),
+    ])
+}
+
+#[test]
+fn reducing_position_realizes_against_average_entry() {
+    let mut portfolio = empty_portfolio();
+    portfolio.apply(&fill(Venue::Aevo, Side::Buy, dec!(1), dec!(2000), dec!(1)));
+    portfolio.apply(&fill(Venue::Aevo, Side::Buy, dec!(1), dec!(2020), dec!(1)));
+
+    let position = portfolio.position(Venue::Aevo);
+    assert_eq!(position.position, dec!(2));
+    assert_eq!(position.avg_entry, Some(dec!(2010)));
+    assert_eq!(position.collateral, dec!(5978));
+
+    portfolio.apply(&fill(
+        Venue::Aevo,
+        Side::Sell,
+        dec!(0.5),
+        dec!(2030),
+        dec!(1),
+    ));
+
+    let position = portfolio.position(Venue::Aevo);
+    assert_eq!(position.position, dec!(1.5));
+    assert_eq!(position.avg_entry, Some(dec!(2010)));
+    //0.5 * (2030 - 2010) minus three fees
+    assert_eq!(position.realized_p_l, dec!(7));
+    assert_eq!(position.fees, dec!(3));
+    assert_eq!(
+        portfolio.available(Venue::Aevo),
+        VenueBalance::new(dec!(6992), dec!(1.5))
+    );
+}
+
+#[test]
+fn flipped_position_opens_at_fill_price() {
+    let mut portfolio = empty_portfolio();
+    portfolio.apply(&fill(
+        Venue::Dxdy,
+        Side::Buy,
+        dec!(1),
+        dec!(2000),
+        Decimal::ZERO,
+    ));
+    portfolio.apply(&fill(
+        Venue::Dxdy,
+        Side::Sell,
+        dec!(3),
+        dec!(1990),
+        Decimal::ZERO,
+    ));
+
+    let position = portfolio.position(Venue::Dxdy);
+    assert_eq!(position.position, dec!(-2));
+    assert_eq!(position.avg_entry, Some(dec!(1990)));
+    assert_eq!(position.realized_p_l, dec!(-10));
+    //Short position can not be sold further
+    assert_eq!(portfolio.available(Venue::Dxdy).base, Decimal::ZERO);
+}
+
+#[test]
+fn open_positions_are_marked_to_mid() {
+    let mut portfolio = empty_portfolio();
+    //Arbitrage leaves long on AEVO and short on dXdY
+    portfolio.apply(&fill(
+        Venue::Aevo,
+        Side::Buy,
+        dec!(1),
+        dec!(2000),
+        Decimal::ZERO,
+    ));
+    portfolio.apply(&fill(
+        Venue::Dxdy,
+        Side::Sell,
+        dec!(1),
+        dec!(2010),
+        Decimal::ZERO,
+    ));
+    assert_eq!(portfolio.net_position(), Decimal::ZERO);
+
+    let book = dxdy_book(&[(dec!(2004), dec!(1))], &[(dec!(2006), dec!(1))]);
+    portfolio.mark_book(&book);
+    portfolio.set_mark(Venue::Aevo, dec!(2001));
+
+    assert_eq!(portfolio.mark(Venue::Dxdy), Some(dec!(2005)));
+    //1 * (2001 - 2000) + -1 * (2005 - 2010)
+    assert_eq!(portfolio.unrealized_p_l(), dec!(6));
+    assert_eq!(portfolio.realized_p_l(), Decimal::ZERO);
+}
+
+#[test]
+fn initial_inventory_is_valued_from_first_mark() {
+    let mut portfolio = Portfolio::new([(Venue::Aevo, VenueBalance::new(dec!(1000), dec!(0.5)))]);
+    portfolio.set_mark(Venue::Aevo, dec!(2000));
+    portfolio.set_mark(Venue::Aevo, dec!(2100));
+
+    assert_eq!(portfolio.position(Venue::Aevo).avg_entry, Some(dec!(2000)));
+    assert_eq!(portfolio.unrealized_p_l(), dec!(50));
+}
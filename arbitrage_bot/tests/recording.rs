@@ -0,0 +1,200 @@
+mod common;
+
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use arbitrage_bot::{
+    aevo::aevo_structs::OrderbookAEVO,
+    dxdy::dxdy_structs::OrderbookDXDY,
+    feed::VenueBooks,
+    orderbook::Orderbook,
+    recording::{recording_segments, spawn_replay, FeedRecorder, FrameReader, MergedFrames},
+    venue::Venue,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use common::books::{aevo_spec, dxdy_spec, shared};
+use rust_decimal_macros::dec;
+
+const AEVO_SNAPSHOT: &str = r#"{"channel":"orderbook:ETH-PERP","data":{"type":"snapshot","instrument_type":"PERPETUAL","bids":[["1999","1","0"]],"asks":[["2000","2","0"]],"last_updated":"1700000000000000000"}}"#;
+const DXDY_SUBSCRIBED: &str = r#"{"type":"subscribed","connection_id":"c","message_id":1,"id":"ETH-USD","contents":{"bids":[{"price":"2010","size":"1"}],"asks":[{"price":"2011","size":"3"}]}}"#;
+const DXDY_UPDATE: &str = r#"{"type":"channel_data","connection_id":"c","message_id":2,"id":"ETH-USD","version":"1","contents":{"asks":[{"price":"2011","size":"0"},{"price":"2012","size":"1"}]}}"#;
+
+fn recording_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("{name}_{}.jsonl.gz", std::process::id()));
+    remove_recording(&path);
+    path
+}
+
+fn remove_recording(path: &Path) {
+    for segment in recording_segments(path) {
+        std::fs::remove_file(segment).unwrap();
+    }
+}
+
+fn record_session(path: &Path) -> DateTime<Utc> {
+    let start = DateTime::from_timestamp_nanos(1_700_000_000_010_000_000);
+    let recorder = FeedRecorder::create(path).unwrap();
+    recorder.record(Venue::Aevo, start, AEVO_SNAPSHOT).unwrap();
+    recorder
+        .record(
+            Venue::Dxdy,
+            start + TimeDelta::milliseconds(500),
+            DXDY_SUBSCRIBED,
+        )
+        .unwrap();
+    start
+}
+
+#[test]
+fn sessions_are_read_as_one_recording() {
+    let path = recording_path("recording_sessions");
+    let start = record_session(&path);
+    FeedRecorder::create(&path)
+        .unwrap()
+        .record(Venue::Dxdy, start + TimeDelta::seconds(1), DXDY_UPDATE)
+        .unwrap();
+    assert_eq!(recording_segments(&path).len(), 2);
+
+    let frames: Vec<_> = FrameReader::open(&path)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0].venue, Venue::Aevo);
+    assert_eq!(frames[0].text, AEVO_SNAPSHOT);
+    assert_eq!(frames[0].received_time, start);
+    assert_eq!(frames[2].text, DXDY_UPDATE);
+    remove_recording(&path);
+}
+
+#[test]
+fn unfinished_session_is_read_up_to_its_last_frame() {
+    let path = recording_path("recording_crash");
+    let start = DateTime::from_timestamp_nanos(1_700_000_000_010_000_000);
+    let crashed = FeedRecorder::create(&path).unwrap();
+    crashed.record(Venue::Aevo, start, AEVO_SNAPSHOT).unwrap();
+    crashed.finish().unwrap();
+    //Crash leaves the gzip member without its 8 byte trailer
+    let written = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &written[..written.len() - 8]).unwrap();
+
+    let recorder = FeedRecorder::create(&path).unwrap();
+    recorder
+        .record(Venue::Dxdy, start + TimeDelta::seconds(1), DXDY_UPDATE)
+        .unwrap();
+    recorder.finish().unwrap();
+
+    let frames: Vec<_> = FrameReader::open(&path)
+        .unwrap()
+        .map(|frame| frame.unwrap().text)
+        .collect();
+
+    assert_eq!(frames, [AEVO_SNAPSHOT, DXDY_UPDATE]);
+    remove_recording(&path);
+}
+
+#[test]
+fn finish_writes_every_queued_frame() {
+    let path = recording_path("recording_queue");
+    let start = DateTime::from_timestamp_nanos(1_700_000_000_010_000_000);
+    let recorder = FeedRecorder::create(&path).unwrap();
+    let feed = recorder.clone();
+    for offset in 0..100 {
+        feed.record(
+            Venue::Dxdy,
+            start + TimeDelta::milliseconds(offset),
+            DXDY_UPDATE,
+        )
+        .unwrap();
+    }
+
+    //Clones still held by feeds do not keep the segment open
+    recorder.finish().unwrap();
+    feed.record(Venue::Dxdy, start, DXDY_UPDATE).unwrap();
+
+    let frames = FrameReader::open(&path).unwrap().count();
+    assert_eq!(frames, 100);
+    remove_recording(&path);
+}
+
+#[test]
+fn reading_stops_after_first_error() {
+    let path = recording_path("recording_corrupt");
+    std::fs::write(&path, "not a gzip recording").unwrap();
+
+    let mut frames = FrameReader::open(&path).unwrap();
+
+    assert!(frames.next().unwrap().is_err());
+    assert!(frames.next().is_none());
+    remove_recording(&path);
+}
+
+#[test]
+fn recordings_are_merged_in_receive_order() {
+    let first = recording_path("recording_merge_first");
+    let second = recording_path("recording_merge_second");
+    let start = record_session(&first);
+    FeedRecorder::create(&second)
+        .unwrap()
+        .record(
+            Venue::Dxdy,
+            start + TimeDelta::milliseconds(200),
+            DXDY_UPDATE,
+        )
+        .unwrap();
+
+    let frames: Vec<_> = MergedFrames::open(&[first.clone(), second.clone()])
+        .unwrap()
+        .map(|frame| frame.unwrap().text)
+        .collect();
+
+    assert_eq!(frames, [AEVO_SNAPSHOT, DXDY_UPDATE, DXDY_SUBSCRIBED]);
+    remove_recording(&first);
+    remove_recording(&second);
+}
+
+#[tokio::test]
+async fn replay_applies_frames_to_books() {
+    let path = recording_path("recording_replay");
+    let start = record_session(&path);
+    FeedRecorder::create(&path)
+        .unwrap()
+        .record(Venue::Dxdy, start + TimeDelta::seconds(1), DXDY_UPDATE)
+        .unwrap();
+
+    let aevo = shared(OrderbookAEVO::new(aevo_spec()));
+    let dxdy = shared(OrderbookDXDY::new(dxdy_spec()));
+
+    //One second of recording at 20x speed
+    let replay_start = Instant::now();
+    let mut replay = spawn_replay(
+        &path,
+        20.0,
+        VenueBooks::single("ETH-PERP", aevo.clone()),
+        VenueBooks::single("ETH-USD", dxdy.clone()),
+    )
+    .unwrap();
+    assert_eq!(replay.handle.await.unwrap().unwrap(), 3);
+    assert!(replay_start.elapsed().as_millis() >= 50);
+    //Update channels close once the recording ends
+    replay.aevo_updates.borrow_and_update();
+    assert!(replay.aevo_updates.changed().await.is_err());
+    assert_eq!(replay.dxdy_updates.borrow().unwrap().sequence, 2);
+
+    let aevo = aevo.lock().await;
+    assert!(!aevo.is_stale());
+    assert_eq!(aevo.best_ask(), Some((dec!(2000), dec!(2))));
+    //Recorded receive time is kept, so feed latency matches the live run
+    assert_eq!(
+        aevo.timestamps().feed_latency(),
+        Some(std::time::Duration::from_millis(10))
+    );
+
+    let dxdy = dxdy.lock().await;
+    assert_eq!(dxdy.best_ask(), Some((dec!(2012), dec!(1))));
+    assert_eq!(dxdy.best_bid(), Some((dec!(2010), dec!(1))));
+    remove_recording(&path);
+}
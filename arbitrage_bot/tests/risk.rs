@@ -0,0 +1,181 @@
+mod common;
+
+use std::time::Duration;
+
+use arbitrage_bot::{
+    calculations::{evaluate_direction, ArbitrageOpportunity},
+    fees::FeeSchedule,
+    orderbook::BookIntegrity,
+    portfolio::{Fill, Portfolio, VenueBalance},
+    risk::{BookState, KillSwitch, RiskConfig, RiskManager, RiskViolation},
+    sizing::VenueTerms,
+    venue::{Side, Venue},
+};
+use common::books::{aevo_book, dxdy_book};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+
+///Buys 1 on AEVO at 2000 and sells it on dXdY at 2010
+fn opportunity() -> ArbitrageOpportunity {
+    let aevo = aevo_book(&[], &[(dec!(2000), dec!(1))]);
+    let dxdy = dxdy_book(&[(dec!(2010), dec!(1))], &[]);
+
+    let terms = VenueTerms::new(FeeSchedule::zero(), None);
+    evaluate_direction(&aevo, &terms, &dxdy, &terms, dec!(4000)).unwrap()
+}
+
+fn portfolio() -> Portfolio {
+    Portfolio::new([
+        (Venue::Aevo, VenueBalance::new(dec!(10000), Decimal::ZERO)),
+        (Venue::Dxdy, VenueBalance::new(dec!(10000), dec!(1))),
+    ])
+}
+
+fn healthy_books() -> [BookState; 2] {
+    [Venue::Aevo, Venue::Dxdy].map(|venue| BookState {
+        venue,
+        age: Some(Duration::from_millis(100)),
+        integrity: BookIntegrity::Healthy,
+    })
+}
+
+fn manager(config: RiskConfig) -> RiskManager {
+    RiskManager::new(config, KillSwitch::default())
+}
+
+#[test]
+fn default_limits_pass_healthy_trade() {
+    let mut risk = manager(RiskConfig::default());
+
+    assert_eq!(
+        risk.check(&opportunity(), &portfolio(), &healthy_books()),
+        Ok(())
+    );
+}
+
+#[test]
+fn broken_or_old_books_are_not_traded() {
+    let mut risk = manager(RiskConfig::default());
+
+    let mut books = healthy_books();
+    books[1].integrity = BookIntegrity::Crossed {
+        best_bid: dec!(2010),
+        best_ask: dec!(2005),
+    };
+    assert!(matches!(
+        risk.check(&opportunity(), &portfolio(), &books),
+        Err(RiskViolation::BrokenBook {
+            venue: Venue::Dxdy,
+            ..
+        })
+    ));
+
+    let mut books = healthy_books();
+    books[0].age = Some(Duration::from_secs(11));
+    assert!(matches!(
+        risk.check(&opportunity(), &portfolio(), &books),
+        Err(RiskViolation::StaleBook {
+            venue: Venue::Aevo,
+            ..
+        })
+    ));
+
+    books[0].age = None;
+    assert!(risk.check(&opportunity(), &portfolio(), &books).is_err());
+}
+
+#[test]
+fn trade_limits_are_enforced() {
+    let mut risk = manager(RiskConfig {
+        min_edge_bps: dec!(60),
+        ..RiskConfig::default()
+    });
+    //10 profit on 2000 spent on the buy leg is 50 bps, unused capital does not dilute it
+    assert_eq!(
+        risk.check(&opportunity(), &portfolio(), &healthy_books()),
+        Err(RiskViolation::EdgeTooLow {
+            edge: dec!(50),
+            min: dec!(60)
+        })
+    );
+
+    let mut risk = manager(RiskConfig {
+        max_trade_notional: Some(dec!(1500)),
+        ..RiskConfig::default()
+    });
+    assert_eq!(
+        risk.check(&opportunity(), &portfolio(), &healthy_books()),
+        Err(RiskViolation::NotionalLimit {
+            venue: Venue::Aevo,
+            notional: dec!(2000),
+            limit: dec!(1500)
+        })
+    );
+
+    let mut risk = manager(RiskConfig {
+        max_position: Some(dec!(1.5)),
+        ..RiskConfig::default()
+    });
+    let mut portfolio = portfolio();
+    portfolio.apply(&Fill {
+        venue: Venue::Aevo,
+        side: Side::Buy,
+        quantity: dec!(1),
+        price: dec!(2000),
+        fee: Decimal::ZERO,
+    });
+    assert_eq!(
+        risk.check(&opportunity(), &portfolio, &healthy_books()),
+        Err(RiskViolation::PositionLimit {
+            venue: Venue::Aevo,
+            position: dec!(2),
+            limit: dec!(1.5)
+        })
+    );
+}
+
+#[test]
+fn daily_loss_blocks_further_trades() {
+    let mut risk = manager(RiskConfig {
+        max_daily_loss: Some(dec!(50)),
+        ..RiskConfig::default()
+    });
+    let mut portfolio = portfolio();
+    portfolio.set_mark(Venue::Dxdy, dec!(2000));
+    assert_eq!(
+        risk.check(&opportunity(), &portfolio, &healthy_books()),
+        Ok(())
+    );
+
+    //Inventory of 1 ETH loses 60 in value
+    portfolio.set_mark(Venue::Dxdy, dec!(1940));
+    assert_eq!(
+        risk.check(&opportunity(), &portfolio, &healthy_books()),
+        Err(RiskViolation::DailyLoss {
+            loss: dec!(60),
+            limit: dec!(50)
+        })
+    );
+}
+
+#[test]
+fn kill_switch_file_halts_trading() {
+    let file = std::env::temp_dir().join(format!("kill_switch_{}", std::process::id()));
+    let _ = std::fs::remove_file(&file);
+    let kill_switch = KillSwitch::new(Some(file.clone()));
+    let mut risk = RiskManager::new(RiskConfig::default(), kill_switch.clone());
+
+    assert_eq!(
+        risk.check(&opportunity(), &portfolio(), &healthy_books()),
+        Ok(())
+    );
+
+    std::fs::write(&file, "").unwrap();
+    assert!(kill_switch.is_engaged());
+    //Engagement is kept after the file is gone
+    std::fs::remove_file(&file).unwrap();
+    assert_eq!(
+        risk.check(&opportunity(), &portfolio(), &healthy_books()),
+        Err(RiskViolation::KillSwitch)
+    );
+}
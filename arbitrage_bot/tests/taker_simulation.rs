@@ -0,0 +1,85 @@
+mod common;
+
+use arbitrage_bot::{
+    dxdy::dxdy_structs::OrderbookDXDY,
+    orderbook::{Orderbook, TakerFill},
+};
+use common::books::dxdy_book;
+use rust_decimal_macros::dec;
+
+fn sample_book() -> OrderbookDXDY {
+    dxdy_book(
+        &[(dec!(1998), dec!(1)), (dec!(1999), dec!(0.5))],
+        &[(dec!(2001), dec!(0.5)), (dec!(2002), dec!(1))],
+    )
+}
+
+#[test]
+fn buy_consumes_asks_ascending() {
+    let fill = sample_book().simulate_buy(dec!(3003));
+
+    //0.5 @ 2001 = 1000.5, remaining 2002.5 buys 1.000 @ 2002
+    assert_eq!(fill.filled_qty, dec!(1.5));
+    assert_eq!(fill.notional, dec!(3002.5));
+    assert_eq!(fill.worst_price, Some(dec!(2002)));
+    assert_eq!(fill.levels_consumed, 2);
+    assert_eq!(fill.avg_price, Some(dec!(3002.5) / dec!(1.5)));
+}
+
+#[test]
+fn partial_buy_is_rounded_down_to_step_size() {
+    let fill = sample_book().simulate_buy(dec!(100));
+
+    //100 / 2001 = 0.04997..., floored to 0.049
+    assert_eq!(fill.filled_qty, dec!(0.049));
+    assert_eq!(fill.notional, dec!(0.049) * dec!(2001));
+    assert_eq!(fill.worst_price, Some(dec!(2001)));
+    assert_eq!(fill.levels_consumed, 1);
+}
+
+#[test]
+fn sell_consumes_bids_descending() {
+    let fill = sample_book().simulate_sell(dec!(1));
+
+    assert_eq!(fill.filled_qty, dec!(1));
+    assert_eq!(
+        fill.notional,
+        dec!(0.5) * dec!(1999) + dec!(0.5) * dec!(1998)
+    );
+    assert_eq!(fill.avg_price, Some(dec!(1998.5)));
+    assert_eq!(fill.worst_price, Some(dec!(1998)));
+    assert_eq!(fill.levels_consumed, 2);
+}
+
+#[test]
+fn buy_quantity_stops_at_requested_size() {
+    let fill = sample_book().simulate_buy_quantity(dec!(0.5));
+
+    assert_eq!(fill.filled_qty, dec!(0.5));
+    assert_eq!(fill.worst_price, Some(dec!(2001)));
+    assert_eq!(fill.levels_consumed, 1);
+}
+
+#[test]
+fn fill_is_limited_by_book_depth() {
+    let fill = sample_book().simulate_sell(dec!(10));
+
+    assert_eq!(fill.filled_qty, dec!(1.5));
+    assert_eq!(fill.levels_consumed, 2);
+}
+
+#[test]
+fn empty_book_fills_nothing() {
+    let fill = dxdy_book(&[], &[]).simulate_buy(dec!(1000));
+
+    assert_eq!(fill, TakerFill::default());
+    assert!(fill.is_empty());
+}
+
+#[test]
+fn round_trip_helpers_use_taker_sides() {
+    let book = sample_book();
+
+    assert_eq!(book.buy_as_much_as_possible(dec!(1000.5)), dec!(0.5));
+    assert_eq!(book.sell_as_much_as_possible(dec!(0.5)), dec!(999.5));
+}